@@ -14,6 +14,10 @@ fn main() {
         .expect("Unable to generate bindings")
         .write_to_file("include/bindings.h");
 
+    #[cfg(feature = "uniffi")]
+    uniffi::generate_scaffolding("src/meesign_crypto.udl")
+        .expect("Unable to generate UniFFI scaffolding");
+
     prost_build::compile_protos(&["proto/meesign.proto"], &["proto/"])
         .expect("Unable to compile protos:");
 }
@@ -0,0 +1,69 @@
+plugins {
+    kotlin("jvm") version "1.9.22"
+    id("com.google.protobuf") version "0.9.4"
+}
+
+repositories {
+    mavenCentral()
+}
+
+val crateDir: File = rootDir.parentFile
+val features = "uniffi,frost,elgamal,musig2"
+val generatedDir = layout.buildDirectory.dir("generated/uniffi")
+
+dependencies {
+    implementation("net.java.dev.jna:jna:5.14.0")
+    testImplementation(kotlin("test"))
+    testImplementation("com.google.protobuf:protobuf-java:3.25.2")
+    testImplementation("org.bouncycastle:bcpkix-jdk18on:1.77")
+}
+
+sourceSets {
+    main {
+        kotlin.srcDir(generatedDir)
+    }
+    test {
+        proto {
+            srcDir(crateDir.resolve("proto"))
+        }
+    }
+}
+
+protobuf {
+    protoc {
+        artifact = "com.google.protobuf:protoc:3.25.2"
+    }
+}
+
+val cargoBuild by tasks.registering(Exec::class) {
+    workingDir = crateDir
+    commandLine("cargo", "build", "--release", "--no-default-features", "--features", features)
+}
+
+val generateBindings by tasks.registering(Exec::class) {
+    workingDir = crateDir
+    inputs.file(crateDir.resolve("src/meesign_crypto.udl"))
+    inputs.file(crateDir.resolve("uniffi.toml"))
+    outputs.dir(generatedDir)
+    commandLine(
+        "cargo", "run", "--release", "--no-default-features", "--features", features,
+        "--bin", "uniffi-bindgen", "--",
+        "generate", "src/meesign_crypto.udl",
+        "--language", "kotlin",
+        "--out-dir", generatedDir.get().asFile.path,
+    )
+}
+
+tasks.compileKotlin {
+    dependsOn(generateBindings)
+}
+
+tasks.test {
+    dependsOn(cargoBuild)
+    useJUnitPlatform()
+    systemProperty("jna.library.path", crateDir.resolve("target/release").path)
+}
+
+kotlin {
+    jvmToolchain(17)
+}
@@ -0,0 +1,165 @@
+package cz.muni.fi.meesign.crypto
+
+import com.google.protobuf.ByteString
+import java.math.BigInteger
+import java.security.KeyPairGenerator
+import java.security.spec.ECGenParameterSpec
+import java.util.Date
+import kotlin.test.Test
+import kotlin.test.assertContentEquals
+import kotlin.test.assertEquals
+import kotlin.test.assertFailsWith
+import kotlin.test.assertTrue
+import meesign.Meesign
+import org.bouncycastle.asn1.ASN1ObjectIdentifier
+import org.bouncycastle.asn1.pkcs.PKCSObjectIdentifiers
+import org.bouncycastle.asn1.x500.X500Name
+import org.bouncycastle.asn1.x509.Extensions
+import org.bouncycastle.cert.X509v3CertificateBuilder
+import org.bouncycastle.operator.jcajce.JcaContentSignerBuilder
+import org.bouncycastle.pkcs.PKCS10CertificationRequest
+
+private val BUNDLE_OID = ASN1ObjectIdentifier("1.3.6.1.5.5.7.13.9939")
+
+/** Runs whole sessions in-process, with the test standing in for the server */
+class SessionTest {
+    private class Identities(val certs: Map<Int, ByteArray>, val pkcs12s: Map<Int, ByteArray>)
+
+    /** Issues a certificate for each party */
+    private fun issue(indices: List<Int>): Identities {
+        val ca = KeyPairGenerator.getInstance("EC")
+            .apply { initialize(ECGenParameterSpec("secp256r1")) }
+            .generateKeyPair()
+        val caName = X500Name("CN=meesign test ca")
+        val signer = JcaContentSignerBuilder("SHA256withECDSA").build(ca.private)
+        val now = System.currentTimeMillis()
+
+        val certs = mutableMapOf<Int, ByteArray>()
+        val pkcs12s = mutableMapOf<Int, ByteArray>()
+        for (index in indices) {
+            val key = authKeygen("party $index", CipherSuite.P256_ECIES, false)
+            val csr = PKCS10CertificationRequest(key.csr)
+            val request = csr.getAttributes(PKCSObjectIdentifiers.pkcs_9_at_extensionRequest).single()
+            val bundle = Extensions.getInstance(request.attrValues.getObjectAt(0)).getExtension(BUNDLE_OID)
+            val cert = X509v3CertificateBuilder(
+                caName,
+                BigInteger.valueOf(index.toLong() + 1),
+                Date(now),
+                Date(now + 3600_000),
+                csr.subject,
+                csr.subjectPublicKeyInfo,
+            ).addExtension(bundle).build(signer).encoded
+            certs[index] = cert
+            pkcs12s[index] = authCertKeyToPkcs12(key.key, cert)
+        }
+        return Identities(certs, pkcs12s)
+    }
+
+    private fun roster(certs: Map<Int, ByteArray>): ByteArray =
+        Meesign.ServerMessage.newBuilder()
+            .putAllBroadcasts(certs.mapValues { ByteString.copyFrom(it.value) })
+            .build()
+            .toByteArray()
+
+    /** Relays the messages of all parties like the server */
+    private fun route(protocolType: Meesign.ProtocolType, outputs: Map<Int, ByteArray>): Map<Int, ByteArray> {
+        val decoded = outputs.mapValues { Meesign.ClientMessage.parseFrom(it.value) }
+        return outputs.keys.associateWith { index ->
+            val message = Meesign.ServerMessage.newBuilder().setProtocolType(protocolType)
+            for ((sender, sent) in decoded) {
+                if (sender == index) continue
+                sent.unicastsMap[index]?.let { message.putUnicasts(sender, it) }
+                if (sent.hasBroadcast()) message.putBroadcasts(sender, sent.broadcast)
+                if (sent.hasAbort()) message.putAborts(sender, sent.abort)
+            }
+            message.build().toByteArray()
+        }
+    }
+
+    /** Advances all parties until they finish, passing each state through serialization */
+    private fun run(
+        protocols: MutableMap<Int, Protocol>,
+        inputs: Map<Int, ByteArray>,
+        protocolType: Meesign.ProtocolType,
+    ): Map<Int, List<ByteArray>> {
+        var outputs = inputs.toMutableMap()
+        while (!protocols.values.all { it.status(0u).finished }) {
+            for (index in protocols.keys) {
+                val protocol = protocols.getValue(index).use { Protocol.deserialize(it.serialize()) }
+                val response = protocol.advance(0u, outputs.getValue(index))
+                assertEquals(Recipient.SERVER, response.recipient)
+                outputs[index] = response.data
+                protocols[index] = protocol
+            }
+            outputs = route(protocolType, outputs).toMutableMap()
+        }
+        return protocols.mapValues { (_, protocol) -> protocol.use { it.finish().results } }
+    }
+
+    private fun frostKeygen(indices: List<Int>, threshold: Int, identities: Identities): Map<Int, List<ByteArray>> {
+        val roster = roster(identities.certs)
+        val protocols = indices.associateWith {
+            Protocol.keygen(ProtocolId.FROST, roster, identities.pkcs12s.getValue(it), 1u, false)
+        }.toMutableMap()
+        protocols.values.forEach { it.advance(0u, ByteArray(0)) }
+
+        val inits = indices.associateWith {
+            Meesign.ProtocolGroupInit.newBuilder()
+                .setProtocolType(Meesign.ProtocolType.FROST)
+                .setIndex(it)
+                .setParties(indices.size)
+                .setThreshold(threshold)
+                .build()
+                .toByteArray()
+        }
+        return run(protocols, inits, Meesign.ProtocolType.FROST)
+    }
+
+    @Test
+    fun frostSession() {
+        val indices = listOf(1, 2, 3)
+        val identities = issue(indices)
+        val groups = frostKeygen(indices, 2, identities)
+
+        val signers = listOf(1, 3)
+        val roster = roster(identities.certs.filterKeys { it in signers })
+        val protocols = signers.associateWith {
+            Protocol.init(groups.getValue(it), roster, identities.pkcs12s.getValue(it), ProtocolId.FROST)
+        }.toMutableMap()
+        val inits = signers.associateWith {
+            Meesign.ProtocolInit.newBuilder()
+                .setProtocolType(Meesign.ProtocolType.FROST)
+                .addAllIndices(signers)
+                .setIndex(it)
+                .setData(ByteString.copyFromUtf8("hello"))
+                .build()
+                .toByteArray()
+        }
+        val signatures = run(protocols, inits, Meesign.ProtocolType.FROST)
+        assertContentEquals(signatures.getValue(1).single(), signatures.getValue(3).single())
+    }
+
+    @Test
+    fun errors() {
+        val indices = listOf(1, 2)
+        val identities = issue(indices)
+        val groups = frostKeygen(indices, 2, identities)
+        val roster = roster(identities.certs)
+        val group = groups.getValue(1)
+        val pkcs12 = identities.pkcs12s.getValue(1)
+
+        assertFailsWith<MeeSignException.Misuse> {
+            Protocol.init(group, roster, pkcs12, ProtocolId.ELGAMAL)
+        }
+
+        Protocol.init(group, roster, pkcs12, null).use { protocol ->
+            assertFailsWith<MeeSignException.MalformedInput> { protocol.advance(0u, byteArrayOf(-1)) }
+            assertEquals("Init", protocol.status(0u).state)
+            assertTrue(protocol.abort(0u, AbortReason.ERROR).isNotEmpty())
+            assertEquals("Aborted", protocol.status(0u).state)
+            assertFailsWith<MeeSignException.Misuse> { protocol.advance(1u, ByteArray(0)) }
+        }
+
+        assertFailsWith<MeeSignException> { Protocol.deserialize("{".toByteArray()) }
+    }
+}
@@ -0,0 +1,238 @@
+"""Runs whole sessions in-process, with this module standing in for the server."""
+
+import datetime
+
+import pytest
+from cryptography import x509
+from cryptography.hazmat.primitives import hashes
+from cryptography.hazmat.primitives.asymmetric import ec
+from cryptography.hazmat.primitives.serialization import Encoding
+from cryptography.x509.oid import NameOID
+
+import meesign_crypto as mc
+
+BUNDLE_OID = x509.ObjectIdentifier("1.3.6.1.5.5.7.13.9939")
+ELGAMAL = 1
+FROST = 2
+
+
+def varint(value):
+    out = bytearray()
+    while value > 0x7F:
+        out.append(value & 0x7F | 0x80)
+        value >>= 7
+    out.append(value)
+    return bytes(out)
+
+
+def uint_field(number, value):
+    return varint(number << 3) + varint(value) if value else b""
+
+
+def bytes_field(number, value):
+    return varint(number << 3 | 2) + varint(len(value)) + value
+
+
+def map_field(number, entries):
+    return b"".join(
+        bytes_field(number, uint_field(1, key) + bytes_field(2, value))
+        for key, value in sorted(entries.items())
+    )
+
+
+def read_varint(data, pos):
+    value, shift = 0, 0
+    while True:
+        byte = data[pos]
+        pos += 1
+        value |= (byte & 0x7F) << shift
+        shift += 7
+        if not byte & 0x80:
+            return value, pos
+
+
+def fields(data):
+    pos = 0
+    while pos < len(data):
+        key, pos = read_varint(data, pos)
+        if key & 7 == 0:
+            value, pos = read_varint(data, pos)
+        else:
+            length, pos = read_varint(data, pos)
+            value, pos = data[pos : pos + length], pos + length
+        yield key >> 3, value
+
+
+def server_message(protocol_type, unicasts={}, broadcasts={}, aborts={}):
+    return (
+        uint_field(1, protocol_type)
+        + map_field(2, unicasts)
+        + map_field(3, broadcasts)
+        + map_field(4, aborts)
+    )
+
+
+def client_message(data):
+    """Decodes the unicasts, broadcast and abort of a `ClientMessage`"""
+    unicasts, broadcast, abort = {}, None, None
+    for number, value in fields(data):
+        if number == 2:
+            entry = dict(fields(value))
+            unicasts[entry.get(1, 0)] = entry.get(2, b"")
+        elif number == 3:
+            broadcast = value
+        elif number == 4:
+            abort = value
+    return unicasts, broadcast, abort
+
+
+def route(protocol_type, outputs):
+    """Relays the messages of all parties like the server"""
+    decoded = {index: client_message(output) for index, output in outputs.items()}
+    routed = {}
+    for index in outputs:
+        unicasts, broadcasts, aborts = {}, {}, {}
+        for sender, (sent_unicasts, broadcast, abort) in decoded.items():
+            if sender == index:
+                continue
+            if index in sent_unicasts:
+                unicasts[sender] = sent_unicasts[index]
+            if broadcast is not None:
+                broadcasts[sender] = broadcast
+            if abort is not None:
+                aborts[sender] = abort
+        routed[index] = server_message(protocol_type, unicasts, broadcasts, aborts)
+    return routed
+
+
+def issue(indices):
+    """Issues a certificate for each party, returning the certificates and PKCS #12 bundles"""
+    ca_key = ec.generate_private_key(ec.SECP256R1())
+    ca_name = x509.Name([x509.NameAttribute(NameOID.COMMON_NAME, "meesign test ca")])
+    now = datetime.datetime.now(datetime.timezone.utc)
+    certs, pkcs12s = {}, {}
+    for index in indices:
+        key, csr = mc.auth_keygen(f"party {index}")
+        csr = x509.load_der_x509_csr(csr)
+        bundle = csr.extensions.get_extension_for_oid(BUNDLE_OID).value
+        cert = (
+            x509.CertificateBuilder()
+            .subject_name(csr.subject)
+            .issuer_name(ca_name)
+            .public_key(csr.public_key())
+            .serial_number(index + 1)
+            .not_valid_before(now)
+            .not_valid_after(now + datetime.timedelta(hours=1))
+            .add_extension(bundle, critical=False)
+            .sign(ca_key, hashes.SHA256())
+            .public_bytes(Encoding.DER)
+        )
+        certs[index] = cert
+        pkcs12s[index] = mc.auth_cert_key_to_pkcs12(key, cert)
+    return certs, pkcs12s
+
+
+def run(protocols, inputs, protocol_type):
+    """Advances all parties until they finish, passing each state through serialization.
+    Returns the results of all parties together with their final messages"""
+    outputs = inputs
+    while not all(protocol.status(0).finished for protocol in protocols.values()):
+        sent = {}
+        for index in protocols:
+            protocol = mc.Protocol.deserialize(protocols[index].serialize())
+            sent[index], recipient = protocol.advance(0, outputs[index])
+            assert recipient == mc.Recipient.Server
+            protocols[index] = protocol
+        outputs = route(protocol_type, sent)
+    return {index: protocol.finish() for index, protocol in protocols.items()}, sent
+
+
+def keygen(protocol_id, protocol_type, indices, threshold, certs, pkcs12s):
+    roster = server_message(0, broadcasts=certs)
+    protocols = {
+        index: mc.Protocol.keygen(protocol_id, roster, pkcs12s[index])
+        for index in indices
+    }
+    for index, protocol in protocols.items():
+        protocol.advance(0, b"")
+
+    inits = {
+        index: uint_field(1, protocol_type)
+        + uint_field(2, index)
+        + uint_field(3, len(indices))
+        + uint_field(4, threshold)
+        for index in indices
+    }
+    return run(protocols, inits, protocol_type)
+
+
+def frost_keygen(indices, threshold, certs, pkcs12s):
+    groups, _ = keygen(mc.ProtocolId.Frost, FROST, indices, threshold, certs, pkcs12s)
+    return groups
+
+
+def threshold_inits(protocol_type, indices, data):
+    return {
+        index: uint_field(1, protocol_type)
+        + bytes_field(2, b"".join(varint(other) for other in indices))
+        + uint_field(3, index)
+        + bytes_field(4, data)
+        for index in indices
+    }
+
+
+def test_frost_session():
+    indices = [1, 2, 3]
+    certs, pkcs12s = issue(indices)
+    groups = frost_keygen(indices, 2, certs, pkcs12s)
+
+    signers = [1, 3]
+    roster = server_message(0, broadcasts={index: certs[index] for index in signers})
+    protocols = {
+        index: mc.Protocol.init(groups[index], roster, pkcs12s[index])
+        for index in signers
+    }
+    signatures, _ = run(protocols, threshold_inits(FROST, signers, b"hello"), FROST)
+    assert signatures[1] == signatures[3]
+
+
+def test_elgamal_session():
+    indices = [0, 1, 2]
+    certs, pkcs12s = issue(indices)
+    groups, final = keygen(mc.ProtocolId.Elgamal, ELGAMAL, indices, 2, certs, pkcs12s)
+    # NOTE: the final keygen message broadcasts the group key in a `SignedMessage`
+    _, broadcast, _ = client_message(final[0])
+    key = dict(fields(broadcast))[1]
+    ciphertext = mc.encrypt(b"hello", key)
+
+    decryptors = [0, 2]
+    roster = server_message(0, broadcasts={index: certs[index] for index in decryptors})
+    protocols = {
+        index: mc.Protocol.init(groups[index], roster, pkcs12s[index])
+        for index in decryptors
+    }
+    inits = threshold_inits(ELGAMAL, decryptors, ciphertext)
+    plaintexts, _ = run(protocols, inits, ELGAMAL)
+    assert plaintexts == {0: [b"hello"], 2: [b"hello"]}
+
+
+def test_errors():
+    indices = [1, 2]
+    certs, pkcs12s = issue(indices)
+    groups = frost_keygen(indices, 2, certs, pkcs12s)
+    roster = server_message(0, broadcasts=certs)
+
+    with pytest.raises(mc.MisuseError):
+        mc.Protocol.init(groups[1], roster, pkcs12s[1], protocol=mc.ProtocolId.Elgamal)
+
+    protocol = mc.Protocol.init(groups[1], roster, pkcs12s[1])
+    with pytest.raises(mc.MalformedInputError):
+        protocol.advance(0, b"\xff")
+    assert protocol.status(0).state == "Init"
+    assert protocol.abort(0, mc.AbortReason.Error)
+    assert protocol.status(0).state == "Aborted"
+
+    with pytest.raises(mc.MeeSignError):
+        mc.Protocol.deserialize(b"{")
+    with pytest.raises(mc.MisuseError):
+        protocol.advance(1, b"")
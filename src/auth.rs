@@ -1,10 +1,14 @@
+use crate::error::Error;
+use crate::hybrid::{self, PqVerifyingKey};
 use crate::proto::SignedMessage;
+use crate::suite::{CipherSuite, VerifyingKey};
 use const_oid::{AssociatedOid, ObjectIdentifier};
 use der::{Decode, Encode};
-use p256::ecdsa::{self, signature::Verifier as _, DerSignature, SigningKey};
-use p256::pkcs8::{DecodePublicKey, EncodePrivateKey, EncodePublicKey};
+use p256::ecdsa::{DerSignature, SigningKey};
+use p256::pkcs8::EncodePrivateKey;
 use rand::rngs::OsRng;
-use std::{error::Error, str::FromStr};
+use std::convert::TryFrom;
+use std::str::FromStr;
 use x509_cert::{
     builder::{Builder, RequestBuilder},
     ext::{AsExtension, Extension},
@@ -12,14 +16,24 @@ use x509_cert::{
     Certificate,
 };
 use yasna;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 /// A bundle of private keys corresponding to `MeeSignPublicBundle`
 /// stored inside of a PKCS#12 SecretBag
-#[derive(der::Sequence)]
+#[derive(der::Sequence, Zeroize, ZeroizeOnDrop)]
 pub struct MeeSignPrivateBundle {
     pub broadcast_sign: Vec<u8>,
     pub unicast_sign: Vec<u8>,
     pub unicast_decrypt: Vec<u8>,
+    /// The `CipherSuite` of the keys, omitted for the default one
+    #[asn1(default = "Default::default")]
+    pub suite: u8,
+    /// The ML-DSA key co-signing broadcasts, present only in hybrid mode
+    #[asn1(context_specific = "0", optional = "true")]
+    pub pq_broadcast_sign: Option<Vec<u8>>,
+    /// The ML-KEM key wrapping unicasts, present only in hybrid mode
+    #[asn1(context_specific = "1", optional = "true")]
+    pub pq_unicast_decrypt: Option<Vec<u8>>,
 }
 
 impl MeeSignPrivateBundle {
@@ -33,6 +47,15 @@ pub struct MeeSignPublicBundle {
     pub broadcast_sign: Vec<u8>,
     pub unicast_sign: Vec<u8>,
     pub unicast_encrypt: Vec<u8>,
+    /// The `CipherSuite` of the keys, omitted for the default one
+    #[asn1(default = "Default::default")]
+    pub suite: u8,
+    /// The ML-DSA key verifying co-signatures of broadcasts, present only in hybrid mode
+    #[asn1(context_specific = "0", optional = "true")]
+    pub pq_broadcast_sign: Option<Vec<u8>>,
+    /// The ML-KEM key wrapping unicasts, present only in hybrid mode
+    #[asn1(context_specific = "1", optional = "true")]
+    pub pq_unicast_encrypt: Option<Vec<u8>>,
 }
 
 /// An OID from a testing namespace as documented here:
@@ -47,49 +70,72 @@ impl AsExtension for MeeSignPublicBundle {
     }
 }
 
-#[derive(der::Sequence)]
+#[derive(der::Sequence, Zeroize, ZeroizeOnDrop)]
 struct PrivateKeys {
     tls: Vec<u8>,
     bundle: Vec<u8>,
 }
 
-pub fn gen_key_with_csr(name: &str) -> Result<(Vec<u8>, Vec<u8>), Box<dyn Error>> {
-    let tls_key = SigningKey::random(&mut OsRng);
-    let tls_key_der = tls_key.to_pkcs8_der()?.as_bytes().to_vec();
-
-    let bcast_key = SigningKey::random(&mut OsRng);
-    let bcast_pub_key = bcast_key
-        .verifying_key()
-        .to_public_key_der()?
-        .as_bytes()
-        .to_vec();
-    let bcast_key_der = bcast_key.to_pkcs8_der()?.as_bytes().to_vec();
+pub fn gen_key_with_csr(name: &str) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    gen_key_with_csr_for_suite(name, CipherSuite::default(), false)
+}
 
-    let uni_sign_key = SigningKey::random(&mut OsRng);
-    let uni_sign_pub_key = uni_sign_key
-        .verifying_key()
-        .to_public_key_der()?
+/// Like `gen_key_with_csr`, with the keys of the bundles following `suite`.
+/// In `hybrid` mode, the bundles additionally carry ML-KEM and ML-DSA keys,
+/// so that unicasts to us and broadcasts from us are also protected post-quantum
+pub fn gen_key_with_csr_for_suite(
+    name: &str,
+    suite: CipherSuite,
+    hybrid: bool,
+) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    let tls_key = SigningKey::random(&mut OsRng);
+    let tls_key_der = tls_key
+        .to_pkcs8_der()
+        .map_err(|_| Error::serialization("failed to encode TLS key"))?
         .as_bytes()
         .to_vec();
-    let uni_sign_key_der = uni_sign_key.to_pkcs8_der()?.as_bytes().to_vec();
 
-    let (uni_dec_key, uni_enc_key) = ecies::utils::generate_keypair();
-    let (uni_dec_key, uni_enc_key) = (uni_dec_key.serialize(), uni_enc_key.serialize());
+    let (bcast_key_der, bcast_pub_key) = suite.generate_signing_key()?;
+    let (uni_sign_key_der, uni_sign_pub_key) = suite.generate_signing_key()?;
+    let (uni_dec_key, uni_enc_key) = suite.generate_encryption_key();
+    let (pq_bcast_key, pq_bcast_pub_key) = match hybrid {
+        true => {
+            let (key, pub_key) = hybrid::generate_signing_key();
+            (Some(key), Some(pub_key))
+        }
+        false => (None, None),
+    };
+    let (pq_uni_dec_key, pq_uni_enc_key) = match hybrid {
+        true => {
+            let (key, pub_key) = hybrid::generate_encryption_key();
+            (Some(key), Some(pub_key))
+        }
+        false => (None, None),
+    };
 
     let subject = Name::from_str(&format!("CN={name}"))?;
-    let mut builder = RequestBuilder::new(subject, &tls_key)?;
-    builder.add_extension(&MeeSignPublicBundle {
-        broadcast_sign: bcast_pub_key,
-        unicast_sign: uni_sign_pub_key,
-        unicast_encrypt: uni_enc_key.into(),
-    })?;
-    let csr = builder.build::<DerSignature>()?;
+    let request_error = |_| Error::serialization("failed to build certificate request");
+    let mut builder = RequestBuilder::new(subject, &tls_key).map_err(request_error)?;
+    builder
+        .add_extension(&MeeSignPublicBundle {
+            broadcast_sign: bcast_pub_key,
+            unicast_sign: uni_sign_pub_key,
+            unicast_encrypt: uni_enc_key,
+            suite: suite.into(),
+            pq_broadcast_sign: pq_bcast_pub_key,
+            pq_unicast_encrypt: pq_uni_enc_key,
+        })
+        .map_err(request_error)?;
+    let csr = builder.build::<DerSignature>().map_err(request_error)?;
     let csr_der = csr.to_der()?;
 
     let private_bundle = MeeSignPrivateBundle {
-        broadcast_sign: bcast_key_der,
-        unicast_sign: uni_sign_key_der,
-        unicast_decrypt: uni_dec_key.into(),
+        broadcast_sign: bcast_key_der.to_vec(),
+        unicast_sign: uni_sign_key_der.to_vec(),
+        unicast_decrypt: uni_dec_key.to_vec(),
+        suite: suite.into(),
+        pq_broadcast_sign: pq_bcast_key.map(|key| key.to_vec()),
+        pq_unicast_decrypt: pq_uni_dec_key.map(|key| key.to_vec()),
     }
     .to_der()?;
 
@@ -102,11 +148,8 @@ pub fn gen_key_with_csr(name: &str) -> Result<(Vec<u8>, Vec<u8>), Box<dyn Error>
     Ok((keys_der, csr_der))
 }
 
-pub fn cert_key_to_pkcs12(keys_der: &[u8], cert_der: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
-    let PrivateKeys {
-        tls: key_der,
-        bundle,
-    } = PrivateKeys::from_der(keys_der)?;
+pub fn cert_key_to_pkcs12(keys_der: &[u8], cert_der: &[u8]) -> Result<Vec<u8>, Error> {
+    let keys = PrivateKeys::from_der(keys_der)?;
     let password = b"";
 
     // The p12 library does not directly support the PKCS#12 features we need,
@@ -133,7 +176,7 @@ pub fn cert_key_to_pkcs12(keys_der: &[u8], cert_der: &[u8]) -> Result<Vec<u8>, B
                                 // RFC7292 Appendix D, Bag types: KeyBag OID
                                 1, 2, 840, 113549, 1, 12, 10, 1, 1,
                             ]),
-                            bag_value: key_der.to_vec(),
+                            bag_value: keys.tls.clone(),
                         }),
                         attributes: vec![tls_auth_friendly_name],
                     }
@@ -144,7 +187,7 @@ pub fn cert_key_to_pkcs12(keys_der: &[u8], cert_der: &[u8]) -> Result<Vec<u8>, B
                                 // RFC7292 Appendix D, Bag types: SecretBag OID
                                 1, 2, 840, 113549, 1, 12, 10, 1, 5,
                             ]),
-                            bag_value: bundle,
+                            bag_value: keys.bundle.clone(),
                         }),
                         attributes: vec![p12::PKCS12Attribute::FriendlyName(
                             MeeSignPrivateBundle::FRIENDLY_NAME.to_string(),
@@ -167,27 +210,49 @@ pub fn cert_key_to_pkcs12(keys_der: &[u8], cert_der: &[u8]) -> Result<Vec<u8>, B
 }
 
 /// Extracts a DER-encoded MeeSignPublicBundle from a DER-encoded X.509 certificate
-pub fn extract_public_bundle_der(cert_der: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+pub fn extract_public_bundle_der(cert_der: &[u8]) -> Result<Vec<u8>, Error> {
     Ok(Certificate::from_der(cert_der)?
         .tbs_certificate
         .extensions
-        .ok_or("certificate does not contain public bundle")?
+        .ok_or_else(|| Error::malformed("certificate does not contain public bundle"))?
         .into_iter()
         .find(|ext| ext.extn_id == MeeSignPublicBundle::OID)
-        .ok_or("certificate does not contain public bundle")?
+        .ok_or_else(|| Error::malformed("certificate does not contain public bundle"))?
         .extn_value
         .into_bytes())
 }
 
+/// Extracts a DER-encoded MeeSignPrivateBundle from a DER-encoded PKCS#12 file
+pub fn extract_private_bundle_der(pfx_der: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let bag = p12::PFX::parse(pfx_der)
+        .and_then(|pfx| pfx.bags(""))
+        .map_err(|_| Error::malformed("invalid PKCS#12"))?
+        .into_iter()
+        .find(|bag| bag.friendly_name().as_deref() == Some(MeeSignPrivateBundle::FRIENDLY_NAME))
+        .ok_or_else(|| Error::malformed("PKCS#12 does not contain private bundle"))?;
+
+    match bag.bag {
+        p12::SafeBagKind::OtherBagKind(p12::OtherBag { bag_value, .. }) => {
+            Ok(Zeroizing::new(bag_value))
+        }
+        _ => Err(Error::malformed("unexpected PKCS#12 SafeBag")),
+    }
+}
+
 /// Verifies a signed broadcast and extracts the message
-pub fn verify_broadcast(msg: &[u8], cert_der: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+pub fn verify_broadcast(msg: &[u8], cert_der: &[u8]) -> Result<Vec<u8>, Error> {
     use crate::proto::Message as _;
     let public_bundle = extract_public_bundle_der(cert_der)?;
     let public_bundle = MeeSignPublicBundle::from_der(&public_bundle)?;
     let msg = SignedMessage::decode(msg)?;
-    let signature = ecdsa::Signature::from_slice(&msg.signature)?;
-    let key = ecdsa::VerifyingKey::from_public_key_der(&public_bundle.broadcast_sign)?;
-    key.verify(&msg.message, &signature)
-        .map_err(|_| "broadcast signature mismatch")?;
+    let suite = CipherSuite::try_from(public_bundle.suite)?;
+    let key = VerifyingKey::from_public_key_der(suite, &public_bundle.broadcast_sign)?;
+    key.verify(&msg.message, &msg.signature)
+        .map_err(|_| Error::crypto("broadcast signature mismatch"))?;
+    if let Some(pq_key) = public_bundle.pq_broadcast_sign {
+        PqVerifyingKey::from_bytes(&pq_key)?
+            .verify(&msg.message, &msg.pq_signature)
+            .map_err(|_| Error::crypto("broadcast co-signature mismatch"))?;
+    }
     Ok(msg.message)
 }
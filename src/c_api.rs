@@ -1,22 +1,30 @@
 use core::slice;
-use std::error::Error;
+use std::cell::RefCell;
+#[cfg(feature = "protocol")]
+use std::convert::{TryFrom, TryInto};
 use std::ffi::CStr;
 use std::ffi::CString;
 use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+
+use zeroize::Zeroize;
 
 use crate::auth;
+#[cfg(feature = "protocol")]
+use crate::capabilities;
+use crate::error::Error as MeeSignError;
+#[cfg(feature = "protocol")]
+use crate::protocol;
 #[cfg(feature = "elgamal")]
 use crate::protocol::elgamal;
-#[cfg(feature = "frost")]
-use crate::protocol::frost;
-#[cfg(feature = "gg18")]
-use crate::protocol::gg18;
-#[cfg(feature = "musig2")]
-use crate::protocol::musig2;
 #[cfg(feature = "protocol")]
-use crate::protocol::{self, KeygenProtocol, ThresholdProtocol};
+use crate::security::{
+    derive_state_key, verify_receipt, AbortReason as SecureLayerAbortReason, ProtocolType,
+    StateKind,
+};
 #[cfg(feature = "protocol")]
-use crate::security::{ProtocolType, SecureLayer, State as SecureLayerState};
+use crate::session::Session;
+use crate::suite::CipherSuite as AuthCipherSuite;
 
 #[repr(C)]
 #[derive(Clone, Copy)]
@@ -25,16 +33,94 @@ pub enum ProtocolId {
     Elgamal,
     Frost,
     Musig2,
+    /// No protocol, for `protocol_init` to infer it from the group
+    Unknown,
 }
 
 #[cfg(feature = "protocol")]
-impl From<ProtocolId> for ProtocolType {
-    fn from(pid: ProtocolId) -> Self {
-        match pid {
-            ProtocolId::Gg18 => ProtocolType::Gg18,
-            ProtocolId::Elgamal => ProtocolType::Elgamal,
-            ProtocolId::Frost => ProtocolType::Frost,
-            ProtocolId::Musig2 => ProtocolType::Musig2,
+impl ProtocolId {
+    /// The identified protocol, none if it is `Unknown`
+    fn protocol_type(self) -> Option<ProtocolType> {
+        match self {
+            ProtocolId::Gg18 => Some(ProtocolType::Gg18),
+            ProtocolId::Elgamal => Some(ProtocolType::Elgamal),
+            ProtocolId::Frost => Some(ProtocolType::Frost),
+            ProtocolId::Musig2 => Some(ProtocolType::Musig2),
+            ProtocolId::Unknown => None,
+        }
+    }
+}
+
+#[cfg(feature = "protocol")]
+impl TryFrom<ProtocolId> for ProtocolType {
+    type Error = MeeSignError;
+
+    fn try_from(pid: ProtocolId) -> Result<Self, Self::Error> {
+        pid.protocol_type()
+            .ok_or_else(|| MeeSignError::misuse("unknown protocol"))
+    }
+}
+
+#[cfg(feature = "protocol")]
+impl From<ProtocolType> for ProtocolId {
+    fn from(protocol_type: ProtocolType) -> Self {
+        match protocol_type {
+            ProtocolType::Gg18 => ProtocolId::Gg18,
+            ProtocolType::Elgamal => ProtocolId::Elgamal,
+            ProtocolType::Frost => ProtocolId::Frost,
+            ProtocolType::Musig2 => ProtocolId::Musig2,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum AbortReason {
+    Error,
+    Declined,
+}
+
+#[cfg(feature = "protocol")]
+impl From<AbortReason> for SecureLayerAbortReason {
+    fn from(reason: AbortReason) -> Self {
+        match reason {
+            AbortReason::Error => SecureLayerAbortReason::Error,
+            AbortReason::Declined => SecureLayerAbortReason::Declined,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum CipherSuite {
+    P256Ecies,
+    Ed25519X25519,
+}
+
+impl From<CipherSuite> for AuthCipherSuite {
+    fn from(suite: CipherSuite) -> Self {
+        match suite {
+            CipherSuite::P256Ecies => AuthCipherSuite::P256Ecies,
+            CipherSuite::Ed25519X25519 => AuthCipherSuite::Ed25519X25519,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum Curve {
+    P256,
+    Secp256k1,
+    Ristretto255,
+}
+
+#[cfg(feature = "protocol")]
+impl From<protocol::Curve> for Curve {
+    fn from(curve: protocol::Curve) -> Self {
+        match curve {
+            protocol::Curve::P256 => Curve::P256,
+            protocol::Curve::Secp256k1 => Curve::Secp256k1,
+            protocol::Curve::Ristretto255 => Curve::Ristretto255,
         }
     }
 }
@@ -56,6 +142,41 @@ impl From<protocol::Recipient> for Recipient {
     }
 }
 
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum ShareState {
+    CertSwap,
+    Init,
+    Running,
+    CardResponse,
+    BroadcastExchange,
+    BroadcastCheck,
+    BroadcastDigestCheck,
+    BroadcastFetch,
+    Aborted,
+    KeyConfirmation,
+    KeyConfirmed,
+}
+
+#[cfg(feature = "protocol")]
+impl From<StateKind> for ShareState {
+    fn from(kind: StateKind) -> Self {
+        match kind {
+            StateKind::CertSwap => ShareState::CertSwap,
+            StateKind::Init => ShareState::Init,
+            StateKind::Running => ShareState::Running,
+            StateKind::CardResponse => ShareState::CardResponse,
+            StateKind::BroadcastExchange => ShareState::BroadcastExchange,
+            StateKind::BroadcastCheck => ShareState::BroadcastCheck,
+            StateKind::BroadcastDigestCheck => ShareState::BroadcastDigestCheck,
+            StateKind::BroadcastFetch => ShareState::BroadcastFetch,
+            StateKind::Aborted => ShareState::Aborted,
+            StateKind::KeyConfirmation => ShareState::KeyConfirmation,
+            StateKind::KeyConfirmed => ShareState::KeyConfirmed,
+        }
+    }
+}
+
 #[repr(C)]
 pub struct Buffer {
     ptr: *mut u8,
@@ -90,11 +211,98 @@ impl Drop for Buffer {
 #[allow(unused_variables)]
 pub unsafe extern "C" fn buffer_free(buffer: Buffer) {}
 
-fn set_error(error_out: *mut *mut c_char, error: &dyn Error) {
+/// Frees a buffer holding a secret, e.g. from `protocol_state_key`, wiping its contents first
+#[no_mangle]
+pub unsafe extern "C" fn buffer_free_secret(buffer: Buffer) {
+    unsafe { slice::from_raw_parts_mut(buffer.ptr, buffer.len) }.zeroize();
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum ErrorCode {
+    None,
+    Misuse,
+    MalformedInput,
+    Crypto,
+    Card,
+    Serialization,
+    Aborted,
+    Panic,
+}
+
+/// The classification of an error reported through `error_out`
+#[repr(C)]
+pub struct ErrorInfo {
+    code: ErrorCode,
+    /// The party which caused a `Crypto` error or aborted the protocol, -1 if unknown
+    party: i64,
+    /// The status word of a `Card` error
+    card_status: u16,
+}
+
+impl From<Option<&MeeSignError>> for ErrorInfo {
+    fn from(error: Option<&MeeSignError>) -> Self {
+        let (code, party, card_status) = match error {
+            None => (ErrorCode::None, None, 0),
+            Some(MeeSignError::Misuse(_)) => (ErrorCode::Misuse, None, 0),
+            Some(MeeSignError::MalformedInput(_)) => (ErrorCode::MalformedInput, None, 0),
+            Some(MeeSignError::Crypto { culprit, .. }) => (ErrorCode::Crypto, *culprit, 0),
+            Some(MeeSignError::Card { status }) => (ErrorCode::Card, None, *status),
+            Some(MeeSignError::Serialization(_)) => (ErrorCode::Serialization, None, 0),
+            Some(MeeSignError::Aborted { party, .. }) => (ErrorCode::Aborted, Some(*party), 0),
+            Some(MeeSignError::Panic(_)) => (ErrorCode::Panic, None, 0),
+        };
+        ErrorInfo {
+            code,
+            party: party.map_or(-1, i64::from),
+            card_status,
+        }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<MeeSignError>> = RefCell::new(None);
+}
+
+fn set_error(error_out: *mut *mut c_char, error: MeeSignError) {
     if !error_out.is_null() {
-        let msg = CString::new(error.to_string()).unwrap().into_raw();
+        let msg = CString::new(error.to_string().replace('\0', ""))
+            .unwrap_or_default()
+            .into_raw();
         unsafe { *error_out = msg };
     }
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(error));
+}
+
+/// Runs the body of an exported function, reporting its error or panic through `error_out`
+/// and returning `fallback()` instead, since a panic must not unwind into the caller
+fn guard<T>(
+    error_out: *mut *mut c_char,
+    fallback: impl FnOnce() -> T,
+    body: impl FnOnce() -> Result<T, MeeSignError>,
+) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(error)) => {
+            set_error(error_out, error);
+            fallback()
+        }
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".into());
+            set_error(error_out, MeeSignError::Panic(message));
+            fallback()
+        }
+    }
+}
+
+/// Classifies the last error reported through `error_out` on the calling thread
+#[no_mangle]
+pub extern "C" fn error_last() -> ErrorInfo {
+    LAST_ERROR.with(|last| last.borrow().as_ref().into())
 }
 
 #[no_mangle]
@@ -106,30 +314,154 @@ pub unsafe extern "C" fn error_free(error: *mut c_char) {
     }
 }
 
+/// Returns the version of the library as a static string, which must not be freed
+#[no_mangle]
+pub extern "C" fn library_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+/// How a protocol is supported by this build of the library
+#[repr(C)]
+pub struct ProtocolSupport {
+    /// False if the protocol is compiled out
+    supported: bool,
+    /// Whether the shares can be computed with a smart card
+    card: bool,
+}
+
+#[cfg(feature = "protocol")]
+#[no_mangle]
+pub extern "C" fn protocol_support(proto_id: ProtocolId) -> ProtocolSupport {
+    let support = proto_id.protocol_type().and_then(capabilities::support);
+    ProtocolSupport {
+        supported: support.is_some(),
+        card: support.map_or(false, |support| support.card),
+    }
+}
+
+/// Returns the protocols compiled into this build, one `ProtocolId` per byte
+#[cfg(feature = "protocol")]
+#[no_mangle]
+pub extern "C" fn supported_protocols() -> Buffer {
+    capabilities::protocols()
+        .into_iter()
+        .map(|support| ProtocolId::from(support.protocol) as u8)
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// The versions of the formats produced by this build, see `capabilities::WireVersions`
+#[repr(C)]
+pub struct WireVersions {
+    messages: u32,
+    sealed_state: u32,
+    group: u32,
+}
+
+#[cfg(feature = "protocol")]
+#[no_mangle]
+pub extern "C" fn wire_versions() -> WireVersions {
+    let versions = capabilities::wire_versions();
+    WireVersions {
+        messages: versions.messages,
+        sealed_state: versions.sealed_state,
+        group: versions.group,
+    }
+}
+
 pub struct Protocol {
     #[cfg(feature = "protocol")]
-    inner: SecureLayer,
+    inner: Session,
 }
 
 impl Protocol {
     #[cfg(feature = "protocol")]
-    fn wrap(inner: SecureLayer) -> *mut Self {
+    fn wrap(inner: Session) -> *mut Self {
         Box::into_raw(Box::new(Protocol { inner }))
     }
 }
 
 #[cfg(feature = "protocol")]
 #[no_mangle]
-pub unsafe extern "C" fn protocol_serialize(proto_ptr: *mut Protocol) -> Buffer {
-    let proto = unsafe { Box::from_raw(proto_ptr) };
-    serde_json::to_vec(&proto.inner).unwrap().into()
+pub unsafe extern "C" fn protocol_serialize(
+    proto_ptr: *mut Protocol,
+    error_out: *mut *mut c_char,
+) -> Buffer {
+    guard(
+        error_out,
+        || vec![].into(),
+        || {
+            let proto = unsafe { Box::from_raw(proto_ptr) };
+            Ok(proto.inner.to_bytes()?.into())
+        },
+    )
+}
+
+#[cfg(feature = "protocol")]
+#[no_mangle]
+pub unsafe extern "C" fn protocol_deserialize(
+    ctx_ptr: *const u8,
+    ctx_len: usize,
+    error_out: *mut *mut c_char,
+) -> *mut Protocol {
+    guard(error_out, std::ptr::null_mut, || {
+        let ser = unsafe { slice::from_raw_parts(ctx_ptr, ctx_len) };
+        Ok(Protocol::wrap(Session::from_bytes(ser)?))
+    })
 }
 
 #[cfg(feature = "protocol")]
 #[no_mangle]
-pub unsafe extern "C" fn protocol_deserialize(ctx_ptr: *const u8, ctx_len: usize) -> *mut Protocol {
-    let ser = unsafe { slice::from_raw_parts(ctx_ptr, ctx_len) };
-    Protocol::wrap(serde_json::from_slice(ser).unwrap())
+pub unsafe extern "C" fn protocol_serialize_sealed(
+    proto_ptr: *mut Protocol,
+    key_ptr: *const u8,
+    key_len: usize,
+    error_out: *mut *mut c_char,
+) -> Buffer {
+    guard(
+        error_out,
+        || vec![].into(),
+        || {
+            let proto = unsafe { Box::from_raw(proto_ptr) };
+            let key = unsafe { slice::from_raw_parts(key_ptr, key_len) };
+            Ok(proto.inner.seal(key)?.into())
+        },
+    )
+}
+
+#[cfg(feature = "protocol")]
+#[no_mangle]
+pub unsafe extern "C" fn protocol_deserialize_sealed(
+    ctx_ptr: *const u8,
+    ctx_len: usize,
+    key_ptr: *const u8,
+    key_len: usize,
+    error_out: *mut *mut c_char,
+) -> *mut Protocol {
+    guard(error_out, std::ptr::null_mut, || {
+        let sealed = unsafe { slice::from_raw_parts(ctx_ptr, ctx_len) };
+        let key = unsafe { slice::from_raw_parts(key_ptr, key_len) };
+        Ok(Protocol::wrap(Session::unseal(sealed, key)?))
+    })
+}
+
+/// Derives a key for sealing protocol states from the private bundle in `pkcs12`.
+/// The key is to be freed by `buffer_free_secret`
+#[cfg(feature = "protocol")]
+#[no_mangle]
+pub unsafe extern "C" fn protocol_state_key(
+    pkcs12_ptr: *const u8,
+    pkcs12_len: usize,
+    error_out: *mut *mut c_char,
+) -> Buffer {
+    guard(
+        error_out,
+        || vec![].into(),
+        || {
+            let pkcs12 = unsafe { slice::from_raw_parts(pkcs12_ptr, pkcs12_len) };
+            Ok(derive_state_key(pkcs12)?.to_vec().into())
+        },
+    )
 }
 
 #[cfg(feature = "protocol")]
@@ -142,36 +474,25 @@ pub unsafe extern "C" fn protocol_keygen(
     pkcs12_len: usize,
     with_card: bool,
     shares: usize,
+    error_out: *mut *mut c_char,
 ) -> *mut Protocol {
-    let build_proto = |_| -> Box<dyn protocol::Protocol> {
-        match (proto_id, with_card) {
-            #[cfg(feature = "gg18")]
-            (ProtocolId::Gg18, false) => Box::new(gg18::KeygenContext::new()),
-            #[cfg(feature = "elgamal")]
-            (ProtocolId::Elgamal, false) => Box::new(elgamal::KeygenContext::new()),
-            #[cfg(feature = "frost")]
-            (ProtocolId::Frost, false) => Box::new(frost::KeygenContext::new()),
-            #[cfg(feature = "frost")]
-            (ProtocolId::Frost, true) => Box::new(frost::KeygenContext::with_card()),
-            #[cfg(feature = "musig2")]
-            (ProtocolId::Musig2, false) => Box::new(musig2::KeygenContext::new()),
-            #[cfg(feature = "musig2")]
-            (ProtocolId::Musig2, true) => Box::new(musig2::KeygenContext::with_card()),
-            _ => panic!("Protocol not supported"),
-        }
-    };
-    let certs = unsafe { slice::from_raw_parts(certs_ptr, certs_len) };
-    let pkcs12 = unsafe { slice::from_raw_parts(pkcs12_ptr, pkcs12_len) };
-    let sl = SecureLayer::new(
-        SecureLayerState::CertSwap,
-        (0..shares).map(build_proto).collect(),
-        certs,
-        pkcs12,
-        proto_id.into(),
-    );
-    Protocol::wrap(sl)
+    guard(error_out, std::ptr::null_mut, || {
+        let certs = unsafe { slice::from_raw_parts(certs_ptr, certs_len) };
+        let pkcs12 = unsafe { slice::from_raw_parts(pkcs12_ptr, pkcs12_len) };
+        let session = Session::keygen(proto_id.try_into()?)
+            .certs(certs)
+            .identity(pkcs12)
+            .shares(shares)
+            .with_card(with_card)
+            .build()?;
+        Ok(Protocol::wrap(session))
+    })
 }
 
+/// Advances share `index` with a message from the server or a card and returns the response.
+/// On error, the returned buffer is empty and `error_out` is set. A share which failed
+/// a cryptographic check is aborted, its abort message must then be obtained from
+/// `protocol_abort` and sent to the server
 #[cfg(feature = "protocol")]
 #[no_mangle]
 pub unsafe extern "C" fn protocol_advance(
@@ -181,17 +502,38 @@ pub unsafe extern "C" fn protocol_advance(
     data_len: usize,
     error_out: *mut *mut c_char,
 ) -> Buffer {
-    let data_in = unsafe { slice::from_raw_parts(data_ptr, data_len) };
-    let proto = unsafe { &mut *proto_ptr };
-
-    let (vec, rec) = match proto.inner.advance_share(index, data_in) {
-        Ok((vec, rec)) => (vec, rec.into()),
-        Err(error) => {
-            set_error(error_out, &*error);
-            (vec![], Recipient::Unknown)
-        }
-    };
-    Buffer::from_vec(vec, rec)
+    guard(
+        error_out,
+        || vec![].into(),
+        || {
+            let data_in = unsafe { slice::from_raw_parts(data_ptr, data_len) };
+            let proto = unsafe { &mut *proto_ptr };
+
+            let response = proto.inner.advance(index, data_in)?;
+            Ok(Buffer::from_vec(response.data, response.recipient.into()))
+        },
+    )
+}
+
+/// Aborts the computation of one share, e.g. when the user declines,
+/// and returns the abort message for the server
+#[cfg(feature = "protocol")]
+#[no_mangle]
+pub unsafe extern "C" fn protocol_abort(
+    proto_ptr: *mut Protocol,
+    index: usize,
+    reason: AbortReason,
+    error_out: *mut *mut c_char,
+) -> Buffer {
+    guard(
+        error_out,
+        || vec![].into(),
+        || {
+            let proto = unsafe { &mut *proto_ptr };
+            let abort = proto.inner.abort(index, reason.into())?;
+            Ok(Buffer::from_vec(abort, Recipient::Server))
+        },
+    )
 }
 
 #[cfg(feature = "protocol")]
@@ -200,24 +542,237 @@ pub unsafe extern "C" fn protocol_finish(
     proto_ptr: *mut Protocol,
     error_out: *mut *mut c_char,
 ) -> Buffer {
-    let proto = unsafe { Box::from_raw(proto_ptr) };
+    guard(
+        error_out,
+        || vec![].into(),
+        || {
+            let proto = unsafe { Box::from_raw(proto_ptr) };
+            let vec_data_out = proto.inner.finish()?;
+            Ok(serde_json::to_vec(&vec_data_out)?.into())
+        },
+    )
+}
 
-    let res = proto.inner.finish_all();
+/// Where the computation of one share stands
+#[repr(C)]
+pub struct ShareStatus {
+    state: ShareState,
+    /// The party which aborted the protocol, -1 if none
+    aborted_by: i64,
+    /// The name of the protocol round, in UTF-8
+    round: Buffer,
+    rounds_done: u16,
+    /// The expected number of rounds, card rounds excluded
+    rounds_total: u16,
+    /// The recipient of the last response, `Unknown` before the first one
+    recipient: Recipient,
+    finished: bool,
+}
 
-    match res {
-        Ok(vec_data_out) => serde_json::to_vec(&vec_data_out).unwrap(),
-        Err(error) => {
-            set_error(error_out, &*error);
-            vec![]
-        }
-    }
-    .into()
+#[no_mangle]
+#[allow(unused_variables)]
+pub unsafe extern "C" fn share_status_free(status: ShareStatus) {}
+
+/// The number of shares computed in the protocol
+#[cfg(feature = "protocol")]
+#[no_mangle]
+pub unsafe extern "C" fn protocol_share_count(
+    proto_ptr: *const Protocol,
+    error_out: *mut *mut c_char,
+) -> usize {
+    guard(
+        error_out,
+        || 0,
+        || {
+            let proto = unsafe { &*proto_ptr };
+            Ok(proto.inner.share_count())
+        },
+    )
+}
+
+/// Reports where the computation of one share stands
+#[cfg(feature = "protocol")]
+#[no_mangle]
+pub unsafe extern "C" fn protocol_status(
+    proto_ptr: *const Protocol,
+    index: usize,
+    error_out: *mut *mut c_char,
+) -> ShareStatus {
+    guard(
+        error_out,
+        || ShareStatus {
+            state: ShareState::Aborted,
+            aborted_by: -1,
+            round: vec![].into(),
+            rounds_done: 0,
+            rounds_total: 0,
+            recipient: Recipient::Unknown,
+            finished: false,
+        },
+        || {
+            let proto = unsafe { &*proto_ptr };
+            let status = proto.inner.status(index)?;
+            Ok(ShareStatus {
+                state: status.state.into(),
+                aborted_by: status.aborted_by.map_or(-1, i64::from),
+                round: status.round.as_bytes().to_vec().into(),
+                rounds_done: status.rounds_done,
+                rounds_total: status.rounds_total,
+                recipient: status.recipient.map_or(Recipient::Unknown, Recipient::from),
+                finished: status.finished,
+            })
+        },
+    )
+}
+
+#[repr(C)]
+pub struct ProtocolResult {
+    results: Buffer,
+    receipts: Buffer,
+}
+
+#[no_mangle]
+#[allow(unused_variables)]
+pub unsafe extern "C" fn protocol_result_free(result: ProtocolResult) {}
+
+/// Finishes the protocol like `protocol_finish`, additionally returning
+/// the signed transcript receipts of all shares
+#[cfg(feature = "protocol")]
+#[no_mangle]
+pub unsafe extern "C" fn protocol_finish_with_receipts(
+    proto_ptr: *mut Protocol,
+    error_out: *mut *mut c_char,
+) -> ProtocolResult {
+    guard(
+        error_out,
+        || ProtocolResult {
+            results: vec![].into(),
+            receipts: vec![].into(),
+        },
+        || {
+            let proto = unsafe { Box::from_raw(proto_ptr) };
+            let output = proto.inner.finish_with_receipts()?;
+            Ok(ProtocolResult {
+                results: serde_json::to_vec(&output.results)?.into(),
+                receipts: serde_json::to_vec(&output.receipts)?.into(),
+            })
+        },
+    )
+}
+
+/// Verifies a transcript receipt against the certificate of its issuer
+/// and returns the transcript digest
+#[cfg(feature = "protocol")]
+#[no_mangle]
+pub unsafe extern "C" fn protocol_verify_receipt(
+    receipt_ptr: *const u8,
+    receipt_len: usize,
+    cert_ptr: *const u8,
+    cert_len: usize,
+    error_out: *mut *mut c_char,
+) -> Buffer {
+    guard(
+        error_out,
+        || vec![].into(),
+        || {
+            let receipt = unsafe { slice::from_raw_parts(receipt_ptr, receipt_len) };
+            let cert = unsafe { slice::from_raw_parts(cert_ptr, cert_len) };
+            Ok(verify_receipt(receipt, cert)?.into())
+        },
+    )
+}
+
+/// What a group blob output by a keygen reveals about its group
+#[repr(C)]
+pub struct GroupInfo {
+    protocol: ProtocolId,
+    curve: Curve,
+    /// The group key, SEC1 compressed or a compressed Ristretto point
+    public_key: Buffer,
+    /// -1 if unknown, as for GG18 groups which do not record it
+    threshold: i64,
+    /// -1 if unknown, as for GG18 groups which do not record it
+    parties: i64,
+    /// The indices of the local shares, as a JSON array
+    indices: Buffer,
+    with_card: bool,
+    /// The verifying shares of the parties, as a JSON object keyed by their index
+    verifying_shares: Buffer,
+}
+
+#[no_mangle]
+#[allow(unused_variables)]
+pub unsafe extern "C" fn group_info_free(info: GroupInfo) {}
+
+/// Describes the group of a group blob returned by `protocol_finish`
+#[cfg(feature = "protocol")]
+#[no_mangle]
+pub unsafe extern "C" fn protocol_group_info(
+    group_ptr: *const u8,
+    group_len: usize,
+    error_out: *mut *mut c_char,
+) -> GroupInfo {
+    guard(
+        error_out,
+        || GroupInfo {
+            protocol: ProtocolId::Gg18,
+            curve: Curve::P256,
+            public_key: vec![].into(),
+            threshold: -1,
+            parties: -1,
+            indices: vec![].into(),
+            with_card: false,
+            verifying_shares: vec![].into(),
+        },
+        || {
+            let group_ser = unsafe { slice::from_raw_parts(group_ptr, group_len) };
+            let shares: Vec<Vec<u8>> = serde_json::from_slice(group_ser)?;
+            let info = protocol::inspect_group(&shares)?;
+            Ok(GroupInfo {
+                protocol: info.protocol.into(),
+                curve: info.curve.into(),
+                public_key: info.public_key.into(),
+                threshold: info.threshold.map_or(-1, i64::from),
+                parties: info.parties.map_or(-1, i64::from),
+                indices: serde_json::to_vec(&info.indices)?.into(),
+                with_card: info.with_card,
+                verifying_shares: serde_json::to_vec(&info.verifying_shares)?.into(),
+            })
+        },
+    )
+}
+
+/// Verifies a signature output by `proto_id` under the group key returned by `protocol_group_info`.
+/// Returns false with the reason in `error_out` if the signature is invalid
+#[cfg(feature = "protocol")]
+#[no_mangle]
+pub unsafe extern "C" fn protocol_verify(
+    proto_id: ProtocolId,
+    group_key_ptr: *const u8,
+    group_key_len: usize,
+    message_ptr: *const u8,
+    message_len: usize,
+    signature_ptr: *const u8,
+    signature_len: usize,
+    error_out: *mut *mut c_char,
+) -> bool {
+    guard(
+        error_out,
+        || false,
+        || {
+            let group_key = unsafe { slice::from_raw_parts(group_key_ptr, group_key_len) };
+            let message = unsafe { slice::from_raw_parts(message_ptr, message_len) };
+            let signature = unsafe { slice::from_raw_parts(signature_ptr, signature_len) };
+            protocol::verify(proto_id.try_into()?, group_key, message, signature)?;
+            Ok(true)
+        },
+    )
 }
 
 #[cfg(feature = "protocol")]
 #[no_mangle]
 pub unsafe extern "C" fn protocol_init(
-    // TODO: proto_id should be inferred from group
+    // NOTE: must match the protocol recorded in the group, `Unknown` infers it from the group
     proto_id: ProtocolId,
     group_ptr: *const u8,
     group_len: usize,
@@ -226,35 +781,25 @@ pub unsafe extern "C" fn protocol_init(
     pkcs12_ptr: *const u8,
     pkcs12_len: usize,
     shares: usize,
+    error_out: *mut *mut c_char,
 ) -> *mut Protocol {
-    let group_ser = unsafe { slice::from_raw_parts(group_ptr, group_len) };
-    let shares_ser: Vec<Vec<u8>> = serde_json::from_slice(group_ser).unwrap();
-
-    let build_proto = |share_ser: &Vec<u8>| -> Box<dyn protocol::Protocol> {
-        match proto_id {
-            #[cfg(feature = "gg18")]
-            ProtocolId::Gg18 => Box::new(gg18::SignContext::new(share_ser)),
-            #[cfg(feature = "elgamal")]
-            ProtocolId::Elgamal => Box::new(elgamal::DecryptContext::new(share_ser)),
-            #[cfg(feature = "frost")]
-            ProtocolId::Frost => Box::new(frost::SignContext::new(share_ser)),
-            #[cfg(feature = "musig2")]
-            ProtocolId::Musig2 => Box::new(musig2::SignContext::new(share_ser)),
-            #[cfg(not(all(feature = "gg18", feature = "elgamal", feature = "frost")))]
-            _ => panic!("Protocol not supported"),
+    guard(error_out, std::ptr::null_mut, || {
+        let group_ser = unsafe { slice::from_raw_parts(group_ptr, group_len) };
+        let shares_ser: Vec<Vec<u8>> = serde_json::from_slice(group_ser)?;
+        let shares_ser = shares_ser
+            .get(..shares)
+            .ok_or_else(|| MeeSignError::malformed("group holds fewer shares than requested"))?;
+
+        let certs = unsafe { slice::from_raw_parts(certs_ptr, certs_len) };
+        let pkcs12 = unsafe { slice::from_raw_parts(pkcs12_ptr, pkcs12_len) };
+        let mut builder = Session::threshold(shares_ser.to_vec())
+            .certs(certs)
+            .identity(pkcs12);
+        if let Some(protocol_type) = proto_id.protocol_type() {
+            builder = builder.protocol(protocol_type);
         }
-    };
-
-    let certs = unsafe { slice::from_raw_parts(certs_ptr, certs_len) };
-    let pkcs12 = unsafe { slice::from_raw_parts(pkcs12_ptr, pkcs12_len) };
-    let sl = SecureLayer::new(
-        SecureLayerState::Init,
-        shares_ser[..shares].iter().map(build_proto).collect(),
-        certs,
-        pkcs12,
-        proto_id.into(),
-    );
-    Protocol::wrap(sl)
+        Ok(Protocol::wrap(builder.build()?))
+    })
 }
 
 #[repr(C)]
@@ -278,14 +823,37 @@ pub unsafe extern "C" fn auth_key_free(key: AuthKey) {}
 
 #[no_mangle]
 pub unsafe extern "C" fn auth_keygen(name: *const c_char, error_out: *mut *mut c_char) -> AuthKey {
-    let name = unsafe { CStr::from_ptr(name) }.to_str().unwrap();
-    match auth::gen_key_with_csr(name) {
-        Ok((key, csr)) => AuthKey::new(key, csr),
-        Err(error) => {
-            set_error(error_out, &*error);
-            AuthKey::new(vec![], vec![])
-        }
-    }
+    guard(
+        error_out,
+        || AuthKey::new(vec![], vec![]),
+        || {
+            let name = unsafe { CStr::from_ptr(name) }
+                .to_str()
+                .map_err(|_| MeeSignError::malformed("name is not valid UTF-8"))?;
+            let (key, csr) = auth::gen_key_with_csr(name)?;
+            Ok(AuthKey::new(key, csr))
+        },
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn auth_keygen_with_suite(
+    name: *const c_char,
+    suite: CipherSuite,
+    hybrid: bool,
+    error_out: *mut *mut c_char,
+) -> AuthKey {
+    guard(
+        error_out,
+        || AuthKey::new(vec![], vec![]),
+        || {
+            let name = unsafe { CStr::from_ptr(name) }
+                .to_str()
+                .map_err(|_| MeeSignError::malformed("name is not valid UTF-8"))?;
+            let (key, csr) = auth::gen_key_with_csr_for_suite(name, suite.into(), hybrid)?;
+            Ok(AuthKey::new(key, csr))
+        },
+    )
 }
 
 #[no_mangle]
@@ -296,16 +864,15 @@ pub unsafe extern "C" fn auth_cert_key_to_pkcs12(
     cert_len: usize,
     error_out: *mut *mut c_char,
 ) -> Buffer {
-    let key_der = unsafe { slice::from_raw_parts(key_ptr, key_len) };
-    let cert_der = unsafe { slice::from_raw_parts(cert_ptr, cert_len) };
-
-    match auth::cert_key_to_pkcs12(key_der, cert_der) {
-        Ok(pkcs12) => pkcs12.into(),
-        Err(error) => {
-            set_error(error_out, &*error);
-            vec![].into()
-        }
-    }
+    guard(
+        error_out,
+        || vec![].into(),
+        || {
+            let key_der = unsafe { slice::from_raw_parts(key_ptr, key_len) };
+            let cert_der = unsafe { slice::from_raw_parts(cert_ptr, cert_len) };
+            Ok(auth::cert_key_to_pkcs12(key_der, cert_der)?.into())
+        },
+    )
 }
 
 #[cfg(feature = "elgamal")]
@@ -317,14 +884,13 @@ pub unsafe extern "C" fn encrypt(
     key_len: usize,
     error_out: *mut *mut c_char,
 ) -> Buffer {
-    let msg = unsafe { slice::from_raw_parts(msg_ptr, msg_len) };
-    let key = unsafe { slice::from_raw_parts(key_ptr, key_len) };
-
-    match elgamal::encrypt(msg, &key) {
-        Ok(ciphertext) => ciphertext.into(),
-        Err(error) => {
-            set_error(error_out, &*error);
-            vec![].into()
-        }
-    }
+    guard(
+        error_out,
+        || vec![].into(),
+        || {
+            let msg = unsafe { slice::from_raw_parts(msg_ptr, msg_len) };
+            let key = unsafe { slice::from_raw_parts(key_ptr, key_len) };
+            Ok(elgamal::encrypt(msg, &key)?.into())
+        },
+    )
 }
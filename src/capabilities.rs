@@ -0,0 +1,85 @@
+#[cfg(feature = "protocol")]
+use crate::security::{self, ProtocolType};
+
+/// The version of this library
+pub fn version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+
+/// A protocol compiled into this build
+#[cfg(feature = "protocol")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProtocolSupport {
+    pub protocol: ProtocolType,
+    /// Whether the shares can be computed with a smart card
+    pub card: bool,
+}
+
+/// The versions of the formats this build produces, each bumped on incompatible changes
+#[cfg(feature = "protocol")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WireVersions {
+    /// The messages exchanged with the server and, through it, with the other parties
+    pub messages: u32,
+    /// The states produced by `Session::seal`
+    pub sealed_state: u32,
+    /// The group blobs output by keygens
+    pub group: u32,
+}
+
+// NOTE: must match the arms of `protocol::keygen`
+#[cfg(feature = "protocol")]
+const PROTOCOLS: [(ProtocolType, bool, bool); 4] = [
+    (ProtocolType::Gg18, cfg!(feature = "gg18"), false),
+    (ProtocolType::Elgamal, cfg!(feature = "elgamal"), false),
+    (ProtocolType::Frost, cfg!(feature = "frost"), true),
+    (ProtocolType::Musig2, cfg!(feature = "musig2"), true),
+];
+
+/// The protocols compiled into this build
+#[cfg(feature = "protocol")]
+pub fn protocols() -> Vec<ProtocolSupport> {
+    PROTOCOLS
+        .iter()
+        .filter(|(_, compiled, _)| *compiled)
+        .map(|&(protocol, _, card)| ProtocolSupport { protocol, card })
+        .collect()
+}
+
+/// Describes the support of `protocol`, or returns `None` if it is compiled out
+#[cfg(feature = "protocol")]
+pub fn support(protocol: ProtocolType) -> Option<ProtocolSupport> {
+    protocols()
+        .into_iter()
+        .find(|support| support.protocol == protocol)
+}
+
+#[cfg(feature = "protocol")]
+pub fn wire_versions() -> WireVersions {
+    WireVersions {
+        messages: security::MESSAGES_VERSION,
+        sealed_state: security::SEALED_STATE_VERSION,
+        group: crate::protocol::GROUP_VERSION,
+    }
+}
+
+#[cfg(all(test, feature = "protocol"))]
+mod tests {
+    use super::*;
+    use crate::protocol;
+
+    #[test]
+    fn support_matches_keygens() {
+        for &(protocol_type, _, _) in &PROTOCOLS {
+            for with_card in [false, true] {
+                let supported =
+                    support(protocol_type).map_or(false, |support| !with_card || support.card);
+                assert_eq!(
+                    protocol::keygen(protocol_type, with_card).is_ok(),
+                    supported,
+                    "{protocol_type:?} with_card={with_card}"
+                );
+            }
+        }
+    }
+}
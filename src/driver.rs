@@ -0,0 +1,387 @@
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::error::Error;
+use crate::protocol::Recipient;
+use crate::security::AbortReason;
+use crate::session::{Output, Session};
+
+/// The errors of a driver, which may come from its session, its transports or an interruption,
+/// and which may be passed between threads, e.g. out of `tokio::spawn(driver.run())`
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// The errors of transports, which may be passed between threads
+pub type TransportResult<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Delivers the messages of a session to the server and back
+#[async_trait]
+pub trait Transport: Send {
+    /// Sends a message of share `index` to the server
+    async fn send(&mut self, index: usize, data: Vec<u8>) -> TransportResult<()>;
+
+    /// Waits for the next server message for share `index`
+    async fn receive(&mut self, index: usize) -> TransportResult<Vec<u8>>;
+}
+
+/// Delivers the commands of a session to a smart card
+#[async_trait]
+pub trait CardTransport: Send {
+    /// Transmits an APDU `command` and returns the response of the card
+    async fn transmit(&mut self, command: &[u8]) -> TransportResult<Vec<u8>>;
+}
+
+/// Why a driver stopped before its session finished
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interrupted {
+    /// The server or the card did not answer within the timeout
+    TimedOut,
+    /// The session was cancelled by the caller
+    Cancelled(AbortReason),
+}
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Interrupted::TimedOut => write!(f, "session timed out"),
+            Interrupted::Cancelled(_) => write!(f, "session cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+enum Stop {
+    Session(Error),
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+    Interrupted(Interrupted),
+}
+
+impl From<Stop> for Box<dyn std::error::Error + Send + Sync> {
+    fn from(stop: Stop) -> Self {
+        match stop {
+            Stop::Session(error) => error.into(),
+            Stop::Transport(error) => error,
+            Stop::Interrupted(interrupted) => interrupted.into(),
+        }
+    }
+}
+
+/// Runs a `Session` to completion, routing its messages to the server or a smart card
+pub struct Driver<T> {
+    session: Session,
+    transport: T,
+    card: Option<Box<dyn CardTransport>>,
+    timeout: Option<Duration>,
+}
+
+impl<T: Transport> Driver<T> {
+    pub fn new(session: Session, transport: T) -> Self {
+        Driver {
+            session,
+            transport,
+            card: None,
+            timeout: None,
+        }
+    }
+
+    /// The smart card computing the shares, required by sessions created `with_card`
+    pub fn card(mut self, card: impl CardTransport + 'static) -> Self {
+        self.card = Some(Box::new(card));
+        self
+    }
+
+    /// How long to wait for each answer of the server or the card, unlimited by default
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Runs the session until all of its shares finish, see `run_until`
+    pub async fn run(self) -> Result<Output> {
+        self.run_until(std::future::pending()).await
+    }
+
+    /// Runs the session until all of its shares finish or `cancel` completes.
+    /// Once the session fails, times out or is cancelled, the unfinished shares are aborted
+    /// and their abort messages are sent to the server
+    pub async fn run_until(mut self, cancel: impl Future<Output = AbortReason>) -> Result<Output> {
+        let stop = {
+            tokio::pin!(cancel);
+            tokio::select! {
+                result = self.drive() => result.err(),
+                reason = &mut cancel => Some(Stop::Interrupted(Interrupted::Cancelled(reason))),
+            }
+        };
+        match stop {
+            None => Ok(self.session.finish_with_receipts()?),
+            Some(stop) => {
+                let reason = match stop {
+                    Stop::Interrupted(Interrupted::Cancelled(reason)) => reason,
+                    _ => AbortReason::Error,
+                };
+                self.abort_all(reason).await;
+                Err(stop.into())
+            }
+        }
+    }
+
+    /// Advances the unfinished shares in lockstep, since the server waits for all of them
+    /// before it starts the next round
+    async fn drive(&mut self) -> std::result::Result<(), Stop> {
+        loop {
+            let mut pending = false;
+            for index in 0..self.session.share_count() {
+                if self.finished(index) {
+                    continue;
+                }
+                pending = true;
+                let data = timed(self.timeout, self.transport.receive(index)).await?;
+                let mut response = self.session.advance(index, &data).map_err(Stop::Session)?;
+                while response.recipient == Recipient::Card {
+                    let card = self
+                        .card
+                        .as_mut()
+                        .ok_or_else(|| Stop::Session(Error::misuse("session requires a card")))?;
+                    let data = timed(self.timeout, card.transmit(&response.data)).await?;
+                    response = self.session.advance(index, &data).map_err(Stop::Session)?;
+                }
+                timed(self.timeout, self.transport.send(index, response.data)).await?;
+            }
+            if !pending {
+                return Ok(());
+            }
+        }
+    }
+
+    fn finished(&self, index: usize) -> bool {
+        self.session
+            .status(index)
+            .map_or(true, |status| status.finished)
+    }
+
+    async fn abort_all(&mut self, reason: AbortReason) {
+        for index in 0..self.session.share_count() {
+            if self.finished(index) {
+                continue;
+            }
+            // NOTE: shares aborted by other parties have nothing to send
+            let message = match self.session.abort(index, reason) {
+                Ok(message) => message,
+                Err(_) => continue,
+            };
+            // NOTE: the server may be what failed, so the aborts are sent on a best-effort basis
+            let _ = timed(self.timeout, self.transport.send(index, message)).await;
+        }
+    }
+}
+
+async fn timed<T>(
+    timeout: Option<Duration>,
+    operation: impl Future<Output = TransportResult<T>>,
+) -> std::result::Result<T, Stop> {
+    let result = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, operation)
+            .await
+            .map_err(|_| Stop::Interrupted(Interrupted::TimedOut))?,
+        None => operation.await,
+    };
+    result.map_err(Stop::Transport)
+}
+
+#[cfg(all(test, feature = "elgamal"))]
+mod tests {
+    use super::*;
+    use crate::proto::{ClientMessage, ProtocolGroupInit, ProtocolInit};
+    use crate::protocol::{elgamal, inspect_group};
+    use crate::security::tests::{route, Identities};
+    use crate::security::ProtocolType;
+    use prost::Message as _;
+    use std::collections::{HashMap, VecDeque};
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+    /// A server which collects a message from every party in each round and routes them,
+    /// unless it has `scripted` messages to send instead
+    struct Hub {
+        protocol_type: ProtocolType,
+        round: HashMap<u32, Vec<u8>>,
+        scripted: VecDeque<HashMap<u32, Vec<u8>>>,
+        inboxes: HashMap<u32, UnboundedSender<Vec<u8>>>,
+    }
+
+    impl Hub {
+        fn deliver(&mut self, messages: HashMap<u32, Vec<u8>>) {
+            for (index, message) in messages {
+                let _ = self.inboxes[&index].send(message);
+            }
+        }
+    }
+
+    struct Party {
+        index: u32,
+        hub: Arc<Mutex<Hub>>,
+        inbox: UnboundedReceiver<Vec<u8>>,
+    }
+
+    #[async_trait]
+    impl Transport for Party {
+        async fn send(&mut self, _: usize, data: Vec<u8>) -> TransportResult<()> {
+            let mut hub = self.hub.lock().unwrap();
+            hub.round.insert(self.index, data);
+            if hub.round.len() == hub.inboxes.len() {
+                let outputs = std::mem::take(&mut hub.round);
+                let messages = match hub.scripted.pop_front() {
+                    Some(messages) => messages,
+                    None => route(hub.protocol_type, &outputs),
+                };
+                hub.deliver(messages);
+            }
+            Ok(())
+        }
+
+        async fn receive(&mut self, _: usize) -> TransportResult<Vec<u8>> {
+            self.inbox.recv().await.ok_or_else(|| "hub closed".into())
+        }
+    }
+
+    fn connect(
+        protocol_type: ProtocolType,
+        indices: &[u32],
+        scripted: Vec<HashMap<u32, Vec<u8>>>,
+    ) -> (Arc<Mutex<Hub>>, Vec<Party>) {
+        let (senders, receivers): (HashMap<_, _>, Vec<_>) = indices
+            .iter()
+            .map(|&index| {
+                let (sender, receiver) = unbounded_channel();
+                ((index, sender), (index, receiver))
+            })
+            .unzip();
+        let mut hub = Hub {
+            protocol_type,
+            round: HashMap::new(),
+            scripted: scripted.into(),
+            inboxes: senders,
+        };
+        let first = hub.scripted.pop_front().unwrap();
+        hub.deliver(first);
+
+        let hub = Arc::new(Mutex::new(hub));
+        let parties = receivers
+            .into_iter()
+            .map(|(index, inbox)| Party {
+                index,
+                hub: hub.clone(),
+                inbox,
+            })
+            .collect();
+        (hub, parties)
+    }
+
+    fn keygen_script(indices: &[u32]) -> Vec<HashMap<u32, Vec<u8>>> {
+        let certs = indices.iter().map(|&i| (i, Vec::new())).collect();
+        let inits = indices
+            .iter()
+            .map(|&index| {
+                let init = ProtocolGroupInit {
+                    protocol_type: ProtocolType::Elgamal.into(),
+                    index,
+                    parties: indices.len() as u32,
+                    threshold: indices.len() as u32,
+                    ..Default::default()
+                };
+                (index, init.encode_to_vec())
+            })
+            .collect();
+        vec![certs, inits]
+    }
+
+    fn keygen(ids: &Identities, indices: &[u32], index: u32) -> Session {
+        Session::keygen(ProtocolType::Elgamal)
+            .certs(ids.certs(indices))
+            .identity(ids.pkcs12s[&index].clone())
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn drives_elgamal() {
+        let indices = [0, 1];
+        let ids = Identities::issue(indices);
+
+        let (_, mut parties) = connect(ProtocolType::Elgamal, &indices, keygen_script(&indices));
+        let first = Driver::new(keygen(&ids, &indices, 0), parties.remove(0));
+        let second = Driver::new(keygen(&ids, &indices, 1), parties.remove(0));
+        let (first, second) = tokio::join!(first.run(), second.run());
+        let groups = [first.unwrap().results, second.unwrap().results];
+
+        let key = inspect_group(&groups[0]).unwrap().public_key;
+        let ciphertext = elgamal::encrypt(b"hello", &key).unwrap();
+        let inits = indices
+            .iter()
+            .map(|&index| {
+                let init = ProtocolInit {
+                    protocol_type: ProtocolType::Elgamal.into(),
+                    indices: indices.to_vec(),
+                    index,
+                    data: ciphertext.clone(),
+                    ..Default::default()
+                };
+                (index, init.encode_to_vec())
+            })
+            .collect();
+        let (_, mut parties) = connect(ProtocolType::Elgamal, &indices, vec![inits]);
+        let mut decrypt = |index: u32| {
+            let session = Session::decrypt(groups[index as usize].clone())
+                .certs(ids.certs(&indices))
+                .identity(ids.pkcs12s[&index].clone())
+                .build()
+                .unwrap();
+            Driver::new(session, parties.remove(0)).timeout(Duration::from_secs(10))
+        };
+        let first = tokio::spawn(decrypt(0).run());
+        let second = tokio::spawn(decrypt(1).run());
+        let (first, second) = tokio::join!(first, second);
+        assert_eq!(first.unwrap().unwrap().results, vec![b"hello".to_vec()]);
+        assert_eq!(second.unwrap().unwrap().results, vec![b"hello".to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn interrupts_and_aborts() {
+        let indices = [0, 1];
+        let ids = Identities::issue(indices);
+        let sent_abort = |hub: &Arc<Mutex<Hub>>| {
+            let message = hub.lock().unwrap().round.remove(&0).unwrap();
+            ClientMessage::decode(message.as_slice())
+                .unwrap()
+                .abort
+                .is_some()
+        };
+
+        // NOTE: party 1 never answers, so party 0 waits for the group init forever
+        let (hub, mut parties) = connect(ProtocolType::Elgamal, &indices, keygen_script(&indices));
+        let driver = Driver::new(keygen(&ids, &indices, 0), parties.remove(0))
+            .timeout(Duration::from_millis(50));
+        let error = driver.run().await.err().unwrap();
+        assert_eq!(
+            error.downcast_ref::<Interrupted>(),
+            Some(&Interrupted::TimedOut)
+        );
+        assert!(sent_abort(&hub));
+
+        let (hub, mut parties) = connect(ProtocolType::Elgamal, &indices, keygen_script(&indices));
+        let driver = Driver::new(keygen(&ids, &indices, 0), parties.remove(0));
+        let cancel = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            AbortReason::Declined
+        };
+        let error = driver.run_until(cancel).await.err().unwrap();
+        assert_eq!(
+            error.downcast_ref::<Interrupted>(),
+            Some(&Interrupted::Cancelled(AbortReason::Declined))
+        );
+        assert!(sent_abort(&hub));
+    }
+}
@@ -0,0 +1,149 @@
+use std::fmt;
+
+/// The errors of this library, classified so that client apps can react to them.
+/// The errors of our dependencies are converted where they occur
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// An operation was requested in a state which does not allow it
+    Misuse(String),
+    /// An input could not be parsed or does not fit the session
+    MalformedInput(String),
+    /// A cryptographic check failed, caused by party `culprit` if it is known
+    Crypto {
+        culprit: Option<u32>,
+        message: String,
+    },
+    /// A smart card returned the error status word `status`
+    Card { status: u16 },
+    /// A state could not be serialized or deserialized
+    Serialization(String),
+    /// Party `party` aborted the protocol, possibly because its user `declined`
+    Aborted { party: u32, declined: bool },
+    /// The library panicked, which is a bug on our side
+    Panic(String),
+}
+
+impl Error {
+    pub fn misuse(message: impl Into<String>) -> Self {
+        Error::Misuse(message.into())
+    }
+
+    pub fn malformed(message: impl Into<String>) -> Self {
+        Error::MalformedInput(message.into())
+    }
+
+    pub fn crypto(message: impl Into<String>) -> Self {
+        Error::Crypto {
+            culprit: None,
+            message: message.into(),
+        }
+    }
+
+    /// A cryptographic failure caused by party `culprit`
+    pub fn blame(culprit: u32, message: impl Into<String>) -> Self {
+        Error::Crypto {
+            culprit: Some(culprit),
+            message: message.into(),
+        }
+    }
+
+    pub fn serialization(message: impl Into<String>) -> Self {
+        Error::Serialization(message.into())
+    }
+
+    /// Blames party `culprit` for a cryptographic failure which is not blamed on anyone yet
+    pub(crate) fn blaming(self, culprit: u32) -> Self {
+        match self {
+            Error::Crypto {
+                culprit: None,
+                message,
+            } => Error::Crypto {
+                culprit: Some(culprit),
+                message,
+            },
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Misuse(message)
+            | Error::MalformedInput(message)
+            | Error::Crypto {
+                culprit: None,
+                message,
+            }
+            | Error::Serialization(message) => write!(f, "{message}"),
+            Error::Crypto {
+                culprit: Some(culprit),
+                message,
+            } => write!(f, "{message} (caused by party {culprit})"),
+            Error::Card { status } => write!(f, "card returned error status {status:#x}"),
+            Error::Aborted {
+                party,
+                declined: false,
+            } => write!(f, "protocol aborted by party {party}"),
+            Error::Aborted {
+                party,
+                declined: true,
+            } => write!(f, "protocol declined by party {party}"),
+            Error::Panic(message) => write!(f, "internal error: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<prost::DecodeError> for Error {
+    fn from(error: prost::DecodeError) -> Self {
+        Error::malformed(error.to_string())
+    }
+}
+
+impl From<der::Error> for Error {
+    fn from(error: der::Error) -> Self {
+        Error::malformed(error.to_string())
+    }
+}
+
+// NOTE: values received from other parties are parsed by `util::deserialize`,
+// which reports them as malformed instead
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::serialization(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_are_converted() {
+        let error: Error = serde_json::from_slice::<u32>(b"{").unwrap_err().into();
+        assert!(matches!(error, Error::Serialization(_)));
+
+        let error: Error = <crate::proto::SignedMessage as prost::Message>::decode(&b"\xff"[..])
+            .unwrap_err()
+            .into();
+        assert!(matches!(error, Error::MalformedInput(_)));
+    }
+
+    #[test]
+    fn errors_are_blamed() {
+        assert_eq!(
+            Error::crypto("signature mismatch").blaming(2),
+            Error::blame(2, "signature mismatch")
+        );
+        assert_eq!(
+            Error::blame(1, "signature mismatch").blaming(2),
+            Error::blame(1, "signature mismatch")
+        );
+        assert_eq!(
+            Error::malformed("duplicate participants").blaming(2),
+            Error::malformed("duplicate participants")
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use crate::error::Error;
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, KeyInit, Nonce};
+use hkdf::Hkdf;
+use ml_dsa::signature::{Signer as _, Verifier as _};
+use ml_dsa::{EncodedSignature, EncodedSigningKey, EncodedVerifyingKey, KeyGen, MlDsa65};
+use ml_kem::kem::{Decapsulate, Encapsulate};
+use ml_kem::{Ciphertext, Encoded, EncodedSizeUser, KemCore, MlKem768};
+use rand::rngs::OsRng;
+use sha2::Sha256;
+use std::convert::TryFrom;
+use zeroize::Zeroizing;
+
+type Result<T> = std::result::Result<T, Error>;
+
+type DecapsulationKey = <MlKem768 as KemCore>::DecapsulationKey;
+type EncapsulationKey = <MlKem768 as KemCore>::EncapsulationKey;
+
+const ML_KEM_INFO: &[u8] = b"meesign ml-kem-768 chacha20poly1305";
+
+/// Generates an ML-DSA-65 key pair, returning the encoded private and public key
+pub(crate) fn generate_signing_key() -> (Zeroizing<Vec<u8>>, Vec<u8>) {
+    let key_pair = MlDsa65::key_gen(&mut OsRng);
+    (
+        Zeroizing::new(key_pair.signing_key().encode().to_vec()),
+        key_pair.verifying_key().encode().to_vec(),
+    )
+}
+
+/// Generates an ML-KEM-768 key pair, returning the encoded private and public key
+pub(crate) fn generate_encryption_key() -> (Zeroizing<Vec<u8>>, Vec<u8>) {
+    let (private_key, public_key) = MlKem768::generate(&mut OsRng);
+    (
+        Zeroizing::new(private_key.as_bytes().to_vec()),
+        public_key.as_bytes().to_vec(),
+    )
+}
+
+/// An ML-DSA-65 key producing co-signatures of broadcasts
+// NOTE: ml-dsa keys cannot be wiped, so only the encoded key is kept and
+// the expanded key lives just for the duration of a signature
+pub(crate) struct PqSigningKey(Zeroizing<Vec<u8>>);
+
+impl PqSigningKey {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        EncodedSigningKey::<MlDsa65>::try_from(bytes)
+            .map_err(|_| Error::malformed("invalid ML-DSA key"))?;
+        Ok(Self(Zeroizing::new(bytes.to_vec())))
+    }
+
+    pub fn sign(&self, msg: &[u8]) -> Vec<u8> {
+        let encoded = EncodedSigningKey::<MlDsa65>::try_from(self.0.as_slice())
+            .expect("checked in from_bytes");
+        ml_dsa::SigningKey::<MlDsa65>::decode(&encoded)
+            .sign(msg)
+            .encode()
+            .to_vec()
+    }
+}
+
+/// An ML-DSA-65 key verifying co-signatures of broadcasts
+#[derive(Clone)]
+pub(crate) struct PqVerifyingKey(ml_dsa::VerifyingKey<MlDsa65>);
+
+impl PqVerifyingKey {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let encoded = EncodedVerifyingKey::<MlDsa65>::try_from(bytes)
+            .map_err(|_| Error::malformed("invalid ML-DSA key"))?;
+        Ok(Self(ml_dsa::VerifyingKey::decode(&encoded)))
+    }
+
+    pub fn verify(&self, msg: &[u8], signature: &[u8]) -> Result<()> {
+        let signature = EncodedSignature::<MlDsa65>::try_from(signature)
+            .ok()
+            .and_then(|encoded| ml_dsa::Signature::decode(&encoded))
+            .ok_or_else(|| Error::crypto("invalid ML-DSA signature"))?;
+        self.0
+            .verify(msg, &signature)
+            .map_err(|_| Error::crypto("ML-DSA signature verification failed"))
+    }
+}
+
+/// Wraps an already encrypted `msg` under a key encapsulated to the ML-KEM `public_key`,
+/// so that it stays confidential as long as either layer holds
+pub(crate) fn encrypt(public_key: &[u8], msg: &[u8]) -> Result<Vec<u8>> {
+    let public_key = Encoded::<EncapsulationKey>::try_from(public_key)
+        .map_err(|_| Error::malformed("invalid ML-KEM key"))?;
+    let public_key = EncapsulationKey::from_bytes(&public_key);
+    let (encapsulated, shared) = public_key
+        .encapsulate(&mut OsRng)
+        .map_err(|_| Error::crypto("failed to encrypt unicast"))?;
+
+    let cipher = ml_kem_cipher(&shared, &encapsulated)?;
+    // NOTE: each message is encrypted under a fresh key, so a fixed nonce is sound
+    let ciphertext = cipher
+        .encrypt(&Nonce::default(), msg)
+        .map_err(|_| Error::crypto("failed to encrypt unicast"))?;
+    Ok([encapsulated.as_slice(), ciphertext.as_slice()].concat())
+}
+
+/// Unwraps a message produced by `encrypt` with the encoded ML-KEM `private_key`
+pub(crate) fn decrypt(private_key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    let encapsulated_len = Ciphertext::<MlKem768>::default().len();
+    if ciphertext.len() < encapsulated_len {
+        return Err(Error::crypto("unicast compromised"));
+    }
+    let (encapsulated, ciphertext) = ciphertext.split_at(encapsulated_len);
+    let encapsulated = Ciphertext::<MlKem768>::try_from(encapsulated)
+        .map_err(|_| Error::crypto("unicast compromised"))?;
+
+    let private_key = Encoded::<DecapsulationKey>::try_from(private_key)
+        .map_err(|_| Error::malformed("invalid ML-KEM key"))?;
+    let private_key = DecapsulationKey::from_bytes(&private_key);
+    let shared = private_key
+        .decapsulate(&encapsulated)
+        .map_err(|_| Error::crypto("unicast compromised"))?;
+
+    let cipher = ml_kem_cipher(&shared, &encapsulated)?;
+    cipher
+        .decrypt(&Nonce::default(), ciphertext)
+        .map_err(|_| Error::crypto("unicast compromised"))
+}
+
+/// Derives the cipher of one message from an ML-KEM shared secret bound to its encapsulation
+fn ml_kem_cipher(shared: &[u8], encapsulated: &[u8]) -> Result<ChaCha20Poly1305> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(Some(encapsulated), shared)
+        .expand(ML_KEM_INFO, &mut key[..])
+        .map_err(|_| Error::crypto("failed to derive unicast key"))?;
+    Ok(ChaCha20Poly1305::new_from_slice(&key[..])
+        .map_err(|_| Error::crypto("invalid unicast key"))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hybrid_roundtrip() {
+        let (private_key, public_key) = generate_signing_key();
+        let signing_key = PqSigningKey::from_bytes(&private_key).unwrap();
+        let verifying_key = PqVerifyingKey::from_bytes(&public_key).unwrap();
+        let signature = signing_key.sign(b"hello");
+        assert!(verifying_key.verify(b"hello", &signature).is_ok());
+        assert!(verifying_key.verify(b"world", &signature).is_err());
+        assert!(verifying_key.verify(b"hello", &signature[1..]).is_err());
+
+        let (private_key, public_key) = generate_encryption_key();
+        let ciphertext = encrypt(&public_key, b"hello").unwrap();
+        assert_eq!(decrypt(&private_key, &ciphertext).unwrap(), b"hello");
+        let (other_key, _) = generate_encryption_key();
+        assert!(decrypt(&other_key, &ciphertext).is_err());
+        assert!(decrypt(&private_key, &ciphertext[..16]).is_err());
+    }
+}
@@ -1,10 +1,29 @@
 pub mod auth;
 pub mod c_api;
+pub mod capabilities;
+#[cfg(feature = "driver")]
+pub mod driver;
+pub mod error;
+mod hybrid;
 pub mod protocol;
+#[cfg(feature = "python")]
+mod python;
 #[cfg(feature = "protocol")]
 pub mod security;
 #[cfg(feature = "protocol")]
+pub mod session;
+pub mod suite;
+#[cfg(feature = "uniffi")]
+mod uniffi_api;
+#[cfg(feature = "protocol")]
 pub mod util;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+#[cfg(feature = "uniffi")]
+use uniffi_api::*;
+#[cfg(feature = "uniffi")]
+uniffi::include_scaffolding!("meesign_crypto");
 
 pub mod proto {
     pub use prost::Message;
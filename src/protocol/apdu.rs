@@ -62,11 +62,43 @@ impl fmt::Display for CardError {
 
 impl Error for CardError {}
 
-pub fn parse_response(raw: &[u8]) -> Result<&[u8], CardError> {
+impl From<CardError> for crate::error::Error {
+    fn from(error: CardError) -> Self {
+        crate::error::Error::Card {
+            status: error.status,
+        }
+    }
+}
+
+/// Splits the data of a card response from its status word, which has to report no error
+pub fn parse_response(raw: &[u8]) -> Result<&[u8], crate::error::Error> {
+    if raw.len() < 2 {
+        return Err(crate::error::Error::malformed("card response too short"));
+    }
     let (data, status) = raw.split_at(raw.len() - 2);
     let status = u16::from_be_bytes(status.try_into().unwrap());
     match status {
         iso7816::SW_NO_ERROR => Ok(data),
-        _ => Err(CardError { status }),
+        _ => Err(CardError { status }.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    #[test]
+    fn responses() {
+        assert_eq!(parse_response(b"\x01\x90\x00").unwrap(), b"\x01");
+        assert!(matches!(
+            parse_response(b"\x6a\x80"),
+            Err(Error::Card { status: 0x6a80 })
+        ));
+        assert!(matches!(
+            parse_response(b"\x90"),
+            Err(Error::MalformedInput(_))
+        ));
+        assert!(matches!(parse_response(b""), Err(Error::MalformedInput(_))));
     }
 }
@@ -1,3 +1,4 @@
+use crate::error::Error;
 use crate::protocol::*;
 use aes_gcm::{
     aead::{Aead, AeadCore, KeyInit, Payload},
@@ -12,6 +13,7 @@ use elastic_elgamal::{
     PublicKey,
 };
 use rand::rngs::OsRng;
+use zeroize::Zeroize;
 
 pub(crate) fn try_encode(message: &[u8]) -> Option<RistrettoPoint> {
     if message.len() > 30 {
@@ -22,6 +24,7 @@ pub(crate) fn try_encode(message: &[u8]) -> Option<RistrettoPoint> {
     message_buffer[0] = message.len() as u8;
     message_buffer[1..(message.len() + 1)].copy_from_slice(message);
     let mut scalar = Scalar::from_bytes_mod_order(message_buffer);
+    message_buffer.zeroize();
 
     let offset = Scalar::from(2u32.pow(8));
     scalar *= offset;
@@ -37,14 +40,16 @@ pub(crate) fn try_encode(message: &[u8]) -> Option<RistrettoPoint> {
 }
 
 pub fn encrypt(msg: &[u8], pk: &[u8]) -> Result<Vec<u8>> {
-    let pk: PublicKey<Ristretto> = PublicKey::from_bytes(pk).unwrap();
-    let key = Aes128Gcm::generate_key(&mut OsRng);
+    let pk: PublicKey<Ristretto> =
+        PublicKey::from_bytes(pk).map_err(|_| Error::malformed("invalid encryption key"))?;
+    let mut key = Aes128Gcm::generate_key(&mut OsRng);
 
     let encoded_key: <Ristretto as ElementOps>::Element =
-        try_encode(&key).ok_or("encoding failed")?;
+        try_encode(&key).ok_or_else(|| Error::crypto("encoding failed"))?;
     let encrypted_key = serde_json::to_vec(&pk.encrypt_element(encoded_key, &mut OsRng))?;
 
     let cipher = Aes128Gcm::new(&key);
+    key.as_mut_slice().zeroize();
     let nonce = Aes128Gcm::generate_nonce(&mut OsRng);
     let ct = cipher
         .encrypt(
@@ -1,6 +1,7 @@
+use crate::error::Error;
 use crate::proto::{ProtocolGroupInit, ProtocolInit, ProtocolType, ServerMessage};
 use crate::protocol::*;
-use crate::util::{deserialize_map, Message};
+use crate::util::{deserialize, deserialize_map, Message};
 use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar};
 use elastic_elgamal::{
     dkg::*,
@@ -12,12 +13,17 @@ use rand::rngs::OsRng;
 
 use aes_gcm::{
     aead::{Aead, KeyInit, Payload},
-    Aes128Gcm,
+    Aes128Gcm, Key,
 };
 use prost::Message as _;
 use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+/// The lengths of the AES-128-GCM key and nonce encrypting the data
+const KEY_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
 
 #[derive(Serialize, Deserialize)]
 pub(crate) struct KeygenContext {
@@ -38,7 +44,7 @@ impl KeygenContext {
         let msg = ProtocolGroupInit::decode(data)?;
 
         if msg.protocol_type != ProtocolType::Elgamal as i32 {
-            return Err("wrong protocol type".into());
+            return Err(Error::misuse("wrong protocol type"));
         }
 
         let (parties, threshold, index) =
@@ -58,7 +64,7 @@ impl KeygenContext {
         let msgs = ServerMessage::decode(data)?;
 
         let (c, msg) = match &self.round {
-            KeygenRound::R0 => return Err("protocol not initialized".into()),
+            KeygenRound::R0 => return Err(Error::misuse("protocol not initialized")),
             KeygenRound::R1(dkg) => {
                 let mut dkg = dkg.clone();
                 let data = deserialize_map(&msgs.broadcasts)?;
@@ -66,7 +72,7 @@ impl KeygenContext {
                     dkg.insert_commitment(i as usize, msg);
                 }
                 if dkg.missing_commitments().next().is_some() {
-                    return Err("not enough commitments".into());
+                    return Err(Error::malformed("not enough commitments"));
                 }
                 let dkg = dkg.finish_commitment_phase();
                 let public_info = dkg.public_info();
@@ -78,10 +84,11 @@ impl KeygenContext {
                 let mut dkg = dkg.clone();
                 let data = deserialize_map(&msgs.broadcasts)?;
                 for (i, msg) in data {
-                    dkg.insert_public_polynomial(i as usize, msg)?
+                    dkg.insert_public_polynomial(i as usize, msg)
+                        .map_err(|error| Error::crypto(error.to_string()))?
                 }
                 if dkg.missing_public_polynomials().next().is_some() {
-                    return Err("not enough polynomials".into());
+                    return Err(Error::malformed("not enough polynomials"));
                 }
                 let dkg = dkg.finish_polynomials_phase();
 
@@ -98,18 +105,21 @@ impl KeygenContext {
                 let mut dkg = dkg.clone();
                 let data = deserialize_map(&msgs.unicasts)?;
                 for (i, msg) in data {
-                    dkg.insert_secret_share(i as usize, msg)?;
+                    dkg.insert_secret_share(i as usize, msg)
+                        .map_err(|error| Error::crypto(error.to_string()))?;
                 }
                 if dkg.missing_shares().next().is_some() {
-                    return Err("not enough shares".into());
+                    return Err(Error::malformed("not enough shares"));
                 }
-                let dkg = dkg.complete()?;
+                let dkg = dkg
+                    .complete()
+                    .map_err(|error| Error::crypto(error.to_string()))?;
 
                 let msg =
                     Message::raw_reliable_broadcast(dkg.key_set().shared_key().as_bytes().to_vec());
                 (KeygenRound::Done(dkg), msg)
             }
-            KeygenRound::Done(_) => return Err("protocol already finished".into()),
+            KeygenRound::Done(_) => return Err(Error::misuse("protocol already finished")),
         };
 
         self.round = c;
@@ -129,8 +139,35 @@ impl Protocol for KeygenContext {
 
     fn finish(self: Box<Self>) -> Result<Vec<u8>> {
         match self.round {
-            KeygenRound::Done(ctx) => Ok(serde_json::to_vec(&ctx)?),
-            _ => Err("protocol not finished".into()),
+            KeygenRound::Done(ctx) => {
+                encode_group(ProtocolType::Elgamal, Curve::Ristretto255, &ctx)
+            }
+            _ => Err(Error::misuse("protocol not finished")),
+        }
+    }
+
+    fn round(&self) -> &'static str {
+        match &self.round {
+            KeygenRound::R0 => "R0",
+            KeygenRound::R1(..) => "R1",
+            KeygenRound::R2(..) => "R2",
+            KeygenRound::R3(..) => "R3",
+            KeygenRound::Done(..) => "Done",
+        }
+    }
+
+    fn rounds(&self) -> u16 {
+        super::KEYGEN_ROUNDS
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(self.round, KeygenRound::Done(..))
+    }
+
+    fn group_key(&self) -> Option<Vec<u8>> {
+        match &self.round {
+            KeygenRound::Done(ctx) => Some(ctx.key_set().shared_key().as_bytes().to_vec()),
+            _ => None,
         }
     }
 }
@@ -157,11 +194,15 @@ impl DecryptContext {
         let msg = ProtocolInit::decode(data)?;
 
         if msg.protocol_type != ProtocolType::Elgamal as i32 {
-            return Err("wrong protocol type".into());
+            return Err(Error::misuse("wrong protocol type"));
         }
 
-        self.data = serde_json::from_slice(&msg.data)?;
-        self.encrypted_key = serde_json::from_slice(&self.data.0)?;
+        let data: (Vec<u8>, Vec<u8>, Vec<u8>) = deserialize(&msg.data)?;
+        if data.1.len() != NONCE_LEN {
+            return Err(Error::malformed("invalid nonce length"));
+        }
+        self.encrypted_key = deserialize(&data.0)?;
+        self.data = data;
 
         let (share, proof) = self.ctx.decrypt_share(self.encrypted_key, &mut OsRng);
 
@@ -176,40 +217,45 @@ impl DecryptContext {
 
     fn update(&mut self, data: &[u8]) -> Result<Message> {
         if self.shares.is_empty() {
-            return Err("protocol not initialized".into());
+            return Err(Error::misuse("protocol not initialized"));
         }
         if self.result.is_some() {
-            return Err("protocol already finished".into());
+            return Err(Error::misuse("protocol already finished"));
         }
 
         let msgs = ServerMessage::decode(data)?;
 
         let data: HashMap<u32, Vec<u8>> = deserialize_map(&msgs.broadcasts)?;
+        let key_set = self.ctx.key_set();
+        // NOTE: the shares are kept only once the decryption succeeds
+        let mut shares = self.shares.clone();
         for (i, msg) in data {
+            if i as usize >= key_set.participant_keys().len() {
+                return Err(Error::malformed(format!("no party {i} in the group")));
+            }
             let msg: (VerifiableDecryption<Ristretto>, LogEqualityProof<Ristretto>) =
-                serde_json::from_slice(&msg)?;
-            self.ctx
-                .key_set()
+                deserialize(&msg)?;
+            key_set
                 .verify_share(msg.0.into(), self.encrypted_key, i as usize, &msg.1)
-                .unwrap();
-            self.shares.push((i as usize, msg.0));
+                .map_err(|_| Error::blame(i, "invalid decryption share"))?;
+            shares.push((i as usize, msg.0));
         }
 
-        let mut key = [0u8; 16];
-        key.copy_from_slice(&decode(
-            self.encrypted_key.blinded_element()
-                - self
-                    .ctx
-                    .key_set()
-                    .params()
-                    .combine_shares(self.shares.clone())
-                    .unwrap()
-                    .as_element(),
-        ));
-        let mut nonce = [0u8; 12];
+        let combined = key_set
+            .params()
+            .combine_shares(shares.clone())
+            .ok_or_else(|| Error::malformed("not enough decryption shares"))?;
+        let decoded = Zeroizing::new(
+            decode(self.encrypted_key.blinded_element() - combined.as_element())
+                .filter(|decoded| decoded.len() == KEY_LEN)
+                .ok_or_else(|| Error::crypto("encrypted key compromised"))?,
+        );
+        let mut key = Zeroizing::new([0u8; KEY_LEN]);
+        key.copy_from_slice(&decoded);
+        let mut nonce = [0u8; NONCE_LEN];
         nonce.copy_from_slice(&self.data.1);
 
-        let cipher = Aes128Gcm::new(&key.into());
+        let cipher = Aes128Gcm::new(Key::<Aes128Gcm>::from_slice(&key[..]));
 
         let msg = cipher
             .decrypt(
@@ -219,8 +265,9 @@ impl DecryptContext {
                     aad: &self.data.0,
                 },
             )
-            .unwrap();
+            .map_err(|_| Error::crypto("ciphertext compromised"))?;
 
+        self.shares = shares;
         self.result = Some(msg.clone());
 
         let msg = Message::raw_broadcast(msg);
@@ -241,28 +288,71 @@ impl Protocol for DecryptContext {
 
     fn finish(self: Box<Self>) -> Result<Vec<u8>> {
         if self.result.is_none() {
-            return Err("protocol not finished".into());
+            return Err(Error::misuse("protocol not finished"));
         }
         Ok(self.result.unwrap())
     }
+
+    fn round(&self) -> &'static str {
+        match (&self.result, self.shares.is_empty()) {
+            (Some(_), _) => "Done",
+            (None, true) => "R0",
+            (None, false) => "R1",
+        }
+    }
+
+    fn rounds(&self) -> u16 {
+        super::DECRYPT_ROUNDS
+    }
+
+    fn is_finished(&self) -> bool {
+        self.result.is_some()
+    }
+
+    fn group_params(&self) -> Option<GroupParams> {
+        let params = self.ctx.key_set().params();
+        Some(GroupParams {
+            parties: params.shares as u32,
+            threshold: params.threshold as u32,
+            index: self.ctx.index() as u32,
+        })
+    }
+
+    fn group_keys(&self) -> Option<GroupKeys> {
+        let key_set = self.ctx.key_set();
+        let verifying_shares = key_set
+            .participant_keys()
+            .iter()
+            .enumerate()
+            .map(|(index, key)| (index as u32, key.as_bytes().to_vec()))
+            .collect();
+        Some(GroupKeys {
+            public_key: key_set.shared_key().as_bytes().to_vec(),
+            verifying_shares,
+            with_card: false,
+        })
+    }
 }
 
 impl ThresholdProtocol for DecryptContext {
-    fn new(group: &[u8]) -> Self {
-        Self {
-            ctx: serde_json::from_slice(group).expect("could not deserialize group context"),
+    fn new(group: &[u8]) -> Result<Self> {
+        Ok(Self {
+            ctx: decode_group(ProtocolType::Elgamal, group)?,
             encrypted_key: Ciphertext::zero(),
             data: (Vec::new(), Vec::new(), Vec::new()),
             shares: Vec::new(),
             result: None,
-        }
+        })
     }
 }
 
-fn decode(p: RistrettoPoint) -> Vec<u8> {
+/// Decodes a message encoded by `encrypt::try_encode`, unless `p` encodes none
+fn decode(p: RistrettoPoint) -> Option<Vec<u8>> {
     let scalar = Scalar::from_bytes_mod_order(p.compress().to_bytes());
     let scalar_bytes = &scalar.as_bytes()[1..];
-    scalar_bytes[1..(scalar_bytes[0] as usize + 1)].to_vec()
+    scalar_bytes
+        .get(1..(scalar_bytes[0] as usize + 1))
+        .map(<[u8]>::to_vec)
 }
 
 #[cfg(test)]
@@ -286,7 +376,7 @@ mod tests {
     fn test_encode() {
         let message = b"hello";
         let point = try_encode(message).unwrap();
-        let decoded = decode(point);
+        let decoded = decode(point).unwrap();
         assert_eq!(message, decoded.as_slice());
     }
 
@@ -329,4 +419,81 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn invalid_share_is_blamed() {
+        let (pks, ctxs) = <KeygenContext as KeygenProtocolTest>::run(2, 2);
+        let ct = encrypt(b"hello", &pks[&0]).unwrap();
+        let init = |index: u32, data: Vec<u8>| {
+            ProtocolInit {
+                protocol_type: ProtocolType::Elgamal as i32,
+                indices: vec![0, 1],
+                index,
+                data,
+                echo_mode: crate::proto::EchoMode::Full as i32,
+                blind_broadcasts: false,
+            }
+            .encode_to_vec()
+        };
+
+        let mut ctx = DecryptContext::new(&ctxs[&0]).unwrap();
+        let (key, _, data): (Vec<u8>, Vec<u8>, Vec<u8>) = deserialize(&ct).unwrap();
+        let short_nonce = serde_json::to_vec(&(key, vec![0u8; 4], data)).unwrap();
+        assert!(matches!(
+            ctx.advance(&init(0, short_nonce)),
+            Err(Error::MalformedInput(_))
+        ));
+
+        // NOTE: party 1 replays the share of party 0, whose proof does not hold for it
+        let share = ctx
+            .advance(&init(0, ct))
+            .unwrap()
+            .encode(ProtocolType::Elgamal)
+            .broadcast
+            .unwrap();
+        let msgs = ServerMessage {
+            protocol_type: ProtocolType::Elgamal as i32,
+            unicasts: HashMap::new(),
+            broadcasts: HashMap::from([(1, share)]),
+            aborts: HashMap::new(),
+        };
+        assert!(matches!(
+            ctx.advance(&msgs.encode_to_vec()),
+            Err(Error::Crypto {
+                culprit: Some(1),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn group_is_tagged() {
+        let (_, ctxs) = <KeygenContext as KeygenProtocolTest>::run(2, 2);
+        let group = &ctxs[&0];
+        assert_eq!(
+            group_protocol(group).unwrap(),
+            crate::security::ProtocolType::Elgamal
+        );
+        assert!(DecryptContext::new(group).is_ok());
+        #[cfg(feature = "frost")]
+        assert!(crate::protocol::frost::SignContext::new(group).is_err());
+    }
+
+    #[test]
+    fn inspect() {
+        let (_, ctxs) = <KeygenContext as KeygenProtocolTest>::run(2, 3);
+        let infos: Vec<_> = (0..3)
+            .map(|i| inspect_group(&[ctxs[&i].clone()]).unwrap())
+            .collect();
+
+        for (i, info) in infos.iter().enumerate() {
+            assert_eq!(info.curve, Curve::Ristretto255);
+            assert_eq!((info.threshold, info.parties), (Some(2), Some(3)));
+            assert_eq!(info.indices, vec![i as u32]);
+            assert_eq!(info.public_key, infos[0].public_key);
+            assert_eq!(info.verifying_shares, infos[0].verifying_shares);
+        }
+        assert_eq!(infos[0].verifying_shares.len(), 3);
+        assert!(inspect_group(&[]).is_err());
+    }
 }
@@ -3,7 +3,7 @@ mod implementation;
 #[cfg(feature = "elgamal")]
 pub(crate) use implementation::*;
 
-pub const KEYGEN_ROUNDS: u16 = 4 + 2;
+pub const KEYGEN_ROUNDS: u16 = 4 + 2 + 1;
 pub const DECRYPT_ROUNDS: u16 = 2;
 
 #[cfg(feature = "elgamal-encrypt")]
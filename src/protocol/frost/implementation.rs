@@ -1,3 +1,4 @@
+use crate::error::Error;
 use crate::proto::{ProtocolGroupInit, ProtocolInit, ProtocolType, ServerMessage};
 use crate::protocol::*;
 use crate::util::{deserialize_map, Message};
@@ -6,11 +7,12 @@ use frost::keys::dkg::{self, round1, round2};
 use frost::keys::{KeyPackage, PublicKeyPackage};
 use frost::round1::{SigningCommitments, SigningNonces};
 use frost::round2::SignatureShare;
-use frost::{Identifier, Signature, SigningPackage};
+use frost::{Identifier, Signature, SigningPackage, VerifyingKey};
 use prost::Message as _;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::convert::{TryFrom, TryInto};
+use zeroize::Zeroizing;
 
 use frost_secp256k1 as frost;
 use rand::rngs::OsRng;
@@ -22,6 +24,12 @@ struct Setup {
     index: u16,
 }
 
+impl From<frost::Error> for Error {
+    fn from(error: frost::Error) -> Self {
+        Error::crypto(error.to_string())
+    }
+}
+
 /// Helper intended for use in `iterator.map`
 fn index_to_identifier<T>((i, x): (u32, T)) -> (Identifier, T) {
     assert!(i > 0);
@@ -38,14 +46,14 @@ pub(crate) struct KeygenContext {
 #[derive(Serialize, Deserialize)]
 enum KeygenRound {
     R0,
-    R1(Setup, round1::SecretPackage),
+    R1(Setup, Zeroizing<round1::SecretPackage>),
     R2(
         Setup,
-        round2::SecretPackage,
+        Zeroizing<round2::SecretPackage>,
         BTreeMap<Identifier, round1::Package>,
     ),
     R21AwaitSetupResp(Setup, PublicKeyPackage),
-    Done(Setup, Option<KeyPackage>, PublicKeyPackage),
+    Done(Setup, Option<Zeroizing<KeyPackage>>, PublicKeyPackage),
 }
 
 impl KeygenContext {
@@ -59,7 +67,7 @@ impl KeygenContext {
     fn init(&mut self, data: &[u8]) -> Result<Message> {
         let msg = ProtocolGroupInit::decode(data)?;
         if msg.protocol_type != ProtocolType::Frost as i32 {
-            return Err("wrong protocol type".into());
+            return Err(Error::misuse("wrong protocol type"));
         }
 
         let setup = Setup {
@@ -76,19 +84,19 @@ impl KeygenContext {
         )?;
 
         let msg = Message::serialize_reliable_broadcast(&public_package)?;
-        self.round = KeygenRound::R1(setup, secret_package);
+        self.round = KeygenRound::R1(setup, Zeroizing::new(secret_package));
         Ok(msg)
     }
 
     fn update(&mut self, data: &[u8]) -> Result<Message> {
         let (c, data) = match &self.round {
-            KeygenRound::R0 => return Err("protocol not initialized".into()),
+            KeygenRound::R0 => return Err(Error::misuse("protocol not initialized")),
             KeygenRound::R1(setup, secret) => {
                 let data = ServerMessage::decode(data)?.broadcasts;
                 let round1 = deserialize_map(&data)?;
                 let indices: Vec<_> = round1.keys().cloned().collect();
                 let round1 = round1.into_iter().map(index_to_identifier).collect();
-                let (secret, round2) = dkg::part2(secret.clone(), &round1)?;
+                let (secret, round2) = dkg::part2((**secret).clone(), &round1)?;
 
                 let round2 = indices.into_iter().map(|i| {
                     let id = Identifier::try_from(i as u16).unwrap();
@@ -96,7 +104,7 @@ impl KeygenContext {
                 });
 
                 (
-                    KeygenRound::R2(*setup, secret, round1),
+                    KeygenRound::R2(*setup, Zeroizing::new(secret), round1),
                     Message::serialize_unicast(round2)?,
                 )
             }
@@ -105,6 +113,7 @@ impl KeygenContext {
                 let round2 = deserialize_map(&data)?;
                 let round2 = round2.into_iter().map(index_to_identifier).collect();
                 let (key, pubkey) = frost::keys::dkg::part3(secret, round1, &round2)?;
+                let key = Zeroizing::new(key);
 
                 if !self.with_card {
                     let msg = Message::serialize_broadcast(&pubkey.verifying_key())?;
@@ -128,7 +137,7 @@ impl KeygenContext {
                 let msg = Message::serialize_broadcast(&pubkey.verifying_key())?;
                 (KeygenRound::Done(*setup, None, pubkey.clone()), msg)
             }
-            KeygenRound::Done(_, _, _) => return Err("protocol already finished".into()),
+            KeygenRound::Done(_, _, _) => return Err(Error::misuse("protocol already finished")),
         };
         self.round = c;
 
@@ -147,10 +156,39 @@ impl Protocol for KeygenContext {
 
     fn finish(self: Box<Self>) -> Result<Vec<u8>> {
         match self.round {
-            KeygenRound::Done(setup, key_package, pubkey_package) => {
-                Ok(serde_json::to_vec(&(setup, key_package, pubkey_package))?)
+            KeygenRound::Done(setup, key_package, pubkey_package) => encode_group(
+                ProtocolType::Frost,
+                Curve::Secp256k1,
+                &(setup, key_package, pubkey_package),
+            ),
+            _ => Err(Error::misuse("protocol not finished")),
+        }
+    }
+
+    fn round(&self) -> &'static str {
+        match &self.round {
+            KeygenRound::R0 => "R0",
+            KeygenRound::R1(..) => "R1",
+            KeygenRound::R2(..) => "R2",
+            KeygenRound::R21AwaitSetupResp(..) => "R21AwaitSetupResp",
+            KeygenRound::Done(..) => "Done",
+        }
+    }
+
+    fn rounds(&self) -> u16 {
+        super::KEYGEN_ROUNDS
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(self.round, KeygenRound::Done(..))
+    }
+
+    fn group_key(&self) -> Option<Vec<u8>> {
+        match &self.round {
+            KeygenRound::Done(_, _, pubkey_package) => {
+                Some(pubkey_package.verifying_key().serialize().to_vec())
             }
-            _ => Err("protocol not finished".into()),
+            _ => None,
         }
     }
 }
@@ -167,7 +205,7 @@ impl KeygenProtocol for KeygenContext {
 #[derive(Serialize, Deserialize)]
 pub(crate) struct SignContext {
     setup: Setup,
-    key: Option<KeyPackage>,
+    key: Option<Zeroizing<KeyPackage>>,
     pubkey: PublicKeyPackage,
     message: Option<Vec<u8>>,
     indices: Option<Vec<u16>>,
@@ -178,7 +216,7 @@ pub(crate) struct SignContext {
 enum SignRound {
     R0,
     R01AwaitCommitResp,
-    R1(Option<SigningNonces>, SigningCommitments),
+    R1(Option<Zeroizing<SigningNonces>>, SigningCommitments),
     R11AwaitCommitmentResp(usize, SigningPackage),
     R12AwaitSignResp(SigningPackage),
     R2(SigningPackage, SignatureShare),
@@ -193,7 +231,7 @@ impl SignContext {
     fn init(&mut self, data: &[u8]) -> Result<Message> {
         let msg = ProtocolInit::decode(data)?;
         if msg.protocol_type != ProtocolType::Frost as i32 {
-            return Err("wrong protocol type".into());
+            return Err(Error::misuse("wrong protocol type"));
         }
 
         self.indices = Some(msg.indices.iter().map(|i| *i as u16).collect());
@@ -203,7 +241,7 @@ impl SignContext {
             let (nonces, commitments) = frost::round1::commit(key.signing_share(), &mut OsRng);
 
             let msg = Message::serialize_broadcast(&commitments)?;
-            self.round = SignRound::R1(Some(nonces), commitments);
+            self.round = SignRound::R1(Some(Zeroizing::new(nonces)), commitments);
             Ok(msg)
         } else {
             self.round = SignRound::R01AwaitCommitResp;
@@ -214,7 +252,7 @@ impl SignContext {
 
     fn update(&mut self, data: &[u8]) -> Result<Message> {
         match &self.round {
-            SignRound::R0 => Err("protocol not initialized".into()),
+            SignRound::R0 => Err(Error::misuse("protocol not initialized")),
             SignRound::R01AwaitCommitResp => {
                 let commitments = jc::response::commit(data)?;
                 let msg = Message::serialize_broadcast(&commitments)?;
@@ -295,7 +333,7 @@ impl SignContext {
                 self.round = SignRound::Done(signature);
                 Ok(msg)
             }
-            SignRound::Done(_) => Err("protocol already finished".into()),
+            SignRound::Done(_) => Err(Error::misuse("protocol already finished")),
         }
     }
 }
@@ -312,31 +350,87 @@ impl Protocol for SignContext {
     fn finish(self: Box<Self>) -> Result<Vec<u8>> {
         match self.round {
             SignRound::Done(sig) => Ok(serde_json::to_vec(&sig)?),
-            _ => Err("protocol not finished".into()),
+            _ => Err(Error::misuse("protocol not finished")),
+        }
+    }
+
+    fn round(&self) -> &'static str {
+        match &self.round {
+            SignRound::R0 => "R0",
+            SignRound::R01AwaitCommitResp => "R01AwaitCommitResp",
+            SignRound::R1(..) => "R1",
+            SignRound::R11AwaitCommitmentResp(..) => "R11AwaitCommitmentResp",
+            SignRound::R12AwaitSignResp(..) => "R12AwaitSignResp",
+            SignRound::R2(..) => "R2",
+            SignRound::Done(..) => "Done",
         }
     }
+
+    fn rounds(&self) -> u16 {
+        super::SIGN_ROUNDS
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(self.round, SignRound::Done(..))
+    }
+
+    fn group_params(&self) -> Option<GroupParams> {
+        Some(GroupParams {
+            parties: self.setup.parties.into(),
+            threshold: self.setup.threshold.into(),
+            index: self.setup.index.into(),
+        })
+    }
+
+    fn group_keys(&self) -> Option<GroupKeys> {
+        let verifying_shares = (1..=self.setup.parties)
+            .filter_map(|index| {
+                let identifier = Identifier::try_from(index).ok()?;
+                let share = self.pubkey.verifying_shares().get(&identifier)?;
+                Some((index.into(), share.serialize().to_vec()))
+            })
+            .collect();
+        Some(GroupKeys {
+            public_key: self.pubkey.verifying_key().serialize().to_vec(),
+            verifying_shares,
+            with_card: self.key.is_none(),
+        })
+    }
 }
 
 impl ThresholdProtocol for SignContext {
-    fn new(group: &[u8]) -> Self {
-        let (setup, key, pubkey): (Setup, Option<KeyPackage>, PublicKeyPackage) =
-            serde_json::from_slice(group).expect("could not deserialize group context");
-        Self {
+    fn new(group: &[u8]) -> Result<Self> {
+        let (setup, key, pubkey): (Setup, Option<Zeroizing<KeyPackage>>, PublicKeyPackage) =
+            decode_group(ProtocolType::Frost, group)?;
+        Ok(Self {
             setup,
             key,
             pubkey,
             message: None,
             indices: None,
             round: SignRound::R0,
-        }
+        })
     }
 }
 
+/// Verifies a serialized `Signature` under a compressed SEC1 encoded `VerifyingKey`
+pub(crate) fn verify(group_key: &[u8], message: &[u8], signature: &[u8]) -> Result<()> {
+    let key = group_key
+        .try_into()
+        .ok()
+        .and_then(|key| VerifyingKey::deserialize(key).ok())
+        .ok_or_else(|| Error::malformed("invalid group key"))?;
+    let signature: Signature =
+        serde_json::from_slice(signature).map_err(|_| Error::malformed("invalid signature"))?;
+    key.verify(message, &signature)
+        .map_err(|_| Error::crypto("signature verification failed"))?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::protocol::tests::{KeygenProtocolTest, ThresholdProtocolTest};
-    use frost::VerifyingKey;
     use rand::seq::IteratorRandom;
 
     impl KeygenProtocolTest for KeygenContext {
@@ -397,10 +491,48 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn inspect() {
+        let (pks, ctxs) = <KeygenContext as KeygenProtocolTest>::run(2, 3);
+        let pk: VerifyingKey = serde_json::from_slice(&pks[&1]).unwrap();
+
+        let info = inspect_group(&[ctxs[&1].clone(), ctxs[&3].clone()]).unwrap();
+        assert_eq!(info.protocol, crate::security::ProtocolType::Frost);
+        assert_eq!(info.curve, Curve::Secp256k1);
+        assert_eq!(info.public_key, pk.serialize().to_vec());
+        assert_eq!((info.threshold, info.parties), (Some(2), Some(3)));
+        assert_eq!(info.indices, vec![1, 3]);
+        assert!(!info.with_card);
+        assert_eq!(
+            info.verifying_shares.keys().copied().collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn verify_signature() {
+        let (_, ctxs) = <KeygenContext as KeygenProtocolTest>::run(2, 3);
+        let shares: Vec<_> = ctxs.values().cloned().collect();
+        let pk = inspect_group(&shares).unwrap().public_key;
+        let ctxs = ctxs.into_iter().take(2).collect();
+        let signature =
+            <SignContext as ThresholdProtocolTest>::run(ctxs, b"hello".to_vec()).remove(0);
+
+        let result = crate::protocol::verify(ProtocolType::Frost, &pk, b"hello", &signature);
+        assert!(result.is_ok());
+
+        assert!(verify(&pk, b"hello", &signature).is_ok());
+        let error = verify(&pk, b"bye", &signature).unwrap_err();
+        assert!(matches!(error, Error::Crypto { .. }));
+        let error = verify(&signature, b"hello", &signature).unwrap_err();
+        assert!(matches!(error, Error::MalformedInput(_)));
+    }
 }
 
 mod jc {
     mod util {
+        use crate::error::Error;
         use crate::protocol::Result;
         use k256::{
             elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint},
@@ -408,10 +540,11 @@ mod jc {
         };
 
         pub fn reencode_point(ser: &[u8], compress: bool) -> Result<Box<[u8]>> {
-            let encoded = EncodedPoint::from_bytes(ser)?;
+            let encoded =
+                EncodedPoint::from_bytes(ser).map_err(|_| Error::malformed("Invalid point"))?;
             match Option::<AffinePoint>::from(AffinePoint::from_encoded_point(&encoded)) {
                 Some(affine) => Ok(affine.to_encoded_point(compress).to_bytes()),
-                None => Err("Invalid point".into()),
+                None => Err(Error::malformed("Invalid point")),
             }
         }
     }
@@ -468,6 +601,7 @@ mod jc {
     pub mod response {
         use super::super::frost;
         use super::util::reencode_point;
+        use crate::error::Error;
         use crate::protocol::apdu::parse_response;
         use crate::protocol::Result;
         use frost::{round1, round2};
@@ -497,7 +631,10 @@ mod jc {
 
         pub fn sign(raw: &[u8]) -> Result<round2::SignatureShare> {
             let data = parse_response(raw)?;
-            let signature = round2::SignatureShare::deserialize(data.try_into()?)?;
+            let data: [u8; 32] = data
+                .try_into()
+                .map_err(|_| Error::malformed("invalid signature share"))?;
+            let signature = round2::SignatureShare::deserialize(data)?;
             Ok(signature)
         }
     }
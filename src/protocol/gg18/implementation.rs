@@ -1,17 +1,24 @@
-use crate::proto::{ProtocolGroupInit, ProtocolInit, ServerMessage};
+use crate::error::Error;
+use crate::proto::{ProtocolGroupInit, ProtocolInit, ProtocolType, ServerMessage};
 use crate::protocol::*;
 use crate::util::{deserialize_map, Message};
 use mpecdsa::{gg18_key_gen::*, gg18_sign::*};
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
 use prost::Message as _;
 // TODO: use bincode instead?
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Serialize, Deserialize)]
 pub(crate) struct KeygenContext {
     round: KeygenRound,
+    // NOTE: the contexts of mpecdsa do not reveal the parameters of the group
+    #[serde(default)]
+    params: Option<GroupParams>,
 }
 
+// NOTE: the contexts of mpecdsa implement no `Zeroize` and their rounds take
+// them by value, so the secret state of GG18 cannot be wiped
 #[derive(Serialize, Deserialize)]
 enum KeygenRound {
     R0,
@@ -23,6 +30,11 @@ enum KeygenRound {
     Done(GG18SignContext),
 }
 
+/// Reports an error of mpecdsa, which fails on invalid messages of other parties
+fn failed(error: impl std::fmt::Display) -> Error {
+    Error::crypto(error.to_string())
+}
+
 /// Collects a hashmap's values sorted by their respective keys
 fn map_to_sorted_vec<T>(map: HashMap<u32, T>) -> Vec<T> {
     let mut vec: Vec<_> = map.into_iter().collect();
@@ -37,10 +49,17 @@ impl KeygenContext {
         let (parties, threshold, index) =
             (msg.parties as u16, msg.threshold as u16, msg.index as u16);
 
-        let (out, c1) = gg18_key_gen_1(parties, threshold, index)?;
+        let params = GroupParams {
+            parties: msg.parties,
+            threshold: msg.threshold,
+            index: msg.index,
+        };
+
+        let (out, c1) = gg18_key_gen_1(parties, threshold, index).map_err(failed)?;
         let msg = Message::serialize_reliable_broadcast(&out)?;
 
         self.round = KeygenRound::R1(c1);
+        self.params = Some(params);
         Ok(msg)
     }
 
@@ -52,14 +71,14 @@ impl KeygenContext {
             KeygenRound::R1(c1) => {
                 let msgs = deserialize_map(&data.broadcasts)?;
                 let msgs = map_to_sorted_vec(msgs);
-                let (out, c2) = gg18_key_gen_2(msgs, c1.clone())?;
+                let (out, c2) = gg18_key_gen_2(msgs, c1.clone()).map_err(failed)?;
                 let msg = Message::serialize_reliable_broadcast(&out)?;
                 (KeygenRound::R2(c2), msg)
             }
             KeygenRound::R2(c2) => {
                 let msgs = deserialize_map(&data.broadcasts)?;
                 let msgs = map_to_sorted_vec(msgs);
-                let (outs, c3) = gg18_key_gen_3(msgs, c2.clone())?;
+                let (outs, c3) = gg18_key_gen_3(msgs, c2.clone()).map_err(failed)?;
 
                 let mut indices: Vec<_> = data.broadcasts.into_keys().collect();
                 indices.sort();
@@ -70,25 +89,25 @@ impl KeygenContext {
             KeygenRound::R3(c3) => {
                 let msgs = deserialize_map(&data.unicasts)?;
                 let msgs = map_to_sorted_vec(msgs);
-                let (out, c4) = gg18_key_gen_4(msgs, c3.clone())?;
+                let (out, c4) = gg18_key_gen_4(msgs, c3.clone()).map_err(failed)?;
                 let msg = Message::serialize_reliable_broadcast(&out)?;
                 (KeygenRound::R4(c4), msg)
             }
             KeygenRound::R4(c4) => {
                 let msgs = deserialize_map(&data.broadcasts)?;
                 let msgs = map_to_sorted_vec(msgs);
-                let (out, c5) = gg18_key_gen_5(msgs, c4.clone())?;
+                let (out, c5) = gg18_key_gen_5(msgs, c4.clone()).map_err(failed)?;
                 let msg = Message::serialize_reliable_broadcast(&out)?;
                 (KeygenRound::R5(c5), msg)
             }
             KeygenRound::R5(c5) => {
                 let msgs = deserialize_map(&data.broadcasts)?;
                 let msgs = map_to_sorted_vec(msgs);
-                let c = gg18_key_gen_6(msgs, c5.clone())?;
+                let c = gg18_key_gen_6(msgs, c5.clone()).map_err(failed)?;
                 let msg = Message::raw_reliable_broadcast(c.pk.to_bytes(false).to_vec());
                 (KeygenRound::Done(c), msg)
             }
-            KeygenRound::Done(_) => todo!(),
+            KeygenRound::Done(_) => return Err(Error::misuse("protocol already finished")),
         };
         self.round = c;
         Ok(msg)
@@ -107,8 +126,37 @@ impl Protocol for KeygenContext {
 
     fn finish(self: Box<Self>) -> Result<Vec<u8>> {
         match self.round {
-            KeygenRound::Done(ctx) => Ok(serde_json::to_vec(&ctx).unwrap()),
-            _ => Err("protocol not finished".into()),
+            KeygenRound::Done(ctx) => {
+                encode_group_with_params(ProtocolType::Gg18, Curve::P256, self.params, &ctx)
+            }
+            _ => Err(Error::misuse("protocol not finished")),
+        }
+    }
+
+    fn round(&self) -> &'static str {
+        match &self.round {
+            KeygenRound::R0 => "R0",
+            KeygenRound::R1(..) => "R1",
+            KeygenRound::R2(..) => "R2",
+            KeygenRound::R3(..) => "R3",
+            KeygenRound::R4(..) => "R4",
+            KeygenRound::R5(..) => "R5",
+            KeygenRound::Done(..) => "Done",
+        }
+    }
+
+    fn rounds(&self) -> u16 {
+        super::KEYGEN_ROUNDS
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(self.round, KeygenRound::Done(..))
+    }
+
+    fn group_key(&self) -> Option<Vec<u8>> {
+        match &self.round {
+            KeygenRound::Done(ctx) => Some(ctx.pk.to_bytes(false).to_vec()),
+            _ => None,
         }
     }
 }
@@ -117,6 +165,7 @@ impl KeygenProtocol for KeygenContext {
     fn new() -> Self {
         Self {
             round: KeygenRound::R0,
+            params: None,
         }
     }
 }
@@ -124,6 +173,9 @@ impl KeygenProtocol for KeygenContext {
 #[derive(Serialize, Deserialize)]
 pub(crate) struct SignContext {
     round: SignRound,
+    /// Unknown for groups which do not record it
+    #[serde(default)]
+    params: Option<GroupParams>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -146,14 +198,17 @@ impl SignContext {
         let msg = ProtocolInit::decode(data)?;
 
         let indices: Vec<u16> = msg.indices.clone().into_iter().map(|i| i as u16).collect();
-        let local_index = indices.iter().position(|&i| i == msg.index as u16).unwrap();
+        let local_index = indices
+            .iter()
+            .position(|&i| i == msg.index as u16)
+            .ok_or_else(|| Error::malformed("party does not participate"))?;
 
         let c0 = match &self.round {
             SignRound::R0(c0) => c0.clone(),
             _ => unreachable!(),
         };
 
-        let (out, c1) = gg18_sign1(c0, indices, local_index, msg.data)?;
+        let (out, c1) = gg18_sign1(c0, indices, local_index, msg.data).map_err(failed)?;
         let msg = Message::serialize_broadcast(&out)?;
         self.round = SignRound::R1(c1);
         Ok(msg)
@@ -167,7 +222,7 @@ impl SignContext {
             SignRound::R1(c1) => {
                 let msgs = deserialize_map(&data.broadcasts)?;
                 let msgs = map_to_sorted_vec(msgs);
-                let (outs, c2) = gg18_sign2(msgs, c1.clone())?;
+                let (outs, c2) = gg18_sign2(msgs, c1.clone()).map_err(failed)?;
 
                 let mut indices: Vec<_> = data.broadcasts.into_keys().collect();
                 indices.sort();
@@ -178,60 +233,60 @@ impl SignContext {
             SignRound::R2(c2) => {
                 let msgs = deserialize_map(&data.unicasts)?;
                 let msgs = map_to_sorted_vec(msgs);
-                let (out, c3) = gg18_sign3(msgs, c2.clone())?;
+                let (out, c3) = gg18_sign3(msgs, c2.clone()).map_err(failed)?;
                 let msg = Message::serialize_broadcast(&out)?;
                 (SignRound::R3(c3), msg)
             }
             SignRound::R3(c3) => {
                 let msgs = deserialize_map(&data.broadcasts)?;
                 let msgs = map_to_sorted_vec(msgs);
-                let (out, c4) = gg18_sign4(msgs, c3.clone())?;
+                let (out, c4) = gg18_sign4(msgs, c3.clone()).map_err(failed)?;
                 let msg = Message::serialize_broadcast(&out)?;
                 (SignRound::R4(c4), msg)
             }
             SignRound::R4(c4) => {
                 let msgs = deserialize_map(&data.broadcasts)?;
                 let msgs = map_to_sorted_vec(msgs);
-                let (out, c5) = gg18_sign5(msgs, c4.clone())?;
+                let (out, c5) = gg18_sign5(msgs, c4.clone()).map_err(failed)?;
                 let msg = Message::serialize_broadcast(&out)?;
                 (SignRound::R5(c5), msg)
             }
             SignRound::R5(c5) => {
                 let msgs = deserialize_map(&data.broadcasts)?;
                 let msgs = map_to_sorted_vec(msgs);
-                let (out, c6) = gg18_sign6(msgs, c5.clone())?;
+                let (out, c6) = gg18_sign6(msgs, c5.clone()).map_err(failed)?;
                 let msg = Message::serialize_broadcast(&out)?;
                 (SignRound::R6(c6), msg)
             }
             SignRound::R6(c6) => {
                 let msgs = deserialize_map(&data.broadcasts)?;
                 let msgs = map_to_sorted_vec(msgs);
-                let (out, c7) = gg18_sign7(msgs, c6.clone())?;
+                let (out, c7) = gg18_sign7(msgs, c6.clone()).map_err(failed)?;
                 let msg = Message::serialize_broadcast(&out)?;
                 (SignRound::R7(c7), msg)
             }
             SignRound::R7(c7) => {
                 let msgs = deserialize_map(&data.broadcasts)?;
                 let msgs = map_to_sorted_vec(msgs);
-                let (out, c8) = gg18_sign8(msgs, c7.clone())?;
+                let (out, c8) = gg18_sign8(msgs, c7.clone()).map_err(failed)?;
                 let msg = Message::serialize_broadcast(&out)?;
                 (SignRound::R8(c8), msg)
             }
             SignRound::R8(c8) => {
                 let msgs = deserialize_map(&data.broadcasts)?;
                 let msgs = map_to_sorted_vec(msgs);
-                let (out, c9) = gg18_sign9(msgs, c8.clone())?;
+                let (out, c9) = gg18_sign9(msgs, c8.clone()).map_err(failed)?;
                 let msg = Message::serialize_broadcast(&out)?;
                 (SignRound::R9(c9), msg)
             }
             SignRound::R9(c9) => {
                 let msgs = deserialize_map(&data.broadcasts)?;
                 let msgs = map_to_sorted_vec(msgs);
-                let sig = gg18_sign10(msgs, c9.clone())?;
+                let sig = gg18_sign10(msgs, c9.clone()).map_err(failed)?;
                 let msg = Message::raw_broadcast(sig.clone());
                 (SignRound::Done(sig), msg)
             }
-            SignRound::Done(_) => todo!(),
+            SignRound::Done(_) => return Err(Error::misuse("protocol already finished")),
         };
 
         self.round = c;
@@ -252,19 +307,71 @@ impl Protocol for SignContext {
     fn finish(self: Box<Self>) -> Result<Vec<u8>> {
         match self.round {
             SignRound::Done(sig) => Ok(sig),
-            _ => Err("protocol not finished".into()),
+            _ => Err(Error::misuse("protocol not finished")),
+        }
+    }
+
+    fn round(&self) -> &'static str {
+        match &self.round {
+            SignRound::R0(..) => "R0",
+            SignRound::R1(..) => "R1",
+            SignRound::R2(..) => "R2",
+            SignRound::R3(..) => "R3",
+            SignRound::R4(..) => "R4",
+            SignRound::R5(..) => "R5",
+            SignRound::R6(..) => "R6",
+            SignRound::R7(..) => "R7",
+            SignRound::R8(..) => "R8",
+            SignRound::R9(..) => "R9",
+            SignRound::Done(..) => "Done",
+        }
+    }
+
+    fn rounds(&self) -> u16 {
+        super::SIGN_ROUNDS
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(self.round, SignRound::Done(..))
+    }
+
+    fn group_params(&self) -> Option<GroupParams> {
+        self.params
+    }
+
+    fn group_keys(&self) -> Option<GroupKeys> {
+        match &self.round {
+            SignRound::R0(ctx) => Some(GroupKeys {
+                public_key: ctx.pk.to_bytes(true).to_vec(),
+                verifying_shares: BTreeMap::new(),
+                with_card: false,
+            }),
+            _ => None,
         }
     }
 }
 
 impl ThresholdProtocol for SignContext {
-    fn new(group: &[u8]) -> Self {
-        Self {
-            round: SignRound::R0(serde_json::from_slice(group).unwrap()),
-        }
+    fn new(group: &[u8]) -> Result<Self> {
+        Ok(Self {
+            round: SignRound::R0(decode_group(ProtocolType::Gg18, group)?),
+            params: decode_group_params(group),
+        })
     }
 }
 
+/// Verifies a raw `r || s` ECDSA signature of a `message` under a SEC1 encoded group key.
+/// The parties sign the SHA-256 digest of the message, so it is hashed here
+pub(crate) fn verify(group_key: &[u8], message: &[u8], signature: &[u8]) -> Result<()> {
+    let key = VerifyingKey::from_sec1_bytes(group_key)
+        .map_err(|_| Error::malformed("invalid group key"))?;
+    let signature =
+        Signature::from_slice(signature).map_err(|_| Error::malformed("invalid signature"))?;
+    key.verify(message, &signature)
+        .map_err(|_| Error::crypto("signature verification failed"))?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -333,4 +440,44 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn group_records_params() {
+        let (_, ctxs) = <KeygenContext as KeygenProtocolTest>::run(2, 3);
+        for (index, group) in &ctxs {
+            let params = SignContext::new(group).unwrap().group_params();
+            assert_eq!(
+                params,
+                Some(GroupParams {
+                    parties: 3,
+                    threshold: 2,
+                    index: *index,
+                })
+            );
+        }
+
+        let info = inspect_group(&[ctxs[&0].clone()]).unwrap();
+        assert_eq!((info.threshold, info.parties), (Some(2), Some(3)));
+        assert_eq!(info.indices, vec![0]);
+    }
+
+    #[test]
+    fn verify_signature() {
+        let (pks, ctxs) = <KeygenContext as KeygenProtocolTest>::run(2, 2);
+        let pk = pks.into_values().next().unwrap();
+        let shares: Vec<_> = ctxs.values().cloned().collect();
+        let dgst = sha2::Sha256::digest(b"hello");
+        let signature = <SignContext as ThresholdProtocolTest>::run(ctxs, dgst.to_vec()).remove(0);
+
+        assert!(verify(&pk, b"hello", &signature).is_ok());
+        let error = verify(&pk, b"bye", &signature).unwrap_err();
+        assert!(matches!(error, Error::Crypto { .. }));
+        let error = verify(&pk, b"hello", &signature[1..]).unwrap_err();
+        assert!(matches!(error, Error::MalformedInput(_)));
+
+        let info = inspect_group(&shares).unwrap();
+        let result =
+            crate::protocol::verify(ProtocolType::Gg18, &info.public_key, b"hello", &signature);
+        assert!(result.is_ok());
+    }
 }
@@ -3,5 +3,5 @@ mod implementation;
 #[cfg(feature = "gg18")]
 pub(crate) use implementation::*;
 
-pub const KEYGEN_ROUNDS: u16 = 6 + 4;
+pub const KEYGEN_ROUNDS: u16 = 6 + 4 + 1;
 pub const SIGN_ROUNDS: u16 = 10;
@@ -4,24 +4,190 @@ pub mod gg18;
 pub mod musig2;
 
 #[cfg(any(feature = "frost", feature = "musig2"))]
-mod apdu;
+pub(crate) mod apdu;
 
-pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+pub type Result<T> = std::result::Result<T, crate::error::Error>;
 
 #[cfg(feature = "protocol")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 pub enum Recipient {
     Card,
     Server,
 }
 
+#[cfg(feature = "protocol")]
+use crate::error::Error;
+#[cfg(feature = "protocol")]
+use crate::proto;
+#[cfg(feature = "protocol")]
+use crate::security::ProtocolType;
 #[cfg(feature = "protocol")]
 use crate::util::Message;
+#[cfg(feature = "protocol")]
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+#[cfg(feature = "protocol")]
+use serde_json::value::RawValue;
+#[cfg(feature = "protocol")]
+use std::collections::BTreeMap;
+
+/// The elliptic curve of a group key
+#[cfg(feature = "protocol")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Curve {
+    P256,
+    Secp256k1,
+    Ristretto255,
+}
+
+/// The version of tagged group shares. Bare shares of older keygens are still accepted
+#[cfg(feature = "protocol")]
+pub(crate) const GROUP_VERSION: u32 = 1;
+
+/// A group share output by a keygen, tagged with the protocol and curve it belongs to
+#[cfg(feature = "protocol")]
+#[derive(Deserialize, Serialize)]
+struct TaggedGroup {
+    protocol: ProtocolType,
+    curve: Curve,
+    /// The parameters of the group, for protocols whose share does not reveal them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    params: Option<GroupParams>,
+    share: Box<RawValue>,
+}
+
+/// Tags the group `share` of a finished keygen of `protocol`
+#[cfg(feature = "protocol")]
+pub(crate) fn encode_group<T: Serialize>(
+    protocol: proto::ProtocolType,
+    curve: Curve,
+    share: &T,
+) -> Result<Vec<u8>> {
+    encode_group_with_params(protocol, curve, None, share)
+}
+
+/// Tags the group `share` of a finished keygen of `protocol` and records the `params` of its group
+#[cfg(feature = "protocol")]
+pub(crate) fn encode_group_with_params<T: Serialize>(
+    protocol: proto::ProtocolType,
+    curve: Curve,
+    params: Option<GroupParams>,
+    share: &T,
+) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec(&TaggedGroup {
+        protocol: protocol.into(),
+        curve,
+        params,
+        share: serde_json::value::to_raw_value(share)?,
+    })?)
+}
+
+/// Reads the group parameters recorded by `encode_group_with_params`, if any
+#[cfg(feature = "protocol")]
+pub(crate) fn decode_group_params(group: &[u8]) -> Option<GroupParams> {
+    serde_json::from_slice::<TaggedGroup>(group)
+        .ok()
+        .and_then(|tagged| tagged.params)
+}
+
+/// Loads a group share for a threshold protocol of `protocol`,
+/// refusing shares tagged with another protocol
+#[cfg(feature = "protocol")]
+pub(crate) fn decode_group<T: DeserializeOwned>(
+    protocol: proto::ProtocolType,
+    group: &[u8],
+) -> Result<T> {
+    let tagged: TaggedGroup = match serde_json::from_slice(group) {
+        Ok(tagged) => tagged,
+        // NOTE: groups created before tagging hold the bare share
+        Err(_) => return Ok(serde_json::from_slice(group)?),
+    };
+    if tagged.protocol != protocol.into() {
+        return Err(Error::misuse(format!(
+            "group belongs to {:?}, not {:?}",
+            tagged.protocol,
+            ProtocolType::from(protocol)
+        )));
+    }
+    Ok(serde_json::from_str(tagged.share.get())?)
+}
+
+/// Detects the protocol of a group share output by a keygen
+#[cfg(feature = "protocol")]
+pub fn group_protocol(group: &[u8]) -> Result<ProtocolType> {
+    let tagged: TaggedGroup = serde_json::from_slice(group)
+        .map_err(|_| Error::malformed("group does not record its protocol"))?;
+    Ok(tagged.protocol)
+}
+
+/// The parameters of the group a threshold protocol runs in
+#[cfg(feature = "protocol")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) struct GroupParams {
+    pub parties: u32,
+    pub threshold: u32,
+    pub index: u32,
+}
 
+/// The keys of the group a threshold protocol runs in
 #[cfg(feature = "protocol")]
+pub(crate) struct GroupKeys {
+    /// The group key, SEC1 compressed or a compressed Ristretto point
+    pub public_key: Vec<u8>,
+    /// The verifying shares of the parties by their index, encoded like the group key
+    pub verifying_shares: BTreeMap<u32, Vec<u8>>,
+    pub with_card: bool,
+}
+
+/// What a group blob output by a keygen reveals about its group, see `inspect_group`
+#[cfg(feature = "protocol")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GroupInfo {
+    pub protocol: ProtocolType,
+    pub curve: Curve,
+    /// The group key, SEC1 compressed or a compressed Ristretto point
+    pub public_key: Vec<u8>,
+    /// Unknown for GG18 groups which do not record it
+    pub threshold: Option<u32>,
+    /// Unknown for GG18 groups which do not record it
+    pub parties: Option<u32>,
+    /// The indices of the local shares, empty for GG18 groups which do not record them
+    pub indices: Vec<u32>,
+    /// Whether any of the local shares lives on a card
+    pub with_card: bool,
+    /// The verifying shares of the parties by their index, empty if the protocol has none
+    pub verifying_shares: BTreeMap<u32, Vec<u8>>,
+}
+
+#[cfg(feature = "protocol")]
+// NOTE: protocols are Send so that bindings may share sessions across threads
 #[typetag::serde]
-pub(crate) trait Protocol {
+pub(crate) trait Protocol: Send {
     fn advance(&mut self, data: &[u8]) -> Result<Message>;
     fn finish(self: Box<Self>) -> Result<Vec<u8>>;
+
+    /// The name of the current round, for diagnostics
+    fn round(&self) -> &'static str;
+
+    /// The expected number of rounds of the whole session, card rounds excluded
+    fn rounds(&self) -> u16;
+
+    /// Whether the result can be obtained from `finish`
+    fn is_finished(&self) -> bool;
+
+    /// The group key computed by a finished keygen
+    fn group_key(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// The parameters of the group loaded by a threshold protocol
+    fn group_params(&self) -> Option<GroupParams> {
+        None
+    }
+
+    /// The keys of the group loaded by a threshold protocol
+    fn group_keys(&self) -> Option<GroupKeys> {
+        None
+    }
 }
 
 #[cfg(feature = "protocol")]
@@ -33,11 +199,117 @@ pub(crate) trait KeygenProtocol: Protocol {
 
 #[cfg(feature = "protocol")]
 pub(crate) trait ThresholdProtocol: Protocol {
-    fn new(group: &[u8]) -> Self
+    fn new(group: &[u8]) -> Result<Self>
     where
         Self: Sized;
 }
 
+/// Creates a keygen of `protocol_type`, optionally computed with a smart card
+#[cfg(feature = "protocol")]
+pub(crate) fn keygen(protocol_type: ProtocolType, with_card: bool) -> Result<Box<dyn Protocol>> {
+    let protocol: Box<dyn Protocol> = match (protocol_type, with_card) {
+        #[cfg(feature = "gg18")]
+        (ProtocolType::Gg18, false) => Box::new(gg18::KeygenContext::new()),
+        #[cfg(feature = "elgamal")]
+        (ProtocolType::Elgamal, false) => Box::new(elgamal::KeygenContext::new()),
+        #[cfg(feature = "frost")]
+        (ProtocolType::Frost, false) => Box::new(frost::KeygenContext::new()),
+        #[cfg(feature = "frost")]
+        (ProtocolType::Frost, true) => Box::new(frost::KeygenContext::with_card()),
+        #[cfg(feature = "musig2")]
+        (ProtocolType::Musig2, false) => Box::new(musig2::KeygenContext::new()),
+        #[cfg(feature = "musig2")]
+        (ProtocolType::Musig2, true) => Box::new(musig2::KeygenContext::with_card()),
+        _ => return Err(Error::misuse("protocol not supported")),
+    };
+    Ok(protocol)
+}
+
+/// Loads a threshold protocol of `protocol_type` with a group share output by its keygen
+#[cfg(feature = "protocol")]
+pub(crate) fn threshold(protocol_type: ProtocolType, group: &[u8]) -> Result<Box<dyn Protocol>> {
+    let protocol: Box<dyn Protocol> = match protocol_type {
+        #[cfg(feature = "gg18")]
+        ProtocolType::Gg18 => Box::new(gg18::SignContext::new(group)?),
+        #[cfg(feature = "elgamal")]
+        ProtocolType::Elgamal => Box::new(elgamal::DecryptContext::new(group)?),
+        #[cfg(feature = "frost")]
+        ProtocolType::Frost => Box::new(frost::SignContext::new(group)?),
+        #[cfg(feature = "musig2")]
+        ProtocolType::Musig2 => Box::new(musig2::SignContext::new(group)?),
+        #[cfg(not(all(
+            feature = "gg18",
+            feature = "elgamal",
+            feature = "frost",
+            feature = "musig2"
+        )))]
+        _ => return Err(Error::misuse("protocol not supported")),
+    };
+    Ok(protocol)
+}
+
+/// Describes the group of the shares output by a keygen, as returned by `protocol_finish`
+#[cfg(feature = "protocol")]
+pub fn inspect_group(shares: &[Vec<u8>]) -> Result<GroupInfo> {
+    let first = shares
+        .first()
+        .ok_or_else(|| Error::malformed("group holds no shares"))?;
+    let tagged: TaggedGroup = serde_json::from_slice(first)
+        .map_err(|_| Error::malformed("group does not record its protocol"))?;
+
+    let mut info = GroupInfo {
+        protocol: tagged.protocol,
+        curve: tagged.curve,
+        public_key: Vec::new(),
+        threshold: None,
+        parties: None,
+        indices: Vec::new(),
+        with_card: false,
+        verifying_shares: BTreeMap::new(),
+    };
+    for (i, share) in shares.iter().enumerate() {
+        let protocol = threshold(tagged.protocol, share)?;
+        let keys = protocol
+            .group_keys()
+            .ok_or_else(|| Error::misuse("protocol does not describe its group"))?;
+        if i > 0 && keys.public_key != info.public_key {
+            return Err(Error::malformed("shares belong to different groups"));
+        }
+        info.public_key = keys.public_key;
+        info.verifying_shares = keys.verifying_shares;
+        info.with_card |= keys.with_card;
+        if let Some(params) = protocol.group_params() {
+            info.threshold = Some(params.threshold);
+            info.parties = Some(params.parties);
+            info.indices.push(params.index);
+        }
+    }
+    Ok(info)
+}
+
+/// Verifies a `signature` of a `message` produced by `protocol_type` under the compressed SEC1
+/// encoded `group_key` returned by `inspect_group`. GG18 parties sign the SHA-256 digest of
+/// the message, which is computed here
+#[cfg(feature = "protocol")]
+pub fn verify(
+    protocol_type: ProtocolType,
+    group_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> Result<()> {
+    match protocol_type {
+        #[cfg(feature = "gg18")]
+        ProtocolType::Gg18 => gg18::verify(group_key, message, signature),
+        #[cfg(feature = "frost")]
+        ProtocolType::Frost => frost::verify(group_key, message, signature),
+        #[cfg(feature = "musig2")]
+        ProtocolType::Musig2 => musig2::verify(group_key, message, signature),
+        ProtocolType::Elgamal => Err(Error::misuse("protocol does not produce signatures")),
+        #[cfg(not(all(feature = "gg18", feature = "frost", feature = "musig2")))]
+        _ => Err(Error::misuse("protocol not supported")),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -45,7 +317,7 @@ mod tests {
     use prost::Message as _;
     use std::collections::HashMap;
 
-    use crate::proto::{ProtocolGroupInit, ProtocolInit, ProtocolType, ServerMessage};
+    use crate::proto::{EchoMode, ProtocolGroupInit, ProtocolInit, ProtocolType, ServerMessage};
 
     pub(super) trait KeygenProtocolTest: KeygenProtocol + Sized {
         // Cannot be added in Protocol (yet) due to typetag Trait limitations
@@ -71,6 +343,8 @@ mod tests {
                                 index,
                                 parties,
                                 threshold,
+                                echo_mode: EchoMode::Full as i32,
+                                blind_broadcasts: false,
                             })
                             .encode_to_vec(),
                         )
@@ -106,6 +380,7 @@ mod tests {
                                     protocol_type: Self::PROTOCOL_TYPE as i32,
                                     unicasts,
                                     broadcasts,
+                                    aborts: HashMap::new(),
                                 })
                                 .encode_to_vec(),
                             )
@@ -140,7 +415,7 @@ mod tests {
             // initialize
             let mut ctxs: HashMap<u32, _> = ctxs
                 .into_iter()
-                .map(|(i, ctx)| (i, Self::new(&ctx)))
+                .map(|(i, ctx)| (i, Self::new(&ctx).unwrap()))
                 .collect();
 
             let mut indices: Vec<_> = ctxs.keys().cloned().collect();
@@ -156,6 +431,8 @@ mod tests {
                                 indices: indices.clone(),
                                 index,
                                 data: data.clone(),
+                                echo_mode: EchoMode::Full as i32,
+                                blind_broadcasts: false,
                             })
                             .encode_to_vec(),
                         )
@@ -191,6 +468,7 @@ mod tests {
                                     protocol_type: Self::PROTOCOL_TYPE as i32,
                                     unicasts,
                                     broadcasts,
+                                    aborts: HashMap::new(),
                                 })
                                 .encode_to_vec(),
                             )
@@ -1,7 +1,7 @@
-use std::collections::HashMap;
-use std::convert::TryInto;
+use std::collections::{BTreeMap, HashMap};
 
 use super::signer::Signer;
+use crate::error::Error;
 use crate::proto::{ProtocolGroupInit, ProtocolInit, ProtocolType, ServerMessage};
 use crate::protocol::*;
 use crate::util::{deserialize_map, Message};
@@ -46,12 +46,14 @@ impl KeygenContext {
         let msg = ProtocolGroupInit::decode(data)?;
 
         if msg.protocol_type != ProtocolType::Musig2 as i32 {
-            return Err("wrong protocol type".into());
+            return Err(Error::misuse("wrong protocol type"));
         }
 
         // Musig2 is n-out-of-n scheme. Therefore n = k.
         if msg.parties as u16 != msg.threshold as u16 {
-            return Err("number of parties must be equal to the threshold".into());
+            return Err(Error::malformed(
+                "number of parties must be equal to the threshold",
+            ));
         }
 
         let setup = Setup {
@@ -78,15 +80,13 @@ impl KeygenContext {
     }
 
     fn update(&mut self, data: &[u8]) -> Result<Message> {
-        let (c, data) = match &mut self.round {
-            KeygenRound::R0 => return Err("protocol not initialized".into()),
+        let msg = match &mut self.round {
+            KeygenRound::R0 => return Err(Error::misuse("protocol not initialized")),
             KeygenRound::R0GetPubkey(setup) => {
                 jc::response::keygen(data)?;
                 let command = jc::command::get_plain_pubkey();
-                (
-                    KeygenRound::R0AwaitGetPubkey(*setup),
-                    Message::new_card_command(command),
-                )
+                self.round = KeygenRound::R0AwaitGetPubkey(*setup);
+                Message::new_card_command(command)
             }
             KeygenRound::R0AwaitGetPubkey(setup) => {
                 let pubkey = jc::response::get_plain_pubkey(data)?;
@@ -95,21 +95,23 @@ impl KeygenContext {
                 let public_key_share = pubkey.serialize().to_vec();
 
                 let msg = Message::serialize_broadcast(&public_key_share)?;
-                (KeygenRound::R1(*setup, signer), msg)
+                self.round = KeygenRound::R1(*setup, signer);
+                msg
             }
-            KeygenRound::R1(setup, signer) => {
+            KeygenRound::R1(_, signer) => {
                 let data = ServerMessage::decode(data)?.broadcasts;
-                let pub_keys_hashmap = deserialize_map(&data)?;
+                let pub_keys_hashmap: HashMap<u32, Vec<u8>> = deserialize_map(&data)?;
 
                 // Get public key shares of all signers
-                let pub_key_shares: Vec<PublicKey> = pub_keys_hashmap
-                    .values()
-                    .cloned()
-                    .map(|x: Vec<u8>| {
-                        let array: [u8; 33] = x.try_into().expect("slice with incorrect length");
-                        PublicKey::from_slice(&array).unwrap()
+                let pub_key_shares = pub_keys_hashmap
+                    .into_iter()
+                    .map(|(sender, x)| {
+                        Some(x)
+                            .filter(|x| x.len() == 33)
+                            .and_then(|x| PublicKey::from_slice(&x).ok())
+                            .ok_or_else(|| Error::blame(sender, "invalid public key share"))
                     })
-                    .collect();
+                    .collect::<Result<Vec<PublicKey>>>()?;
 
                 // Generate key_agg_ctx (together with agg_pubkey). Currently there's no support for tweaks.
                 signer.generate_key_agg_ctx(pub_key_shares);
@@ -119,32 +121,37 @@ impl KeygenContext {
                     let coef_a = signer.get_coef_a().serialize();
 
                     let command = jc::command::set_aggpubkey(agg_pubkey, coef_a);
-
-                    (
-                        KeygenRound::R1AwaitAggkeyLoad(*setup, signer.clone()),
-                        Message::new_card_command(command),
-                    )
+                    self.round.proceed(KeygenRound::R1AwaitAggkeyLoad);
+                    Message::new_card_command(command)
                 } else {
-                    (
-                        KeygenRound::Done(*setup, signer.clone()),
-                        Message::serialize_broadcast(&agg_pubkey)?,
-                    )
+                    let msg = Message::serialize_broadcast(&agg_pubkey)?;
+                    self.round.proceed(KeygenRound::Done);
+                    msg
                 }
             }
-            KeygenRound::R1AwaitAggkeyLoad(setup, signer) => {
+            KeygenRound::R1AwaitAggkeyLoad(_, signer) => {
                 jc::response::set_aggpubkey(data)?;
-                let agg_pubkey = signer.get_agg_pubkey();
-
-                (
-                    KeygenRound::Done(*setup, signer.clone()),
-                    Message::serialize_broadcast(&agg_pubkey)?,
-                )
+                let msg = Message::serialize_broadcast(&signer.get_agg_pubkey())?;
+                self.round.proceed(KeygenRound::Done);
+                msg
             }
-            KeygenRound::Done(_, _) => return Err("protocol already finished".into()),
+            KeygenRound::Done(_, _) => return Err(Error::misuse("protocol already finished")),
         };
-        self.round = c;
 
-        Ok(data)
+        Ok(msg)
+    }
+}
+
+impl KeygenRound {
+    /// Moves the setup and signer of this round into the round made by `next`,
+    /// so that the key share is never copied
+    fn proceed(&mut self, next: fn(Setup, Signer) -> KeygenRound) {
+        *self = match std::mem::replace(self, KeygenRound::R0) {
+            KeygenRound::R1(setup, signer) | KeygenRound::R1AwaitAggkeyLoad(setup, signer) => {
+                next(setup, signer)
+            }
+            round => round,
+        };
     }
 }
 
@@ -159,8 +166,36 @@ impl Protocol for KeygenContext {
 
     fn finish(self: Box<Self>) -> Result<Vec<u8>> {
         match self.round {
-            KeygenRound::Done(setup, signer) => Ok(serde_json::to_vec(&(setup, signer))?),
-            _ => Err("protocol not finished".into()),
+            KeygenRound::Done(setup, signer) => {
+                encode_group(ProtocolType::Musig2, Curve::Secp256k1, &(setup, signer))
+            }
+            _ => Err(Error::misuse("protocol not finished")),
+        }
+    }
+
+    fn round(&self) -> &'static str {
+        match &self.round {
+            KeygenRound::R0 => "R0",
+            KeygenRound::R0GetPubkey(..) => "R0GetPubkey",
+            KeygenRound::R0AwaitGetPubkey(..) => "R0AwaitGetPubkey",
+            KeygenRound::R1(..) => "R1",
+            KeygenRound::R1AwaitAggkeyLoad(..) => "R1AwaitAggkeyLoad",
+            KeygenRound::Done(..) => "Done",
+        }
+    }
+
+    fn rounds(&self) -> u16 {
+        super::KEYGEN_ROUNDS
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(self.round, KeygenRound::Done(..))
+    }
+
+    fn group_key(&self) -> Option<Vec<u8>> {
+        match &self.round {
+            KeygenRound::Done(_, signer) => Some(signer.get_agg_pubkey().serialize().to_vec()),
+            _ => None,
         }
     }
 }
@@ -175,24 +210,31 @@ impl KeygenProtocol for KeygenContext {
 }
 
 // By GitHub Copilot
-// Deserialize a vector of bytes with pubnonces/partial signatures and internal identifiers of the signers to a hashmap
-fn deserialize_musig(data: Vec<Vec<u8>>, chunk_len: usize) -> Result<HashMap<u8, Vec<u8>>> {
-    let data = data.concat();
-    if data.len() % chunk_len != 0 {
-        return Err("Input data length must be a multiple of the input size".into());
-    }
-
+// Deserialize the pubnonces/partial signatures and internal identifiers of the signers sent by each party
+// to a hashmap <internal identifier, (sender, value)>
+fn deserialize_musig(
+    data: HashMap<u32, Vec<u8>>,
+    chunk_len: usize,
+) -> Result<HashMap<u8, (u32, Vec<u8>)>> {
     let mut hashmap = HashMap::new();
-    for chunk in data.chunks_exact(chunk_len) {
-        let key = chunk[chunk_len - 1];
-        let mut value = Vec::new();
-        value.extend_from_slice(&chunk[..chunk_len - 1]);
-
-        if hashmap.contains_key(&key) {
-            return Err("Duplicate key found".into());
+    for (sender, data) in data {
+        if data.len() % chunk_len != 0 {
+            return Err(Error::malformed(
+                "Input data length must be a multiple of the input size",
+            ));
         }
 
-        hashmap.insert(key, value);
+        for chunk in data.chunks_exact(chunk_len) {
+            let key = chunk[chunk_len - 1];
+            let mut value = Vec::new();
+            value.extend_from_slice(&chunk[..chunk_len - 1]);
+
+            if hashmap.contains_key(&key) {
+                return Err(Error::malformed("Duplicate key found"));
+            }
+
+            hashmap.insert(key, (sender, value));
+        }
     }
 
     Ok(hashmap)
@@ -201,7 +243,8 @@ fn deserialize_musig(data: Vec<Vec<u8>>, chunk_len: usize) -> Result<HashMap<u8,
 #[derive(Serialize, Deserialize)]
 pub(crate) struct SignContext {
     setup: Setup,
-    initial_signer: Signer,
+    /// Moved into the rounds once the protocol is initialized
+    initial_signer: Option<Signer>,
     message: Option<Vec<u8>>,
     indices: Option<Vec<u16>>,
     round: SignRound,
@@ -227,52 +270,79 @@ impl SignContext {
     fn init(&mut self, data: &[u8]) -> Result<Message> {
         let msg = ProtocolInit::decode(data)?;
         if msg.protocol_type != ProtocolType::Musig2 as i32 {
-            return Err("wrong protocol type".into());
+            return Err(Error::misuse("wrong protocol type"));
         }
 
         self.indices = Some(msg.indices.iter().map(|i| *i as u16).collect());
 
         if (msg.data.len() > 255) && self.setup.with_card {
-            return Err("Card supports messages only up to 255 bytes".into());
+            return Err(Error::malformed(
+                "Card supports messages only up to 255 bytes",
+            ));
         }
 
         self.message = Some(msg.data);
 
+        let signer = self
+            .initial_signer
+            .as_mut()
+            .ok_or_else(|| Error::misuse("protocol already initialized"))?;
+
         if self.setup.with_card {
             let command = jc::command::noncegen();
-            self.round = SignRound::R0GenerateNonce(self.initial_signer.clone());
+            self.start(SignRound::R0GenerateNonce);
 
             return Ok(Message::new_card_command(command));
         }
 
-        self.initial_signer.first_round();
+        signer.first_round();
 
-        let mut out_buffer = self.initial_signer.get_pubnonce()?.serialize().to_vec();
-        let internal_index: u8 = self.initial_signer.get_index() as u8;
+        let mut out_buffer = signer.get_pubnonce()?.serialize().to_vec();
+        let internal_index: u8 = signer.get_index() as u8;
 
         out_buffer.push(internal_index);
 
         // Serialize the public nonce and the internal index of the signer. Format: &[u8] + u8
         let msg = Message::serialize_broadcast(&out_buffer)?;
 
-        self.round = SignRound::R1(self.initial_signer.clone());
+        self.start(SignRound::R1);
 
         Ok(msg)
     }
 
-    fn update(&mut self, data: &[u8]) -> Result<Message> {
+    /// Moves the initial signer into the round made by `next`, so that the key share is never copied
+    fn start(&mut self, next: fn(Signer) -> SignRound) {
+        if let Some(signer) = self.initial_signer.take() {
+            self.round = next(signer);
+        }
+    }
+
+    /// The signer holding the key share, unless the protocol is finished
+    fn signer(&self) -> Option<&Signer> {
         match &self.round {
-            SignRound::R0 => Err("protocol not initialized".into()),
-            SignRound::R0GenerateNonce(signer) => {
+            SignRound::R0 => self.initial_signer.as_ref(),
+            SignRound::R0GenerateNonce(signer)
+            | SignRound::R0AwaitNonce(signer)
+            | SignRound::R1(signer)
+            | SignRound::R1PartiallySign(signer)
+            | SignRound::R1AwaitPartialSignature(signer)
+            | SignRound::R2(signer) => Some(signer),
+            SignRound::Done(_) => None,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) -> Result<Message> {
+        match &mut self.round {
+            SignRound::R0 => Err(Error::misuse("protocol not initialized")),
+            SignRound::R0GenerateNonce(_) => {
                 jc::response::noncegen(data)?;
                 let command: Vec<u8> = jc::command::get_pubnonce();
-                self.round = SignRound::R0AwaitNonce(signer.clone());
+                self.round.proceed(SignRound::R0AwaitNonce);
                 Ok(Message::new_card_command(command))
             }
             SignRound::R0AwaitNonce(signer) => {
                 let pubnonce = jc::response::get_pubnonce(data)?;
 
-                let mut signer = signer.clone();
                 signer.set_pubnonce(pubnonce)?;
 
                 //signer.set_pubnonce(pubnonce);
@@ -282,7 +352,7 @@ impl SignContext {
                 out_buffer.push(internal_index);
                 let msg = Message::serialize_broadcast(&out_buffer)?;
 
-                self.round = SignRound::R1(signer);
+                self.round.proceed(SignRound::R1);
                 Ok(msg)
             }
             SignRound::R1(signer) => {
@@ -291,30 +361,26 @@ impl SignContext {
 
                 // TODO: Is the pubnonce instance of this Signer object returned by the server?
                 // Generate hashmap <internal index of Signer, pubnonce>
-                let pubnonces = deserialize_musig(
-                    pubnonce_hashmap.values().cloned().collect(),
-                    PUBNONCE_CHUNK_LEN,
-                )?;
+                let pubnonces = deserialize_musig(pubnonce_hashmap, PUBNONCE_CHUNK_LEN)?;
 
                 // Create a vector of tuples (internal index of Signer, pubnonce)
-                let pubnonces: Vec<(usize, PubNonce)> = pubnonces
+                let pubnonces = pubnonces
                     .into_iter()
-                    .map(|(index, pubnonce)| {
-                        (index as usize, PubNonce::from_bytes(&pubnonce).unwrap())
+                    .map(|(index, (sender, pubnonce))| {
+                        let pubnonce = PubNonce::from_bytes(&pubnonce)
+                            .map_err(|_| Error::blame(sender, "invalid public nonce"))?;
+                        Ok((index as usize, pubnonce))
                     })
-                    .collect();
-
-                // Get copy of Signer object
-                let mut signer: Signer = signer.clone();
+                    .collect::<Result<Vec<(usize, PubNonce)>>>()?;
 
                 // Establish second round
                 if let Some(message) = &mut self.message {
-                    signer.second_round(message, pubnonces);
+                    signer.second_round(message, pubnonces)?;
 
                     if self.setup.with_card {
                         let aggnonce = signer.get_aggnonce()?;
                         let command = jc::command::set_agg_nonces(&aggnonce);
-                        self.round = SignRound::R1PartiallySign(signer);
+                        self.round.proceed(SignRound::R1PartiallySign);
 
                         return Ok(Message::new_card_command(command));
                     }
@@ -328,22 +394,21 @@ impl SignContext {
                     // Serialize the partial signature and the internal index of the signer. Format: &[u8] + u8
                     let msg = Message::serialize_broadcast(&out_buffer)?;
 
-                    self.round = SignRound::R2(signer);
+                    self.round.proceed(SignRound::R2);
 
                     Ok(msg)
                 } else {
-                    Err("message to sign not initialized".into())
+                    Err(Error::misuse("message to sign not initialized"))
                 }
             }
-            SignRound::R1PartiallySign(signer) => {
+            SignRound::R1PartiallySign(_) => {
                 jc::response::set_agg_nonces(data)?;
                 let command: Vec<u8> = jc::command::sign(self.message.as_ref().unwrap().as_slice());
-                self.round = SignRound::R1AwaitPartialSignature(signer.clone());
+                self.round.proceed(SignRound::R1AwaitPartialSignature);
                 Ok(Message::new_card_command(command))
             }
             SignRound::R1AwaitPartialSignature(signer) => {
                 let partial_signature = jc::response::sign(data)?;
-                let mut signer = signer.clone();
                 signer.set_partial_signature(partial_signature)?;
 
                 let mut out_buffer = partial_signature.serialize().to_vec();
@@ -355,47 +420,54 @@ impl SignContext {
                 // Serialize the partial signature and the internal index of the signer. Format: &[u8] + u8
                 let msg = Message::serialize_broadcast(&out_buffer)?;
 
-                self.round = SignRound::R2(signer.clone());
+                self.round.proceed(SignRound::R2);
 
                 Ok(msg)
             }
             SignRound::R2(signer) => {
                 let data = ServerMessage::decode(data)?.broadcasts;
                 let shares_hashmap: HashMap<u32, Vec<u8>> = deserialize_map(&data)?;
-                let shares = deserialize_musig(
-                    shares_hashmap.values().cloned().collect(),
-                    SCALAR_CHUNK_LEN,
-                )?;
+                let shares = deserialize_musig(shares_hashmap, SCALAR_CHUNK_LEN)?;
 
                 // Create a vector of tuples (internal index of Signer, partial signature)
-                let partial_signatures: Vec<(usize, PartialSignature)> = shares
+                let partial_signatures = shares
                     .into_iter()
-                    .map(|(index, partial_signature)| {
-                        (
-                            index as usize,
-                            PartialSignature::from_slice(&partial_signature).unwrap(),
-                        )
+                    .map(|(index, (sender, partial_signature))| {
+                        let partial_signature = PartialSignature::from_slice(&partial_signature)
+                            .map_err(|_| Error::blame(sender, "invalid partial signature"))?;
+                        Ok((index as usize, partial_signature))
                     })
-                    .collect();
+                    .collect::<Result<Vec<(usize, PartialSignature)>>>()?;
 
-                let mut signer: Signer = signer.clone();
                 signer.receive_partial_signatures(partial_signatures);
 
                 // Get aggregated signature and if successful, return it to the server
-                match signer.get_agg_signature() {
-                    Ok(signature) => {
-                        let msg = Message::serialize_broadcast(&signature)?;
-                        self.round = SignRound::Done(signature);
-                        Ok(msg)
-                    }
-                    Err(e) => Err(e.into()),
-                }
+                let signature = signer.get_agg_signature()?;
+                let msg = Message::serialize_broadcast(&signature)?;
+                self.round = SignRound::Done(signature);
+                Ok(msg)
             }
-            SignRound::Done(_) => Err("protocol already finished".into()),
+            SignRound::Done(_) => Err(Error::misuse("protocol already finished")),
         }
     }
 }
 
+impl SignRound {
+    /// Moves the signer of this round into the round made by `next`,
+    /// so that the key share is never copied
+    fn proceed(&mut self, next: fn(Signer) -> SignRound) {
+        *self = match std::mem::replace(self, SignRound::R0) {
+            SignRound::R0GenerateNonce(signer)
+            | SignRound::R0AwaitNonce(signer)
+            | SignRound::R1(signer)
+            | SignRound::R1PartiallySign(signer)
+            | SignRound::R1AwaitPartialSignature(signer)
+            | SignRound::R2(signer) => next(signer),
+            round => round,
+        };
+    }
+}
+
 #[typetag::serde(name = "musig2_sign")]
 impl Protocol for SignContext {
     fn advance(&mut self, data: &[u8]) -> Result<Message> {
@@ -408,25 +480,73 @@ impl Protocol for SignContext {
     fn finish(self: Box<Self>) -> Result<Vec<u8>> {
         match self.round {
             SignRound::Done(sig) => Ok(serde_json::to_vec(&sig)?),
-            _ => Err("protocol not finished".into()),
+            _ => Err(Error::misuse("protocol not finished")),
+        }
+    }
+
+    fn round(&self) -> &'static str {
+        match &self.round {
+            SignRound::R0 => "R0",
+            SignRound::R0GenerateNonce(..) => "R0GenerateNonce",
+            SignRound::R0AwaitNonce(..) => "R0AwaitNonce",
+            SignRound::R1(..) => "R1",
+            SignRound::R1PartiallySign(..) => "R1PartiallySign",
+            SignRound::R1AwaitPartialSignature(..) => "R1AwaitPartialSignature",
+            SignRound::R2(..) => "R2",
+            SignRound::Done(..) => "Done",
         }
     }
+
+    fn rounds(&self) -> u16 {
+        super::SIGN_ROUNDS
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(self.round, SignRound::Done(..))
+    }
+
+    fn group_params(&self) -> Option<GroupParams> {
+        Some(GroupParams {
+            parties: self.setup.parties.into(),
+            threshold: self.setup.threshold.into(),
+            index: self.setup.index.into(),
+        })
+    }
+
+    fn group_keys(&self) -> Option<GroupKeys> {
+        Some(GroupKeys {
+            public_key: self.signer()?.get_agg_pubkey().serialize().to_vec(),
+            // NOTE: the aggregated keys are sorted, so they cannot be attributed to the parties
+            verifying_shares: BTreeMap::new(),
+            with_card: self.setup.with_card,
+        })
+    }
 }
 
 impl ThresholdProtocol for SignContext {
-    fn new(group: &[u8]) -> Self {
-        let (setup, initial_signer): (Setup, Signer) =
-            serde_json::from_slice(group).expect("could not deserialize group context");
-        Self {
+    fn new(group: &[u8]) -> Result<Self> {
+        let (setup, initial_signer): (Setup, Signer) = decode_group(ProtocolType::Musig2, group)?;
+        Ok(Self {
             setup,
-            initial_signer,
+            initial_signer: Some(initial_signer),
             message: None,
             indices: None,
             round: SignRound::R0,
-        }
+        })
     }
 }
 
+/// Verifies a serialized `CompactSignature` under a compressed SEC1 encoded aggregate `PublicKey`
+pub(crate) fn verify(group_key: &[u8], message: &[u8], signature: &[u8]) -> Result<()> {
+    let key =
+        PublicKey::from_slice(group_key).map_err(|_| Error::malformed("invalid group key"))?;
+    let signature: CompactSignature =
+        serde_json::from_slice(signature).map_err(|_| Error::malformed("invalid signature"))?;
+    ::musig2::verify_single(key, signature, message)
+        .map_err(|_| Error::crypto("signature verification failed"))?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -484,10 +604,29 @@ mod tests {
             assert!(::musig2::verify_single(pk, signature, msg).is_ok());
         }
     }
+
+    #[test]
+    fn verify_signature() {
+        let (_, ctxs) = <KeygenContext as KeygenProtocolTest>::run(2, 2);
+        let shares: Vec<_> = ctxs.values().cloned().collect();
+        let pk = inspect_group(&shares).unwrap().public_key;
+        let signature =
+            <SignContext as ThresholdProtocolTest>::run(ctxs, b"hello".to_vec()).remove(0);
+
+        let result = crate::protocol::verify(ProtocolType::Musig2, &pk, b"hello", &signature);
+        assert!(result.is_ok());
+
+        assert!(verify(&pk, b"hello", &signature).is_ok());
+        let error = verify(&pk, b"bye", &signature).unwrap_err();
+        assert!(matches!(error, Error::Crypto { .. }));
+        let error = verify(&pk, b"hello", b"{}").unwrap_err();
+        assert!(matches!(error, Error::MalformedInput(_)));
+    }
 }
 
 mod jc {
     mod util {
+        use crate::error::Error;
         use crate::protocol::Result;
         use k256::{
             elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint},
@@ -495,10 +634,11 @@ mod jc {
         };
 
         pub fn reencode_point(ser: &[u8], compress: bool) -> Result<Box<[u8]>> {
-            let encoded = EncodedPoint::from_bytes(ser)?;
+            let encoded =
+                EncodedPoint::from_bytes(ser).map_err(|_| Error::malformed("Invalid point"))?;
             match Option::<AffinePoint>::from(AffinePoint::from_encoded_point(&encoded)) {
                 Some(affine) => Ok(affine.to_encoded_point(compress).to_bytes()),
-                None => Err("Invalid point".into()),
+                None => Err(Error::malformed("Invalid point")),
             }
         }
     }
@@ -576,6 +716,7 @@ mod jc {
         use musig2::{PartialSignature, PubNonce};
 
         use super::util::reencode_point;
+        use crate::error::Error;
         use crate::protocol::apdu::parse_response;
         use crate::protocol::Result;
 
@@ -592,7 +733,8 @@ mod jc {
 
         pub fn get_plain_pubkey(raw: &[u8]) -> Result<PublicKey> {
             let data = parse_response(raw)?;
-            let pubkey = PublicKey::from_slice(&reencode_point(data, true)?)?;
+            let pubkey = PublicKey::from_slice(&reencode_point(data, true)?)
+                .map_err(|_| Error::malformed("invalid public key"))?;
             Ok(pubkey)
         }
 
@@ -608,7 +750,8 @@ mod jc {
 
         pub fn get_pubnonce(raw: &[u8]) -> Result<PubNonce> {
             let data = parse_response(raw)?;
-            let pubnonce = PubNonce::from_bytes(data)?;
+            let pubnonce =
+                PubNonce::from_bytes(data).map_err(|_| Error::malformed("invalid public nonce"))?;
             Ok(pubnonce)
         }
 
@@ -619,7 +762,8 @@ mod jc {
 
         pub fn sign(raw: &[u8]) -> Result<PartialSignature> {
             let data = parse_response(raw)?;
-            let signature = PartialSignature::from_slice(data)?;
+            let signature = PartialSignature::from_slice(data)
+                .map_err(|_| Error::malformed("invalid partial signature"))?;
             Ok(signature)
         }
     }
@@ -753,7 +897,8 @@ mod jc {
             for i in 0..n {
                 let mut pubnonces_filtered = pubnonces_indexed.clone();
                 pubnonces_filtered.remove(i as usize);
-                signers[i as usize].second_round(&message.as_bytes().to_vec(), pubnonces_filtered);
+                signers[i as usize]
+                    .second_round(&message.as_bytes().to_vec(), pubnonces_filtered)?;
             }
 
             // Compute aggnonce (second round). Only for the card.
@@ -5,5 +5,5 @@ mod signer;
 #[cfg(feature = "musig2")]
 pub(crate) use implementation::*;
 
-pub const KEYGEN_ROUNDS: u16 = 2;
+pub const KEYGEN_ROUNDS: u16 = 2 + 1;
 pub const SIGN_ROUNDS: u16 = 3;
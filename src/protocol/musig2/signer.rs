@@ -1,3 +1,4 @@
+use crate::error::Error;
 use ::musig2::secp256k1::{PublicKey, Secp256k1, SecretKey};
 use musig2::adaptor::aggregate_partial_signatures;
 use musig2::secp::{MaybePoint, MaybeScalar};
@@ -7,12 +8,14 @@ use musig2::{
 };
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
 
 use rand::rngs::OsRng;
 
 const DUMMY_SKEY: [u8; 32] = [0; 32];
 
-#[derive(Serialize, Deserialize, Clone)]
+// NOTE: the signer is not `Clone`, so that its secrets are only ever held once
+#[derive(Serialize, Deserialize)]
 pub struct Signer {
     with_card: bool,
     pubkey: PublicKey,
@@ -46,6 +49,7 @@ impl Signer {
         let mut pubkey_array: [u8; 32] = [0; 32];
         rng.fill_bytes(&mut pubkey_array);
         let secret_key = SecretKey::from_byte_array(&pubkey_array).unwrap();
+        pubkey_array.zeroize();
         let public_key = PublicKey::from_secret_key(&secp, &secret_key);
 
         Self {
@@ -92,6 +96,8 @@ impl Signer {
         self.pubkey
     }
 
+    // NOTE: `SecretKey` is `Copy` and the musig2 crate takes it by value, so the
+    // copies handed to it cannot be wiped; only the stored key is erased on drop
     fn seckey(&self) -> SecretKey {
         match self.seckey {
             None => panic!("Secret key not set"),
@@ -133,21 +139,25 @@ impl Signer {
     pub fn set_partial_signature(
         &mut self,
         partial_signature: PartialSignature,
-    ) -> Result<(), String> {
+    ) -> Result<(), Error> {
         if self.with_card {
             self.partial_signature = Some(partial_signature);
             Ok(())
         } else {
-            Err("Only card based signers can set their partial signature.".into())
+            Err(Error::misuse(
+                "Only card based signers can set their partial signature.",
+            ))
         }
     }
 
-    pub fn set_pubnonce(&mut self, pubnonce: PubNonce) -> Result<(), String> {
+    pub fn set_pubnonce(&mut self, pubnonce: PubNonce) -> Result<(), Error> {
         if self.with_card {
             self.pubnonce = Some(pubnonce);
             Ok(())
         } else {
-            Err("Only card based signers can set their pubnonce.".into())
+            Err(Error::misuse(
+                "Only card based signers can set their pubnonce.",
+            ))
         }
     }
 
@@ -160,6 +170,7 @@ impl Signer {
         rng.fill_bytes(&mut nonce_seed);
 
         self.secnonce = Some(nonce_seed);
+        nonce_seed.zeroize();
     }
 
     fn first_round_internal(&self) -> Result<FirstRound, String> {
@@ -189,14 +200,14 @@ impl Signer {
         }
     }
 
-    pub fn get_pubnonce(&self) -> Result<PubNonce, String> {
+    pub fn get_pubnonce(&self) -> Result<PubNonce, Error> {
         if self.with_card {
             match &self.pubnonce {
                 Some(pubnonce) => {
                     return Ok(pubnonce.clone());
                 }
                 None => {
-                    return Err("No pubnonce set for card signer".into());
+                    return Err(Error::misuse("No pubnonce set for card signer"));
                 }
             }
         }
@@ -205,7 +216,7 @@ impl Signer {
 
         match first_round {
             Ok(first_round) => Ok(first_round.our_public_nonce()),
-            Err(_) => Err("Error getting pubnonce".into()),
+            Err(_) => Err(Error::misuse("Error getting pubnonce")),
         }
     }
 
@@ -215,22 +226,17 @@ impl Signer {
         nonce_index: &usize,
         pubnonce: PubNonce,
         first_round: &mut FirstRound,
-    ) {
+    ) -> Result<(), Error> {
         let index: usize = *nonce_index;
 
-        let res = first_round.receive_nonce(index, pubnonce);
-
-        match res {
-            Ok(_) => {}
-            Err(e) => {
-                panic!("Error adding pubnonce: {:?}", e);
-            }
-        }
+        first_round
+            .receive_nonce(index, pubnonce)
+            .map_err(|e| Error::crypto(format!("Error adding pubnonce: {:?}", e)))
     }
 
-    pub fn get_aggnonce(&self) -> Result<AggNonce, String> {
+    pub fn get_aggnonce(&self) -> Result<AggNonce, Error> {
         match &self.pub_nonces {
-            None => return Err("Pubnonces not initialized".into()),
+            None => return Err(Error::misuse("Pubnonces not initialized")),
             Some(pub_nonces) => {
                 let mut pubnonces_all = pub_nonces.clone();
                 pubnonces_all.push((self.get_index(), self.get_pubnonce()?));
@@ -240,37 +246,52 @@ impl Signer {
         };
     }
 
-    pub fn second_round(&mut self, message: &[u8], pubnonces: Vec<(usize, PubNonce)>) {
-        self.message = Some(message.to_owned());
-        self.pub_nonces = Some(pubnonces.clone());
+    /// Sets the message and the pubnonces of the other signers,
+    /// unless they do not make up a second round, in which case the signer is left unchanged
+    pub fn second_round(
+        &mut self,
+        message: &[u8],
+        pubnonces: Vec<(usize, PubNonce)>,
+    ) -> Result<(), Error> {
+        let message = self.message.replace(message.to_owned());
+        let pub_nonces = self.pub_nonces.replace(pubnonces);
+
+        let result = if self.with_card {
+            self.get_aggnonce().map(|_| ())
+        } else {
+            self.second_round_internal().map(|_| ())
+        };
+        if result.is_err() {
+            self.message = message;
+            self.pub_nonces = pub_nonces;
+        }
+        result
     }
 
-    fn second_round_internal(&mut self) -> Result<SecondRound<Vec<u8>>, String> {
+    fn second_round_internal(&mut self) -> Result<SecondRound<Vec<u8>>, Error> {
         let pubnonces = match &self.pub_nonces {
             Some(pubnonces) => pubnonces.clone(),
-            None => return Err("Pubnonces not initialized".into()),
+            None => return Err(Error::misuse("Pubnonces not initialized")),
         };
 
         let message = match &self.message {
             Some(message) => message.clone(),
-            None => return Err("Message not initialized".into()),
+            None => return Err(Error::misuse("Message not initialized")),
         };
 
-        let mut first_round = self.first_round_internal().unwrap();
+        if self.with_card {
+            return Err(Error::misuse("Card signers cant generate second round."));
+        }
+
+        let mut first_round = self.first_round_internal().map_err(Error::misuse)?;
 
         for (index, pubnonce) in pubnonces.iter() {
-            self.add_pubnonce(index, pubnonce.clone(), &mut first_round);
+            self.add_pubnonce(index, pubnonce.clone(), &mut first_round)?;
         }
 
-        let second_round: SecondRound<Vec<u8>> = if self.with_card {
-            return Err("Card signers cant generate second round.".into());
-        } else {
-            first_round
-                .finalize::<Vec<u8>>(self.seckey(), message)
-                .unwrap()
-        };
-
-        Ok(second_round)
+        first_round
+            .finalize::<Vec<u8>>(self.seckey(), message)
+            .map_err(|e| Error::crypto(e.to_string()))
     }
 
     pub fn get_partial_signature(&mut self) -> PartialSignature {
@@ -300,52 +321,50 @@ impl Signer {
         self.partial_signatures = Some(partial_signatures);
     }
 
-    pub fn get_agg_signature(&mut self) -> Result<CompactSignature, String> {
-        let partial_signatures = match &self.partial_signatures {
-            Some(partial_signatures) => partial_signatures.clone(),
-            None => return Err("Partial signatures not initialized".into()),
+    /// Aggregates the received partial signatures, which are dropped even if they are invalid,
+    /// so that the signer is left as it was before receiving them
+    pub fn get_agg_signature(&mut self) -> Result<CompactSignature, Error> {
+        let partial_signatures = match self.partial_signatures.take() {
+            Some(partial_signatures) => partial_signatures,
+            None => return Err(Error::misuse("Partial signatures not initialized")),
         };
 
         let agg_signature = if self.with_card {
             self.get_agg_signature_with_card(partial_signatures)
         } else {
             self.get_agg_signature_no_card(partial_signatures)
-        };
+        }?;
 
         // Erase nonces for security reasons
-        self.secnonce = None;
+        self.secnonce.zeroize();
         self.pub_nonces = None;
-        self.partial_signatures = None;
 
-        agg_signature
+        Ok(agg_signature)
     }
 
     fn get_agg_signature_no_card(
         &mut self,
         partial_signatures: Vec<(usize, MaybeScalar)>,
-    ) -> Result<CompactSignature, String> {
-        let mut sr = match self.second_round_internal() {
-            Ok(sr) => sr,
-            Err(_) => return Err("Second round not initialized".into()),
-        };
+    ) -> Result<CompactSignature, Error> {
+        let mut sr = self.second_round_internal()?;
 
         for (signer_index, partial_signature) in partial_signatures {
             match sr.receive_signature(signer_index, partial_signature) {
                 Ok(_) => {}
                 Err(_) => {
-                    return Err("Error receiving partial signature".into());
+                    return Err(Error::crypto("Error receiving partial signature"));
                 }
             }
         }
 
-        Ok(sr.finalize().unwrap())
+        sr.finalize().map_err(|e| Error::crypto(e.to_string()))
     }
 
     // Insipired by [`musig2::SecondRound::finalize`] method
     fn get_agg_signature_with_card<T>(
         &mut self,
         partial_signatures: Vec<(usize, MaybeScalar)>,
-    ) -> Result<T, String>
+    ) -> Result<T, Error>
     where
         T: From<LiftedSignature>,
     {
@@ -367,7 +386,7 @@ impl Signer {
         );
 
         let agg_sig = sig
-            .map_err(|e| e.to_string())?
+            .map_err(|e| Error::crypto(e.to_string()))?
             .adapt(MaybeScalar::Zero)
             .expect("finalizing with empty adaptor should never result in an adaptor failure");
 
@@ -391,3 +410,12 @@ impl Signer {
         self.index = index;
     }
 }
+
+impl Drop for Signer {
+    fn drop(&mut self) {
+        if let Some(seckey) = self.seckey.as_mut() {
+            seckey.non_secure_erase();
+        }
+        self.secnonce.zeroize();
+    }
+}
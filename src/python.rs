@@ -0,0 +1,395 @@
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::auth;
+use crate::error::Error;
+#[cfg(feature = "elgamal")]
+use crate::protocol::elgamal;
+use crate::protocol::Recipient as ProtocolRecipient;
+use crate::security::{
+    derive_state_key, verify_receipt as verify_transcript_receipt,
+    AbortReason as SecureLayerAbortReason, ProtocolType,
+};
+use crate::session::Session;
+use crate::suite::CipherSuite as AuthCipherSuite;
+
+create_exception!(
+    meesign_crypto,
+    MeeSignError,
+    PyException,
+    "Base class of the errors raised by meesign_crypto"
+);
+create_exception!(
+    meesign_crypto,
+    MisuseError,
+    MeeSignError,
+    "An operation was requested in a state which does not allow it"
+);
+create_exception!(
+    meesign_crypto,
+    MalformedInputError,
+    MeeSignError,
+    "An input could not be parsed or does not fit the session"
+);
+create_exception!(
+    meesign_crypto,
+    CryptoError,
+    MeeSignError,
+    "A cryptographic check failed, args[1] holds the culprit party or None"
+);
+create_exception!(
+    meesign_crypto,
+    CardError,
+    MeeSignError,
+    "A smart card returned an error, args[1] holds its status word"
+);
+create_exception!(
+    meesign_crypto,
+    SerializationError,
+    MeeSignError,
+    "A protocol state could not be serialized or deserialized"
+);
+create_exception!(
+    meesign_crypto,
+    AbortedError,
+    MeeSignError,
+    "A party aborted the protocol, args[1] holds the party and args[2] whether it declined"
+);
+
+/// Raises the exception matching the classification of `error`
+fn raise(error: Error) -> PyErr {
+    let message = error.to_string();
+    match error {
+        Error::Misuse(_) => MisuseError::new_err(message),
+        Error::MalformedInput(_) => MalformedInputError::new_err(message),
+        Error::Crypto { culprit, .. } => CryptoError::new_err((message, culprit)),
+        Error::Card { status } => CardError::new_err((message, status)),
+        Error::Serialization(_) => SerializationError::new_err(message),
+        Error::Aborted { party, declined } => AbortedError::new_err((message, party, declined)),
+        Error::Panic(_) => MeeSignError::new_err(message),
+    }
+}
+
+#[pyclass]
+#[derive(Clone, Copy)]
+pub enum ProtocolId {
+    Gg18,
+    Elgamal,
+    Frost,
+    Musig2,
+}
+
+impl From<ProtocolId> for ProtocolType {
+    fn from(pid: ProtocolId) -> Self {
+        match pid {
+            ProtocolId::Gg18 => ProtocolType::Gg18,
+            ProtocolId::Elgamal => ProtocolType::Elgamal,
+            ProtocolId::Frost => ProtocolType::Frost,
+            ProtocolId::Musig2 => ProtocolType::Musig2,
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Clone, Copy)]
+pub enum AbortReason {
+    Error,
+    Declined,
+}
+
+impl From<AbortReason> for SecureLayerAbortReason {
+    fn from(reason: AbortReason) -> Self {
+        match reason {
+            AbortReason::Error => SecureLayerAbortReason::Error,
+            AbortReason::Declined => SecureLayerAbortReason::Declined,
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Clone, Copy)]
+pub enum CipherSuite {
+    P256Ecies,
+    Ed25519X25519,
+}
+
+impl From<CipherSuite> for AuthCipherSuite {
+    fn from(suite: CipherSuite) -> Self {
+        match suite {
+            CipherSuite::P256Ecies => AuthCipherSuite::P256Ecies,
+            CipherSuite::Ed25519X25519 => AuthCipherSuite::Ed25519X25519,
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Recipient {
+    Card,
+    Server,
+}
+
+impl From<ProtocolRecipient> for Recipient {
+    fn from(recipient: ProtocolRecipient) -> Self {
+        match recipient {
+            ProtocolRecipient::Card => Recipient::Card,
+            ProtocolRecipient::Server => Recipient::Server,
+        }
+    }
+}
+
+/// Where the computation of one share stands
+#[pyclass(get_all)]
+pub struct ShareStatus {
+    /// The name of the state of the secure layer, e.g. `Running`
+    state: String,
+    aborted_by: Option<u32>,
+    /// The name of the protocol round, e.g. `R11AwaitCommitmentResp`
+    round: String,
+    rounds_done: u16,
+    rounds_total: u16,
+    recipient: Option<Recipient>,
+    finished: bool,
+}
+
+/// A protocol session computing one or more shares of a participant
+#[pyclass(unsendable)]
+pub struct Protocol {
+    /// Taken by `finish`
+    inner: Option<Session>,
+}
+
+impl Protocol {
+    fn wrap(inner: Session) -> Self {
+        Protocol { inner: Some(inner) }
+    }
+
+    fn inner(&self) -> PyResult<&Session> {
+        self.inner
+            .as_ref()
+            .ok_or_else(|| MisuseError::new_err("protocol already finished"))
+    }
+
+    fn share(&mut self, index: usize) -> PyResult<&mut Session> {
+        let inner = self
+            .inner
+            .as_mut()
+            .ok_or_else(|| MisuseError::new_err("protocol already finished"))?;
+        if index >= inner.share_count() {
+            return Err(MisuseError::new_err(format!("no share {index}")));
+        }
+        Ok(inner)
+    }
+}
+
+#[pymethods]
+impl Protocol {
+    /// Starts a keygen computing `shares` shares with the certificates
+    /// of all participants sent by the server
+    #[staticmethod]
+    #[pyo3(signature = (protocol, certs, pkcs12, shares = 1, with_card = false))]
+    fn keygen(
+        protocol: ProtocolId,
+        certs: &[u8],
+        pkcs12: &[u8],
+        shares: usize,
+        with_card: bool,
+    ) -> PyResult<Self> {
+        Session::keygen(protocol.into())
+            .certs(certs)
+            .identity(pkcs12)
+            .shares(shares)
+            .with_card(with_card)
+            .build()
+            .map(Protocol::wrap)
+            .map_err(raise)
+    }
+
+    /// Starts a threshold protocol computing the `group` shares output by a keygen.
+    /// The protocol is detected from the group, and checked against `protocol` if given
+    #[staticmethod]
+    #[pyo3(signature = (group, certs, pkcs12, protocol = None))]
+    fn init(
+        group: Vec<Vec<u8>>,
+        certs: &[u8],
+        pkcs12: &[u8],
+        protocol: Option<ProtocolId>,
+    ) -> PyResult<Self> {
+        let mut builder = Session::threshold(group).certs(certs).identity(pkcs12);
+        if let Some(protocol) = protocol {
+            builder = builder.protocol(protocol.into());
+        }
+        builder.build().map(Protocol::wrap).map_err(raise)
+    }
+
+    #[staticmethod]
+    fn deserialize(data: &[u8]) -> PyResult<Self> {
+        Session::from_bytes(data).map(Protocol::wrap).map_err(raise)
+    }
+
+    fn serialize<'py>(&self, py: Python<'py>) -> PyResult<&'py PyBytes> {
+        let data = self.inner()?.to_bytes().map_err(raise)?;
+        Ok(PyBytes::new(py, &data))
+    }
+
+    /// Restores a protocol sealed with `seal`
+    #[staticmethod]
+    fn unseal(sealed: &[u8], key: &[u8]) -> PyResult<Self> {
+        Session::unseal(sealed, key)
+            .map(Protocol::wrap)
+            .map_err(raise)
+    }
+
+    /// Serializes the protocol encrypted under `key`, see `state_key`
+    fn seal<'py>(&self, py: Python<'py>, key: &[u8]) -> PyResult<&'py PyBytes> {
+        let sealed = self.inner()?.seal(key).map_err(raise)?;
+        Ok(PyBytes::new(py, &sealed))
+    }
+
+    /// Advances share `index` with a message from the server or a card,
+    /// returning the response and its recipient. A share which fails a cryptographic check
+    /// is aborted and its abort message for the server can then be obtained from `abort`
+    fn advance<'py>(
+        &mut self,
+        py: Python<'py>,
+        index: usize,
+        data: &[u8],
+    ) -> PyResult<(&'py PyBytes, Recipient)> {
+        let response = self.share(index)?.advance(index, data).map_err(raise)?;
+        Ok((PyBytes::new(py, &response.data), response.recipient.into()))
+    }
+
+    /// Aborts share `index` and returns the abort message for the server
+    fn abort<'py>(
+        &mut self,
+        py: Python<'py>,
+        index: usize,
+        reason: AbortReason,
+    ) -> PyResult<&'py PyBytes> {
+        let abort = self
+            .share(index)?
+            .abort(index, reason.into())
+            .map_err(raise)?;
+        Ok(PyBytes::new(py, &abort))
+    }
+
+    fn share_count(&self) -> PyResult<usize> {
+        Ok(self.inner()?.share_count())
+    }
+
+    fn status(&self, index: usize) -> PyResult<ShareStatus> {
+        let status = self.inner()?.status(index).map_err(raise)?;
+        Ok(ShareStatus {
+            state: format!("{:?}", status.state),
+            aborted_by: status.aborted_by,
+            round: status.round.into(),
+            rounds_done: status.rounds_done,
+            rounds_total: status.rounds_total,
+            recipient: status.recipient.map(Into::into),
+            finished: status.finished,
+        })
+    }
+
+    /// Finishes the protocol and returns the result of each share,
+    /// i.e. the group shares of a keygen or the outputs of a threshold protocol
+    fn finish<'py>(&mut self, py: Python<'py>) -> PyResult<Vec<&'py PyBytes>> {
+        let (results, _) = self.finish_with_receipts(py)?;
+        Ok(results)
+    }
+
+    /// Finishes the protocol like `finish`, additionally returning
+    /// the signed transcript receipts of all shares
+    fn finish_with_receipts<'py>(
+        &mut self,
+        py: Python<'py>,
+    ) -> PyResult<(Vec<&'py PyBytes>, Vec<&'py PyBytes>)> {
+        let inner = self
+            .inner
+            .take()
+            .ok_or_else(|| MisuseError::new_err("protocol already finished"))?;
+        let output = inner.finish_with_receipts().map_err(raise)?;
+        let to_bytes = |items: Vec<Vec<u8>>| {
+            items
+                .iter()
+                .map(|item| PyBytes::new(py, item))
+                .collect::<Vec<_>>()
+        };
+        Ok((to_bytes(output.results), to_bytes(output.receipts)))
+    }
+}
+
+/// Generates the keys of an identity, returning them with a certificate signing request
+#[pyfunction]
+#[pyo3(signature = (name, suite = CipherSuite::P256Ecies, hybrid = false))]
+fn auth_keygen<'py>(
+    py: Python<'py>,
+    name: &str,
+    suite: CipherSuite,
+    hybrid: bool,
+) -> PyResult<(&'py PyBytes, &'py PyBytes)> {
+    let (key, csr) = auth::gen_key_with_csr_for_suite(name, suite.into(), hybrid).map_err(raise)?;
+    Ok((PyBytes::new(py, &key), PyBytes::new(py, &csr)))
+}
+
+/// Bundles the keys of an identity with its issued certificate
+#[pyfunction]
+fn auth_cert_key_to_pkcs12<'py>(
+    py: Python<'py>,
+    key: &[u8],
+    cert: &[u8],
+) -> PyResult<&'py PyBytes> {
+    let pkcs12 = auth::cert_key_to_pkcs12(key, cert).map_err(raise)?;
+    Ok(PyBytes::new(py, &pkcs12))
+}
+
+/// Derives a key for sealing protocol states from the private bundle in `pkcs12`.
+/// NOTE: the returned copy is not wiped by the bindings, as Python bytes are immutable
+#[pyfunction]
+fn state_key<'py>(py: Python<'py>, pkcs12: &[u8]) -> PyResult<&'py PyBytes> {
+    let key = derive_state_key(pkcs12).map_err(raise)?;
+    Ok(PyBytes::new(py, &key))
+}
+
+/// Verifies a transcript receipt against the certificate of its issuer
+/// and returns the transcript digest
+#[pyfunction]
+fn verify_receipt<'py>(py: Python<'py>, receipt: &[u8], cert: &[u8]) -> PyResult<&'py PyBytes> {
+    let digest = verify_transcript_receipt(receipt, cert).map_err(raise)?;
+    Ok(PyBytes::new(py, &digest))
+}
+
+/// Encrypts `msg` under the ElGamal group key `key`
+#[cfg(feature = "elgamal")]
+#[pyfunction]
+fn encrypt<'py>(py: Python<'py>, msg: &[u8], key: &[u8]) -> PyResult<&'py PyBytes> {
+    let ciphertext = elgamal::encrypt(msg, key).map_err(raise)?;
+    Ok(PyBytes::new(py, &ciphertext))
+}
+
+#[pymodule]
+fn meesign_crypto(py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<ProtocolId>()?;
+    m.add_class::<AbortReason>()?;
+    m.add_class::<CipherSuite>()?;
+    m.add_class::<Recipient>()?;
+    m.add_class::<ShareStatus>()?;
+    m.add_class::<Protocol>()?;
+
+    m.add_function(wrap_pyfunction!(auth_keygen, m)?)?;
+    m.add_function(wrap_pyfunction!(auth_cert_key_to_pkcs12, m)?)?;
+    m.add_function(wrap_pyfunction!(state_key, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_receipt, m)?)?;
+    #[cfg(feature = "elgamal")]
+    m.add_function(wrap_pyfunction!(encrypt, m)?)?;
+
+    m.add("MeeSignError", py.get_type::<MeeSignError>())?;
+    m.add("MisuseError", py.get_type::<MisuseError>())?;
+    m.add("MalformedInputError", py.get_type::<MalformedInputError>())?;
+    m.add("CryptoError", py.get_type::<CryptoError>())?;
+    m.add("CardError", py.get_type::<CardError>())?;
+    m.add("SerializationError", py.get_type::<SerializationError>())?;
+    m.add("AbortedError", py.get_type::<AbortedError>())?;
+    Ok(())
+}
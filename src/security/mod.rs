@@ -1,18 +1,31 @@
-use crate::auth::{extract_public_bundle_der, MeeSignPrivateBundle, MeeSignPublicBundle};
+use crate::auth::{
+    extract_private_bundle_der, extract_public_bundle_der, MeeSignPrivateBundle,
+    MeeSignPublicBundle,
+};
+use crate::error::Error;
+use crate::hybrid::{self, PqSigningKey, PqVerifyingKey};
 use crate::proto::{
     self, ClientMessage, ProtocolGroupInit, ProtocolInit, ServerMessage, SignedMessage,
 };
-use crate::protocol::{Protocol, Recipient, Result};
+use crate::protocol::{self, Protocol, Recipient, Result};
+use crate::suite::{self, CipherSuite, SigningKey, VerifyingKey};
 use crate::util::Message;
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use chacha20poly1305::ChaCha20Poly1305;
 use der::{self, Decode as _};
-use p256::ecdsa;
-use p256::ecdsa::signature::{Signer as _, Verifier as _};
-use p256::pkcs8::{DecodePrivateKey as _, DecodePublicKey as _};
+use hkdf::Hkdf;
 use prost::Message as _;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest as _, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use zeroize::{Zeroize, Zeroizing};
 
-#[derive(Copy, Clone, Deserialize, Serialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum ProtocolType {
     Gg18,
     Elgamal,
@@ -31,52 +44,731 @@ impl From<ProtocolType> for proto::ProtocolType {
     }
 }
 
+impl From<proto::ProtocolType> for ProtocolType {
+    fn from(pt: proto::ProtocolType) -> ProtocolType {
+        match pt {
+            proto::ProtocolType::Gg18 => ProtocolType::Gg18,
+            proto::ProtocolType::Elgamal => ProtocolType::Elgamal,
+            proto::ProtocolType::Frost => ProtocolType::Frost,
+            proto::ProtocolType::Musig2 => ProtocolType::Musig2,
+        }
+    }
+}
+
 impl From<ProtocolType> for i32 {
     fn from(pt: ProtocolType) -> i32 {
         proto::ProtocolType::from(pt).into()
     }
 }
 
-fn verify_message(signed_message: &[u8], key: &ecdsa::VerifyingKey) -> Result<Vec<u8>> {
+/// The version of the messages of the secure layer, see `capabilities::wire_versions`
+pub(crate) const MESSAGES_VERSION: u32 = 1;
+
+/// The version of sealed states, which is named in `SEALED_STATE_AAD`
+pub(crate) const SEALED_STATE_VERSION: u32 = 1;
+
+/// Binds sealed states to the format of their plaintext
+const SEALED_STATE_AAD: &[u8] = b"meesign sealed state v1";
+const SEALED_STATE_NONCE_LEN: usize = 12;
+
+/// Separates key confirmations from other broadcasts signed with the same key
+const KEY_CONFIRMATION_CONTEXT: &[u8] = b"meesign key confirmation v1";
+
+/// Separates commitments to the keys of blind broadcasts from other hashes
+const BLIND_BROADCAST_CONTEXT: &[u8] = b"meesign blind broadcast v1";
+
+/// Upper bound on the size of a single message of one party
+const MAX_MESSAGE_LEN: usize = 1 << 20;
+
+/// Upper bound on the size of a signed digest or abort of one party
+const MAX_CONTROL_MESSAGE_LEN: usize = 1 << 14;
+
+/// Upper bound on the encoding overhead of a single entry of a `ServerMessage`
+const MAX_ENTRY_OVERHEAD: usize = 32;
+
+/// Derives a key for `SecureLayer::seal` from the private bundle of a PKCS#12 file
+pub fn derive_state_key(pfx_der: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let private_bundle = extract_private_bundle_der(pfx_der)?;
+    let mut key = Zeroizing::new(vec![0u8; 32]);
+    Hkdf::<Sha256>::new(None, &private_bundle)
+        .expand(b"meesign state key", &mut key)
+        .map_err(|_| Error::crypto("failed to derive state key"))?;
+    Ok(key)
+}
+
+/// Determines what the parties relay to each other in the echo-broadcast round
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum EchoMode {
+    /// Relays all received broadcasts
+    #[default]
+    Full,
+    /// Relays a digest of all received broadcasts and falls back to `Full` on mismatch
+    Digest,
+}
+
+impl From<proto::EchoMode> for EchoMode {
+    fn from(mode: proto::EchoMode) -> EchoMode {
+        match mode {
+            proto::EchoMode::Full => EchoMode::Full,
+            proto::EchoMode::Digest => EchoMode::Digest,
+        }
+    }
+}
+
+/// Why a party aborted the protocol
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum AbortReason {
+    /// The computation failed locally
+    Error,
+    /// The user declined to take part
+    Declined,
+}
+
+impl From<proto::AbortReason> for AbortReason {
+    fn from(reason: proto::AbortReason) -> AbortReason {
+        match reason {
+            proto::AbortReason::Error => AbortReason::Error,
+            proto::AbortReason::Declined => AbortReason::Declined,
+        }
+    }
+}
+
+impl From<AbortReason> for proto::AbortReason {
+    fn from(reason: AbortReason) -> proto::AbortReason {
+        match reason {
+            AbortReason::Error => proto::AbortReason::Error,
+            AbortReason::Declined => proto::AbortReason::Declined,
+        }
+    }
+}
+
+/// The error reported after party `party` aborted the protocol
+fn abort_error(party: u32, reason: AbortReason) -> Error {
+    Error::Aborted {
+        party,
+        declined: reason == AbortReason::Declined,
+    }
+}
+
+/// A `MeeSignPublicBundle` with its keys parsed once,
+/// serialized as the DER encoding of the bundle
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(try_from = "Vec<u8>", into = "Vec<u8>")]
+struct PublicBundle {
+    der: Vec<u8>,
+    suite: CipherSuite,
+    broadcast_sign: VerifyingKey,
+    unicast_sign: VerifyingKey,
+    unicast_encrypt: Vec<u8>,
+    pq_broadcast_sign: Option<PqVerifyingKey>,
+    pq_unicast_encrypt: Option<Vec<u8>>,
+}
+
+impl TryFrom<Vec<u8>> for PublicBundle {
+    type Error = Error;
+
+    fn try_from(der: Vec<u8>) -> Result<Self> {
+        let bundle = MeeSignPublicBundle::from_der(&der)?;
+        let suite = CipherSuite::try_from(bundle.suite)?;
+        Ok(Self {
+            suite,
+            broadcast_sign: VerifyingKey::from_public_key_der(suite, &bundle.broadcast_sign)?,
+            unicast_sign: VerifyingKey::from_public_key_der(suite, &bundle.unicast_sign)?,
+            unicast_encrypt: bundle.unicast_encrypt,
+            pq_broadcast_sign: bundle
+                .pq_broadcast_sign
+                .map(|key| PqVerifyingKey::from_bytes(&key))
+                .transpose()?,
+            pq_unicast_encrypt: bundle.pq_unicast_encrypt,
+            der,
+        })
+    }
+}
+
+impl PublicBundle {
+    /// Verifies a broadcast of the owner of the bundle, including its co-signature in hybrid mode
+    fn verify_broadcast(&self, signed_message: &[u8]) -> Result<Vec<u8>> {
+        let signed_message = SignedMessage::decode(signed_message)?;
+        self.broadcast_sign
+            .verify(&signed_message.message, &signed_message.signature)?;
+        if let Some(pq_key) = &self.pq_broadcast_sign {
+            pq_key.verify(&signed_message.message, &signed_message.pq_signature)?;
+        }
+        Ok(signed_message.message)
+    }
+
+    /// Encrypts a unicast to the owner of the bundle, wrapping it under ML-KEM in hybrid mode
+    fn encrypt_unicast(&self, unicast: &[u8]) -> Result<Vec<u8>> {
+        let encrypted = suite::encrypt(self.suite, &self.unicast_encrypt, unicast)?;
+        match &self.pq_unicast_encrypt {
+            Some(pq_key) => hybrid::encrypt(pq_key, &encrypted),
+            None => Ok(encrypted),
+        }
+    }
+}
+
+impl From<PublicBundle> for Vec<u8> {
+    fn from(bundle: PublicBundle) -> Self {
+        bundle.der
+    }
+}
+
+/// A `MeeSignPrivateBundle` with its keys parsed once,
+/// serialized as the DER encoding of the bundle.
+/// All key material is wiped from memory on drop
+struct PrivateBundle {
+    der: Zeroizing<Vec<u8>>,
+    suite: CipherSuite,
+    broadcast_sign: SigningKey,
+    unicast_sign: SigningKey,
+    unicast_decrypt: Zeroizing<Vec<u8>>,
+    pq_broadcast_sign: Option<PqSigningKey>,
+    pq_unicast_decrypt: Option<Zeroizing<Vec<u8>>>,
+}
+
+impl PrivateBundle {
+    fn from_der(der: Zeroizing<Vec<u8>>) -> Result<Self> {
+        let bundle = MeeSignPrivateBundle::from_der(&der)?;
+        let suite = CipherSuite::try_from(bundle.suite)?;
+        Ok(Self {
+            suite,
+            broadcast_sign: SigningKey::from_pkcs8_der(suite, &bundle.broadcast_sign)?,
+            unicast_sign: SigningKey::from_pkcs8_der(suite, &bundle.unicast_sign)?,
+            unicast_decrypt: Zeroizing::new(bundle.unicast_decrypt.clone()),
+            pq_broadcast_sign: bundle
+                .pq_broadcast_sign
+                .as_ref()
+                .map(|key| PqSigningKey::from_bytes(key))
+                .transpose()?,
+            pq_unicast_decrypt: bundle.pq_unicast_decrypt.clone().map(Zeroizing::new),
+            der,
+        })
+    }
+
+    /// Signs a broadcast, adding a co-signature in hybrid mode
+    fn sign_broadcast(&self, message: Vec<u8>) -> Vec<u8> {
+        let signature = self.broadcast_sign.sign(&message);
+        let pq_signature = self
+            .pq_broadcast_sign
+            .as_ref()
+            .map(|pq_key| pq_key.sign(&message))
+            .unwrap_or_default();
+        SignedMessage {
+            message,
+            signature,
+            pq_signature,
+        }
+        .encode_to_vec()
+    }
+
+    /// Decrypts a unicast encrypted by `PublicBundle::encrypt_unicast`
+    fn decrypt_unicast(&self, unicast: &[u8]) -> Result<Vec<u8>> {
+        let unwrapped;
+        let unicast = match &self.pq_unicast_decrypt {
+            Some(pq_key) => {
+                unwrapped = hybrid::decrypt(pq_key, unicast)?;
+                &unwrapped
+            }
+            None => unicast,
+        };
+        suite::decrypt(self.suite, &self.unicast_decrypt, unicast)
+    }
+}
+
+impl Serialize for PrivateBundle {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.der.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PrivateBundle {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let der = Zeroizing::<Vec<u8>>::deserialize(deserializer)?;
+        Self::from_der(der).map_err(serde::de::Error::custom)
+    }
+}
+
+fn verify_message(signed_message: &[u8], key: &VerifyingKey) -> Result<Vec<u8>> {
     let signed_message = SignedMessage::decode(signed_message)?;
-    let signature = ecdsa::Signature::from_slice(&signed_message.signature)?;
-    key.verify(&signed_message.message, &signature)?;
+    key.verify(&signed_message.message, &signed_message.signature)?;
     Ok(signed_message.message)
 }
 
+/// Hashes the broadcasts of a round, including our own, in the order of their senders
+fn broadcast_digest(
+    original_msgs: &HashMap<u32, Vec<u8>>,
+    our_index: u32,
+    our_msg: &[u8],
+) -> Vec<u8> {
+    let mut msgs: Vec<_> = original_msgs
+        .iter()
+        .map(|(&sender, msg)| (sender, msg.as_slice()))
+        .chain(std::iter::once((our_index, our_msg)))
+        .collect();
+    msgs.sort_by_key(|(sender, _)| *sender);
+
+    let mut hasher = Sha256::new();
+    for (sender, msg) in msgs {
+        hasher.update(sender.to_be_bytes());
+        hasher.update((msg.len() as u64).to_be_bytes());
+        hasher.update(msg);
+    }
+    hasher.finalize().to_vec()
+}
+
+/// Commits to the key of a blind broadcast, so that all its recipients decrypt the same plaintext
+fn blind_key_commitment(key: &[u8]) -> Vec<u8> {
+    Sha256::new()
+        .chain_update(BLIND_BROADCAST_CONTEXT)
+        .chain_update(key)
+        .finalize()
+        .to_vec()
+}
+
+/// Encrypts a broadcast under a fresh key wrapped for each of the other parties,
+/// so that the server can only relay it
+fn blind_broadcast(
+    data: &[u8],
+    our_index: u32,
+    public_bundles: &HashMap<u32, PublicBundle>,
+) -> Result<Vec<u8>> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(&mut key[..]);
+    let cipher = ChaCha20Poly1305::new_from_slice(&key[..])
+        .map_err(|_| Error::crypto("invalid broadcast key"))?;
+    // NOTE: each broadcast is encrypted under a fresh key, so a fixed nonce is sound
+    let ciphertext = cipher
+        .encrypt(&chacha20poly1305::Nonce::default(), data)
+        .map_err(|_| Error::crypto("failed to encrypt broadcast"))?;
+    let keys = public_bundles
+        .iter()
+        .filter(|(&index, _)| index != our_index)
+        .map(|(&index, bundle)| Ok((index, bundle.encrypt_unicast(&key[..])?)))
+        .collect::<Result<_>>()?;
+
+    Ok(proto::BlindBroadcast {
+        ciphertext,
+        key_commitment: blind_key_commitment(&key[..]),
+        keys,
+    }
+    .encode_to_vec())
+}
+
+/// Decrypts a broadcast produced by `blind_broadcast`
+fn open_broadcast(
+    blinded: &[u8],
+    our_index: u32,
+    private_bundle: &PrivateBundle,
+) -> Result<Vec<u8>> {
+    let blinded = proto::BlindBroadcast::decode(blinded)?;
+    let key = blinded
+        .keys
+        .get(&our_index)
+        .ok_or_else(|| Error::crypto("broadcast not encrypted for us"))?;
+    let key = Zeroizing::new(private_bundle.decrypt_unicast(key)?);
+    if blind_key_commitment(&key) != blinded.key_commitment {
+        return Err(Error::crypto("broadcast compromised"));
+    }
+    let cipher = ChaCha20Poly1305::new_from_slice(&key)
+        .map_err(|_| Error::crypto("broadcast compromised"))?;
+    Ok(cipher
+        .decrypt(
+            &chacha20poly1305::Nonce::default(),
+            blinded.ciphertext.as_slice(),
+        )
+        .map_err(|_| Error::crypto("broadcast compromised"))?)
+}
+
+/// Decrypts the broadcasts of a round for the protocol if they are blind
+fn open_broadcasts(
+    msgs: &HashMap<u32, Vec<u8>>,
+    our_index: u32,
+    private_bundle: &PrivateBundle,
+    blind_broadcasts: bool,
+) -> Result<HashMap<u32, Vec<u8>>> {
+    if !blind_broadcasts {
+        return Ok(msgs.clone());
+    }
+    msgs.iter()
+        .map(|(&sender, msg)| {
+            let msg = open_broadcast(msg, our_index, private_bundle)
+                .map_err(|error| error.blaming(sender))?;
+            Ok((sender, msg))
+        })
+        .collect()
+}
+
+/// Signs the data relayed in the echo-broadcast round
+fn echo_message(
+    data: Vec<u8>,
+    private_bundle: &PrivateBundle,
+    protocol_type: ProtocolType,
+) -> Vec<u8> {
+    ClientMessage {
+        unicasts: HashMap::new(),
+        abort: None,
+        broadcast: Some(private_bundle.sign_broadcast(data)),
+        protocol_type: protocol_type.into(),
+    }
+    .encode_to_vec()
+}
+
+/// The entries a `ServerMessage` can carry in some state, bounded separately for each map
+#[derive(Clone, Copy)]
+struct MessageBounds {
+    /// Maximum length of a unicast, or `None` if no unicasts are expected
+    unicast_len: Option<usize>,
+    /// Maximum length of a broadcast
+    broadcast_len: usize,
+}
+
+impl MessageBounds {
+    /// The bounds of a message carrying only broadcasts of up to `broadcast_len` bytes
+    fn broadcasts(broadcast_len: usize) -> Self {
+        Self {
+            unicast_len: None,
+            broadcast_len,
+        }
+    }
+
+    /// The bounds of relayed broadcasts, i.e. a signed message of the broadcasts
+    /// of all `participants` but the relayer
+    fn relayed(participants: usize) -> Self {
+        let inner = Self::broadcasts(MAX_MESSAGE_LEN).max_len(participants);
+        Self::broadcasts(inner.saturating_add(MAX_CONTROL_MESSAGE_LEN))
+    }
+
+    /// The maximum length of the whole message relayed to one of the `participants`
+    fn max_len(&self, participants: usize) -> usize {
+        let others = participants.saturating_sub(1);
+        [
+            self.unicast_len.unwrap_or(0),
+            self.broadcast_len,
+            MAX_CONTROL_MESSAGE_LEN,
+        ]
+        .iter()
+        .map(|len| others.saturating_mul(len.saturating_add(MAX_ENTRY_OVERHEAD)))
+        .fold(MAX_ENTRY_OVERHEAD, usize::saturating_add)
+    }
+}
+
+/// Decodes a `ServerMessage` relayed to `our_index`, rejecting inputs the other
+/// `participants` cannot have produced. Each of them contributes at most one entry to each map
+fn decode_server_message(
+    data: &[u8],
+    our_index: u32,
+    participants: &[u32],
+    bounds: MessageBounds,
+) -> Result<ServerMessage> {
+    if data.len() > bounds.max_len(participants.len()) {
+        return Err(Error::malformed("server message too large"));
+    }
+
+    let msg = ServerMessage::decode(data)?;
+    let others = participants.len().saturating_sub(1);
+    if bounds.unicast_len.is_none() && !msg.unicasts.is_empty() {
+        return Err(Error::malformed("unexpected unicasts in server message"));
+    }
+    let maps = [
+        (&msg.unicasts, bounds.unicast_len.unwrap_or(0)),
+        (&msg.broadcasts, bounds.broadcast_len),
+        (&msg.aborts, MAX_CONTROL_MESSAGE_LEN),
+    ];
+    for (map, max_entry_len) in maps {
+        if map.len() > others {
+            return Err(Error::malformed("too many messages in server message"));
+        }
+        for (sender, message) in map {
+            if *sender == our_index || !participants.contains(sender) {
+                return Err(Error::malformed(format!(
+                    "message of unexpected party {sender}"
+                )));
+            }
+            if message.len() > max_entry_len {
+                return Err(Error::malformed(format!(
+                    "message of party {sender} too large"
+                )));
+            }
+        }
+    }
+    Ok(msg)
+}
+
+/// Checks that the roster of a session is sound and matches the received certificates,
+/// which must cover all other participants and nobody else
+fn check_participants(
+    participants: &[u32],
+    our_index: u32,
+    threshold: u32,
+    public_bundles: &HashMap<u32, PublicBundle>,
+) -> Result<()> {
+    let unique: HashSet<u32> = participants.iter().copied().collect();
+    if unique.len() != participants.len() {
+        return Err(Error::malformed("duplicate participants"));
+    }
+    if !unique.contains(&our_index) {
+        return Err(Error::malformed(format!(
+            "party {our_index} does not participate"
+        )));
+    }
+    if threshold == 0 || participants.len() < threshold as usize {
+        return Err(Error::malformed(
+            "not enough participants for the threshold",
+        ));
+    }
+    let certified = |i: &u32| *i == our_index || public_bundles.contains_key(i);
+    if !unique.iter().all(certified) || !public_bundles.keys().all(|i| unique.contains(i)) {
+        return Err(Error::malformed(
+            "certificates do not match the participants",
+        ));
+    }
+    Ok(())
+}
+
+/// Encodes the abort of party `index`, signed with its broadcast key
+fn abort_message(
+    index: u32,
+    reason: AbortReason,
+    private_bundle: &PrivateBundle,
+    protocol_type: ProtocolType,
+) -> Vec<u8> {
+    let abort = proto::Abort {
+        protocol_type: protocol_type.into(),
+        index,
+        reason: proto::AbortReason::from(reason).into(),
+    }
+    .encode_to_vec();
+    ClientMessage {
+        unicasts: HashMap::new(),
+        broadcast: None,
+        abort: Some(private_bundle.sign_broadcast(abort)),
+        protocol_type: protocol_type.into(),
+    }
+    .encode_to_vec()
+}
+
+/// Verifies the aborts relayed by the server and returns the one of the lowest party, if any
+fn check_aborts(
+    msg: &ServerMessage,
+    public_bundles: &HashMap<u32, PublicBundle>,
+    protocol_type: ProtocolType,
+) -> Result<Option<(u32, AbortReason)>> {
+    let mut aborts = Vec::with_capacity(msg.aborts.len());
+    for (sender, abort) in &msg.aborts {
+        let bundle = public_bundles
+            .get(sender)
+            .ok_or_else(|| Error::malformed("abort of an unknown party"))?;
+        let abort = bundle
+            .verify_broadcast(abort)
+            .map_err(|error| error.blaming(*sender))?;
+        let abort = proto::Abort::decode(abort.as_slice())?;
+        if abort.index != *sender || abort.protocol_type != i32::from(protocol_type) {
+            return Err(Error::blame(*sender, "abort compromised"));
+        }
+        aborts.push((*sender, abort.reason().into()));
+    }
+    Ok(aborts.into_iter().min_by_key(|(sender, _)| *sender))
+}
+
+/// Checks that the broadcasts relayed by other parties match the ones we received
+fn check_relayed_broadcasts(
+    relayed: &ServerMessage,
+    original_msgs: &HashMap<u32, Vec<u8>>,
+    our_original_msg: &[u8],
+    our_index: u32,
+    participants: &[u32],
+    public_bundles: &HashMap<u32, PublicBundle>,
+) -> Result<()> {
+    if relayed.broadcasts.len() + 1 != participants.len() {
+        return Err(Error::malformed("unexpected number of relayed broadcasts"));
+    }
+
+    for (relayer, relayed_msgs) in &relayed.broadcasts {
+        let blame = |error: Error| Error::blame(*relayer, error.to_string());
+        let relayed_msgs = public_bundles[relayer]
+            .verify_broadcast(relayed_msgs)
+            .map_err(|error| error.blaming(*relayer))?;
+        let relayed_msgs = decode_server_message(
+            &relayed_msgs,
+            *relayer,
+            participants,
+            MessageBounds::broadcasts(MAX_MESSAGE_LEN),
+        )
+        .map_err(blame)?;
+        if relayed_msgs.broadcasts.len() + 1 != participants.len() {
+            return Err(Error::blame(*relayer, "relayed broadcasts incomplete"));
+        }
+
+        for (sender, relayed_msg) in &relayed_msgs.broadcasts {
+            if *sender == our_index {
+                // NOTE: our own broadcast is authentic if it matches the one we sent
+                let relayed_msg = SignedMessage::decode(relayed_msg.as_slice())
+                    .map_err(|error| blame(Error::from(error)))?;
+                if our_original_msg != relayed_msg.message {
+                    return Err(Error::crypto("broadcast compromised"));
+                }
+                continue;
+            }
+            // NOTE: the relayer vouches for the broadcasts it relays
+            let relayed_msg = public_bundles[sender]
+                .verify_broadcast(relayed_msg)
+                .map_err(|error| error.blaming(*relayer))?;
+            if !original_msgs
+                .get(sender)
+                .is_some_and(|msg| msg == &relayed_msg)
+            {
+                return Err(Error::blame(*sender, "broadcast compromised"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the other parties whose relayed broadcast digests differ from ours, in ascending order
+fn check_relayed_digests(
+    relayed: &ServerMessage,
+    digest: &[u8],
+    participants: usize,
+    public_bundles: &HashMap<u32, PublicBundle>,
+) -> Result<Vec<u32>> {
+    if relayed.broadcasts.len() + 1 != participants {
+        return Err(Error::malformed("unexpected number of broadcast digests"));
+    }
+
+    let mut mismatching = Vec::new();
+    for (relayer, relayed_digest) in &relayed.broadcasts {
+        let relayed_digest = public_bundles[relayer]
+            .verify_broadcast(relayed_digest)
+            .map_err(|error| error.blaming(*relayer))?;
+        if relayed_digest != digest {
+            mismatching.push(*relayer);
+        }
+    }
+    mismatching.sort_unstable();
+    Ok(mismatching)
+}
+
+/// The last input of a share together with our response to it
+#[derive(Clone, Deserialize, Serialize)]
+struct LastRound {
+    input_digest: Vec<u8>,
+    output: Vec<u8>,
+    recipient: Recipient,
+}
+
+/// A running hash over the parts of a session that all parties observe identically,
+/// i.e. the session parameters, the certificates of the participants and all broadcasts
+#[derive(Clone, Default, Deserialize, Serialize)]
+struct Transcript {
+    digest: Vec<u8>,
+    /// Our last plain broadcast, absorbed together with those of the other parties
+    pending_broadcast: Option<Vec<u8>>,
+}
+
+impl Transcript {
+    /// Chains the hash of `data` onto the transcript
+    fn absorb(&mut self, data: &[u8]) {
+        self.digest = Sha256::new()
+            .chain_update(&self.digest)
+            .chain_update(Sha256::digest(data))
+            .finalize()
+            .to_vec();
+    }
+
+    /// Absorbs the session parameters and the certificates of the `participants`
+    fn absorb_session(
+        &mut self,
+        params: &[u8],
+        our_index: u32,
+        participants: &[u32],
+        public_bundles: &HashMap<u32, PublicBundle>,
+    ) {
+        let bundle_der = |index| {
+            public_bundles
+                .get(index)
+                .map(|bundle: &PublicBundle| bundle.der.clone())
+                .unwrap_or_default()
+        };
+        let others: HashMap<u32, Vec<u8>> = participants
+            .iter()
+            .filter(|&&index| index != our_index)
+            .map(|index| (*index, bundle_der(index)))
+            .collect();
+
+        self.absorb(params);
+        self.absorb(&broadcast_digest(
+            &others,
+            our_index,
+            &bundle_der(&our_index),
+        ));
+    }
+
+    /// Encodes a receipt of the transcript of party `index`, signed with its broadcast key
+    fn receipt(
+        &self,
+        index: u32,
+        private_bundle: &PrivateBundle,
+        protocol_type: ProtocolType,
+    ) -> Vec<u8> {
+        let receipt = proto::TranscriptReceipt {
+            protocol_type: protocol_type.into(),
+            index,
+            digest: self.digest.clone(),
+        }
+        .encode_to_vec();
+        private_bundle.sign_broadcast(receipt)
+    }
+}
+
+/// Verifies a receipt produced by `SecureLayer::finish_all_with_receipts`
+/// against the certificate of its issuer and returns the transcript digest
+pub fn verify_receipt(receipt: &[u8], cert_der: &[u8]) -> Result<Vec<u8>> {
+    let bundle = PublicBundle::try_from(extract_public_bundle_der(cert_der)?)?;
+    let receipt = bundle.verify_broadcast(receipt)?;
+    Ok(proto::TranscriptReceipt::decode(receipt.as_slice())?.digest)
+}
+
+/// The message confirming the `group_key` of a keygen, bound to the session by its transcript
+fn key_confirmation(transcript: &Transcript, group_key: &[u8]) -> Vec<u8> {
+    [KEY_CONFIRMATION_CONTEXT, &transcript.digest, group_key].concat()
+}
+
+/// Encodes the verified broadcasts for the protocol
+fn original_round(original_msgs: &HashMap<u32, Vec<u8>>, protocol_type: ProtocolType) -> Vec<u8> {
+    ServerMessage {
+        unicasts: HashMap::new(),
+        broadcasts: original_msgs.clone(),
+        aborts: HashMap::new(),
+        protocol_type: protocol_type.into(),
+    }
+    .encode_to_vec()
+}
+
 fn secure_message(
     msg: Message,
-    private_bundle: &MeeSignPrivateBundle,
-    public_bundles: &HashMap<u32, MeeSignPublicBundle>,
+    private_bundle: &PrivateBundle,
+    public_bundles: &HashMap<u32, PublicBundle>,
 ) -> Result<Message> {
-    let sign_bcast = |data: Vec<u8>| -> Result<Vec<u8>> {
-        let pkey = ecdsa::SigningKey::from_pkcs8_der(&private_bundle.broadcast_sign)?;
-        let signature: ecdsa::Signature = pkey.sign(&data);
-        let msg = SignedMessage {
-            message: data,
-            signature: signature.to_vec(),
-        };
-        Ok(msg.encode_to_vec())
-    };
-
     let msg = match msg {
         Message::Unicast(mut data) => {
-            let sign_key = ecdsa::SigningKey::from_pkcs8_der(&private_bundle.unicast_sign)?;
+            let sign_key = &private_bundle.unicast_sign;
             for (recipient, unicast) in &mut data {
-                let enc_key = &public_bundles[recipient].unicast_encrypt;
-                let encrypted =
-                    ecies::encrypt(enc_key, unicast).map_err(|_| "failed to encrypt unicast")?;
-                let signature: ecdsa::Signature = sign_key.sign(&encrypted);
+                let encrypted = public_bundles[recipient].encrypt_unicast(unicast)?;
+                unicast.zeroize();
+                let signature = sign_key.sign(&encrypted);
                 *unicast = SignedMessage {
                     message: encrypted,
-                    signature: signature.to_vec(),
+                    signature,
+                    pq_signature: Vec::new(),
                 }
                 .encode_to_vec();
             }
             Message::Unicast(data)
         }
-        Message::Broadcast(data) => Message::Broadcast(sign_bcast(data)?),
-        Message::ReliableBroadcast(data) => Message::ReliableBroadcast(sign_bcast(data)?),
+        Message::Broadcast(data) => Message::Broadcast(private_bundle.sign_broadcast(data)),
+        Message::ReliableBroadcast(data) => {
+            Message::ReliableBroadcast(private_bundle.sign_broadcast(data))
+        }
         Message::CardCommand(_) => unreachable!(),
     };
     Ok(msg)
@@ -84,22 +776,37 @@ fn secure_message(
 
 fn finalize_round(
     msg: Message,
-    private_bundle: &MeeSignPrivateBundle,
-    public_bundles: &HashMap<u32, MeeSignPublicBundle>,
+    our_index: u32,
+    private_bundle: &PrivateBundle,
+    public_bundles: &HashMap<u32, PublicBundle>,
     protocol_type: ProtocolType,
+    blind_broadcasts: bool,
+    transcript: &mut Transcript,
 ) -> Result<(State, Vec<u8>, Recipient)> {
+    // NOTE: the echo-broadcast rounds and the transcript only see blinded broadcasts
+    let blind = |data: Vec<u8>| match blind_broadcasts {
+        true => blind_broadcast(&data, our_index, public_bundles),
+        false => Ok(data),
+    };
     let (state, msg, recipient) = match msg {
         Message::CardCommand(data) => {
             // NOTE: We just pass card commands through
             return Ok((State::CardResponse, data, Recipient::Card));
         }
         msg @ Message::Unicast(_) => (State::Running, msg, Recipient::Server),
-        msg @ Message::Broadcast(_) => (State::Running, msg, Recipient::Server),
-        Message::ReliableBroadcast(data) => (
-            State::BroadcastExchange(data.clone()),
-            Message::ReliableBroadcast(data),
-            Recipient::Server,
-        ),
+        Message::Broadcast(data) => {
+            let data = blind(data)?;
+            transcript.pending_broadcast = Some(data.clone());
+            (State::Running, Message::Broadcast(data), Recipient::Server)
+        }
+        Message::ReliableBroadcast(data) => {
+            let data = blind(data)?;
+            (
+                State::BroadcastExchange(data.clone()),
+                Message::ReliableBroadcast(data),
+                Recipient::Server,
+            )
+        }
     };
 
     let msg = secure_message(msg, private_bundle, public_bundles)?;
@@ -123,6 +830,71 @@ pub enum State {
     BroadcastExchange(Vec<u8>),
     /// Finished echo-broadcast and continues with another protocol round
     BroadcastCheck(HashMap<u32, Vec<u8>>, Vec<u8>),
+    /// Finished digest echo-broadcast and continues with another protocol round,
+    /// unless the digests differ. Keeps the received broadcasts to relay them in that case
+    BroadcastDigestCheck(HashMap<u32, Vec<u8>>, Vec<u8>, Vec<u8>),
+    /// Reached after the broadcast digests of the given parties differ. Relays all broadcasts
+    /// to find the culprit and always aborts the protocol
+    BroadcastFetch(HashMap<u32, Vec<u8>>, Vec<u8>, Vec<u32>),
+    /// Reached after the party with the given index aborted the protocol. Never advances again
+    Aborted(u32, AbortReason),
+    /// Reached after a keygen finishes. Checks that all parties computed the same group key
+    /// and only then sends the held back final message of the protocol to its recipient
+    KeyConfirmation(Vec<u8>, Vec<u8>, Recipient),
+    /// Reached after all parties confirmed the group key. Never advances again
+    KeyConfirmed,
+}
+
+/// The variant of a `State`, without the data it holds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateKind {
+    CertSwap,
+    Init,
+    Running,
+    CardResponse,
+    BroadcastExchange,
+    BroadcastCheck,
+    BroadcastDigestCheck,
+    BroadcastFetch,
+    Aborted,
+    KeyConfirmation,
+    KeyConfirmed,
+}
+
+impl State {
+    pub fn kind(&self) -> StateKind {
+        match self {
+            State::CertSwap => StateKind::CertSwap,
+            State::Init => StateKind::Init,
+            State::Running => StateKind::Running,
+            State::CardResponse => StateKind::CardResponse,
+            State::BroadcastExchange(..) => StateKind::BroadcastExchange,
+            State::BroadcastCheck(..) => StateKind::BroadcastCheck,
+            State::BroadcastDigestCheck(..) => StateKind::BroadcastDigestCheck,
+            State::BroadcastFetch(..) => StateKind::BroadcastFetch,
+            State::Aborted(..) => StateKind::Aborted,
+            State::KeyConfirmation(..) => StateKind::KeyConfirmation,
+            State::KeyConfirmed => StateKind::KeyConfirmed,
+        }
+    }
+}
+
+/// Where the computation of one share stands
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShareStatus {
+    pub state: StateKind,
+    /// The party which aborted the protocol, if any
+    pub aborted_by: Option<u32>,
+    /// The round of the underlying protocol, e.g. `R11AwaitCommitmentResp`
+    pub round: &'static str,
+    /// The number of rounds advanced so far
+    pub rounds_done: u16,
+    /// The expected number of rounds of the whole session, card rounds excluded
+    pub rounds_total: u16,
+    /// The recipient of the last response, which the client has to deliver next
+    pub recipient: Option<Recipient>,
+    /// Whether the result of the share can be obtained
+    pub finished: bool,
 }
 
 /// A wrapper around the raw threshold protocols providing necessary security guarantees,
@@ -135,12 +907,27 @@ pub(crate) struct SecureLayer {
     share_indices: Vec<u32>,
     /// The respective computation states for each share of this participant
     shares: Vec<(State, Box<dyn Protocol>)>,
-    /// MeeSignPublicBundles in DER format for each share index
-    public_bundles: HashMap<u32, Vec<u8>>,
-    /// MeeSignPrivateBundle in DER format
-    private_bundle: Vec<u8>,
+    /// MeeSignPublicBundles for each share index, serialized in DER format
+    public_bundles: HashMap<u32, PublicBundle>,
+    /// MeeSignPrivateBundle, serialized in DER format
+    private_bundle: PrivateBundle,
     /// The underlying threshold protocol
     protocol_type: ProtocolType,
+    /// The echo-broadcast variant chosen by the server in round 0
+    #[serde(default)]
+    echo_mode: EchoMode,
+    /// Whether broadcasts are encrypted to the participants, chosen by the server in round 0
+    #[serde(default)]
+    blind_broadcasts: bool,
+    /// The running transcripts corresponding to the `shares` field
+    #[serde(default)]
+    transcripts: Vec<Transcript>,
+    /// The last round of each share of the `shares` field, repeated on redelivery of its input
+    #[serde(default)]
+    last_rounds: Vec<Option<LastRound>>,
+    /// The number of rounds advanced by each share of the `shares` field
+    #[serde(default)]
+    rounds_done: Vec<u16>,
 }
 
 impl SecureLayer {
@@ -151,37 +938,25 @@ impl SecureLayer {
         certs: &[u8],
         pfx_der: &[u8],
         protocol_type: ProtocolType,
-    ) -> Self {
-        let public_bundles = ServerMessage::decode(certs)
-            .unwrap()
+    ) -> Result<Self> {
+        let public_bundles = ServerMessage::decode(certs)?
             .broadcasts
             .into_iter()
             .map(|(party, cert)| {
-                let bundle = extract_public_bundle_der(&cert)?;
+                let bundle = PublicBundle::try_from(extract_public_bundle_der(&cert)?)?;
                 Ok((party, bundle))
             })
-            .collect::<Result<HashMap<_, _>>>()
-            .unwrap();
-
-        let private_bundle = p12::PFX::parse(pfx_der)
-            .unwrap()
-            .bags("")
-            .unwrap()
-            .into_iter()
-            .find(|bag| bag.friendly_name().unwrap() == MeeSignPrivateBundle::FRIENDLY_NAME)
-            .unwrap();
+            .collect::<Result<HashMap<_, _>>>()?;
 
-        let p12::SafeBagKind::OtherBagKind(p12::OtherBag {
-            bag_value: private_bundle,
-            ..
-        }) = private_bundle.bag
-        else {
-            panic!("unexpected PKCS#12 SafeBag");
-        };
+        let private_bundle = extract_private_bundle_der(pfx_der)?;
+        let private_bundle = PrivateBundle::from_der(private_bundle)?;
 
-        Self {
+        Ok(Self {
             participant_indices: Vec::new(),      // NOTE: initialized in round 0
             share_indices: vec![0; shares.len()], // NOTE: initialized in round 0
+            transcripts: vec![Transcript::default(); shares.len()],
+            last_rounds: vec![None; shares.len()],
+            rounds_done: vec![0; shares.len()],
             shares: shares
                 .into_iter()
                 .map(|share| (initial_state.clone(), share))
@@ -189,29 +964,232 @@ impl SecureLayer {
             public_bundles,
             private_bundle,
             protocol_type,
+            echo_mode: EchoMode::default(), // NOTE: initialized in round 0
+            blind_broadcasts: false,        // NOTE: initialized in round 0
+        })
+    }
+
+    pub fn protocol_type(&self) -> ProtocolType {
+        self.protocol_type
+    }
+
+    /// Starts a keygen of `protocol_type` computing `shares` shares
+    pub fn keygen(
+        protocol_type: ProtocolType,
+        with_card: bool,
+        shares: usize,
+        certs: &[u8],
+        pfx_der: &[u8],
+    ) -> Result<Self> {
+        let shares = (0..shares)
+            .map(|_| protocol::keygen(protocol_type, with_card))
+            .collect::<Result<_>>()?;
+        Self::new(State::CertSwap, shares, certs, pfx_der, protocol_type)
+    }
+
+    /// Starts a threshold protocol computing the `group` shares output by a keygen.
+    /// The protocol is detected from the group, which has to belong to the `expected` protocol
+    /// if one is given
+    pub fn init(
+        expected: Option<ProtocolType>,
+        group: &[Vec<u8>],
+        certs: &[u8],
+        pfx_der: &[u8],
+    ) -> Result<Self> {
+        let detected = group.first().map(|share| protocol::group_protocol(share));
+        let protocol_type = match (detected, expected) {
+            (Some(Ok(protocol_type)), _) => protocol_type,
+            // NOTE: groups created before tagging leave only the expected protocol to go by
+            (_, Some(expected)) => expected,
+            (Some(Err(error)), None) => return Err(error),
+            (None, None) => return Err(Error::misuse("no shares requested")),
+        };
+        if expected.map_or(false, |expected| expected != protocol_type) {
+            return Err(Error::misuse(format!("group belongs to {protocol_type:?}")));
+        }
+
+        let shares: Vec<Box<dyn Protocol>> = group
+            .iter()
+            .map(|share| protocol::threshold(protocol_type, share))
+            .collect::<Result<_>>()?;
+        // NOTE: the rosters of GG18 groups created before they recorded their parameters
+        // cannot be checked against the group, so such groups have to be created again
+        if shares.iter().any(|share| share.group_params().is_none()) {
+            return Err(Error::misuse(
+                "group does not record its parameters, create it again",
+            ));
         }
+        Self::new(State::Init, shares, certs, pfx_der, protocol_type)
     }
 
-    /// Advances the computation of one share
+    /// Advances the computation of one share. A share which fails a cryptographic check
+    /// is aborted, its abort message can then be obtained from `abort_share`.
+    /// A redelivered input is answered with the same response without advancing again
     pub fn advance_share(&mut self, share_idx: usize, data: &[u8]) -> Result<(Vec<u8>, Recipient)> {
-        let (state, protocol) = &mut self.shares[share_idx];
+        let (state, _) = self
+            .shares
+            .get(share_idx)
+            .ok_or_else(|| Error::misuse(format!("no share {share_idx}")))?;
+        let input_digest = Sha256::digest(data).to_vec();
+        // NOTE: after a card command, the next input is a response of the card,
+        // which may repeat a previous one without being redelivered, e.g. a bare status word
+        if let Some(Some(last)) = self.last_rounds.get(share_idx) {
+            if last.input_digest == input_digest
+                && last.recipient == Recipient::Server
+                && !matches!(state, State::Aborted(..))
+            {
+                return Ok((last.output.clone(), last.recipient));
+            }
+        }
 
-        let public_bundles = self
-            .public_bundles
-            .iter()
-            .map(|(&party, bundle)| {
-                let bundle = MeeSignPublicBundle::from_der(bundle)?;
-                Ok((party, bundle))
-            })
-            .collect::<Result<HashMap<_, _>>>()?;
+        // NOTE: the certificate swap precedes the rounds of the protocol
+        let counted = !matches!(state, State::CertSwap);
+        let result = self.advance_state(share_idx, data);
+        match &result {
+            Ok((output, recipient)) => {
+                // NOTE: states serialized before the cache was introduced have none
+                self.last_rounds.resize(self.shares.len(), None);
+                self.last_rounds[share_idx] = Some(LastRound {
+                    input_digest,
+                    output: output.clone(),
+                    recipient: *recipient,
+                });
+                self.rounds_done.resize(self.shares.len(), 0);
+                if counted {
+                    self.rounds_done[share_idx] = self.rounds_done[share_idx].saturating_add(1);
+                }
+            }
+            // NOTE: misuse and malformed input leave the share as it was, so that it can still
+            // advance on the right input, whereas failed checks of the messages of others abort it
+            Err(error) => {
+                let (state, _) = &mut self.shares[share_idx];
+                let failed_check = matches!(error, Error::Crypto { .. });
+                if failed_check && !matches!(state, State::Aborted(..)) {
+                    *state = State::Aborted(self.share_indices[share_idx], AbortReason::Error);
+                }
+            }
+        }
+        result
+    }
+
+    /// The number of shares computed by this participant
+    pub fn share_count(&self) -> usize {
+        self.shares.len()
+    }
+
+    /// Reports where the computation of one share stands
+    pub fn status(&self, share_idx: usize) -> Result<ShareStatus> {
+        let (state, protocol) = self
+            .shares
+            .get(share_idx)
+            .ok_or_else(|| Error::misuse(format!("no share {share_idx}")))?;
+        let aborted_by = match state {
+            State::Aborted(party, _) => Some(*party),
+            _ => None,
+        };
+        let confirmed = protocol.group_key().is_none() || matches!(state, State::KeyConfirmed);
+        Ok(ShareStatus {
+            state: state.kind(),
+            aborted_by,
+            round: protocol.round(),
+            rounds_done: self.rounds_done.get(share_idx).copied().unwrap_or(0),
+            rounds_total: protocol.rounds(),
+            recipient: self
+                .last_rounds
+                .get(share_idx)
+                .and_then(|last| last.as_ref())
+                .map(|last| last.recipient),
+            finished: aborted_by.is_none() && confirmed && protocol.is_finished(),
+        })
+    }
+
+    /// Aborts the computation of one share and returns the abort message for the other parties
+    pub fn abort_share(&mut self, share_idx: usize, reason: AbortReason) -> Result<Vec<u8>> {
+        let (state, _) = self
+            .shares
+            .get_mut(share_idx)
+            .ok_or_else(|| Error::misuse(format!("no share {share_idx}")))?;
+        let index = self.share_indices[share_idx];
+        let reason = match *state {
+            State::Aborted(party, _) if party != index => {
+                return Err(Error::misuse(format!(
+                    "protocol already aborted by party {party}"
+                )))
+            }
+            State::Aborted(_, reason) => reason,
+            _ => reason,
+        };
+        *state = State::Aborted(index, reason);
+
+        Ok(abort_message(
+            index,
+            reason,
+            &self.private_bundle,
+            self.protocol_type,
+        ))
+    }
+
+    fn advance_state(&mut self, share_idx: usize, data: &[u8]) -> Result<(Vec<u8>, Recipient)> {
+        // NOTE: states serialized before the transcripts were introduced have none
+        self.transcripts
+            .resize(self.shares.len(), Transcript::default());
+        let (state, protocol) = &mut self.shares[share_idx];
+        // NOTE: the transcript only takes up inputs which are accepted
+        let mut next_transcript = self.transcripts[share_idx].clone();
+        let transcript = &mut next_transcript;
+        let public_bundles = &self.public_bundles;
+        let private_bundle = &self.private_bundle;
 
-        let private_bundle = MeeSignPrivateBundle::from_der(&self.private_bundle)?;
+        // NOTE: states which do not expect a `ServerMessage` see an empty one
+        let bounds = match state {
+            State::Aborted(party, reason) => return Err(abort_error(*party, *reason)),
+            State::CertSwap | State::CardResponse | State::KeyConfirmed => None,
+            State::Init => {
+                if data.len() > MAX_MESSAGE_LEN {
+                    return Err(Error::malformed("init message too large"));
+                }
+                None
+            }
+            State::Running => Some(MessageBounds {
+                unicast_len: Some(MAX_MESSAGE_LEN),
+                broadcast_len: MAX_MESSAGE_LEN,
+            }),
+            State::BroadcastExchange(..) | State::KeyConfirmation(..) => {
+                Some(MessageBounds::broadcasts(MAX_MESSAGE_LEN))
+            }
+            State::BroadcastCheck(..) | State::BroadcastFetch(..) => {
+                Some(MessageBounds::relayed(self.participant_indices.len()))
+            }
+            State::BroadcastDigestCheck(..) => {
+                Some(MessageBounds::broadcasts(MAX_CONTROL_MESSAGE_LEN))
+            }
+        };
+        let server_msg = match bounds {
+            Some(bounds) => {
+                let msg = decode_server_message(
+                    data,
+                    self.share_indices[share_idx],
+                    &self.participant_indices,
+                    bounds,
+                )?;
+                // NOTE: the server relays aborts only in place of a `ServerMessage`
+                if let Some((party, reason)) =
+                    check_aborts(&msg, public_bundles, self.protocol_type)?
+                {
+                    *state = State::Aborted(party, reason);
+                    return Err(abort_error(party, reason));
+                }
+                msg
+            }
+            None => ServerMessage::default(),
+        };
 
         let (msg, recipient);
         (*state, msg, recipient) = match state {
             State::CertSwap => {
                 let ack = ClientMessage {
                     broadcast: Some(Vec::from("certificates received")),
+                    abort: None,
                     unicasts: HashMap::new(),
                     protocol_type: self.protocol_type.into(),
                 }
@@ -219,146 +1197,1361 @@ impl SecureLayer {
                 (State::Init, ack, Recipient::Server)
             }
             State::Init => {
-                if let Ok(pgi) = ProtocolGroupInit::decode(data) {
-                    let index_offset = match self.protocol_type {
-                        ProtocolType::Frost => 1,
-                        _ => 0,
-                    };
-                    self.participant_indices = (index_offset..pgi.parties + index_offset).collect();
-                    self.share_indices[share_idx] = pgi.index;
+                let index_offset = match self.protocol_type {
+                    ProtocolType::Frost => 1,
+                    _ => 0,
+                };
+                let (participants, index, echo_mode, blind_broadcasts, params) = if let Ok(pgi) =
+                    ProtocolGroupInit::decode(data)
+                {
+                    let participants: Vec<u32> =
+                        (index_offset..pgi.parties + index_offset).collect();
+                    check_participants(&participants, pgi.index, pgi.threshold, public_bundles)?;
+
+                    let params = ProtocolGroupInit { index: 0, ..pgi }.encode_to_vec();
+                    let echo_mode = pgi.echo_mode().into();
+                    (
+                        participants,
+                        pgi.index,
+                        echo_mode,
+                        pgi.blind_broadcasts,
+                        params,
+                    )
                 } else if let Ok(pi) = ProtocolInit::decode(data) {
-                    self.participant_indices = pi.indices;
-                    self.share_indices[share_idx] = pi.index;
+                    let params = protocol
+                        .group_params()
+                        .ok_or_else(|| Error::malformed("session does not hold a group"))?;
+                    if pi.index != params.index {
+                        return Err(Error::malformed("index does not match the group"));
+                    }
+                    let group = index_offset..params.parties + index_offset;
+                    if !pi.indices.iter().all(|i| group.contains(i)) {
+                        return Err(Error::malformed("participants outside of the group"));
+                    }
+                    check_participants(&pi.indices, pi.index, params.threshold, public_bundles)?;
+
+                    let (index, echo_mode) = (pi.index, pi.echo_mode().into());
+                    let (participants, blind_broadcasts) =
+                        (pi.indices.clone(), pi.blind_broadcasts);
+                    let params = ProtocolInit { index: 0, ..pi }.encode_to_vec();
+                    (participants, index, echo_mode, blind_broadcasts, params)
                 } else {
-                    return Err("invalid data in round 0".into());
+                    return Err(Error::malformed("invalid data in round 0"));
+                };
+                if !self.participant_indices.is_empty() {
+                    if self.participant_indices != participants {
+                        return Err(Error::malformed("participants differ between shares"));
+                    }
+                    if self.blind_broadcasts != blind_broadcasts {
+                        return Err(Error::malformed("broadcast modes differ between shares"));
+                    }
                 }
 
-                let msg = protocol.advance(&data)?;
+                let msg = protocol.advance(data)?;
 
-                finalize_round(msg, &private_bundle, &public_bundles, self.protocol_type)?
+                self.participant_indices = participants;
+                self.share_indices[share_idx] = index;
+                self.echo_mode = echo_mode;
+                self.blind_broadcasts = blind_broadcasts;
+                transcript.absorb_session(
+                    &params,
+                    index,
+                    &self.participant_indices,
+                    public_bundles,
+                );
+
+                finalize_round(
+                    msg,
+                    self.share_indices[share_idx],
+                    private_bundle,
+                    public_bundles,
+                    self.protocol_type,
+                    self.blind_broadcasts,
+                    transcript,
+                )?
             }
             State::BroadcastExchange(our_original_msg) => {
-                let data_dec = ServerMessage::decode(data)?;
-                let mut original_msgs = HashMap::with_capacity(data_dec.broadcasts.len());
-                for (sender, message) in &data_dec.broadcasts {
-                    let key = &public_bundles[sender].broadcast_sign;
-                    let key = ecdsa::VerifyingKey::from_public_key_der(key)?;
-                    let message = verify_message(message, &key)?;
+                let mut original_msgs = HashMap::with_capacity(server_msg.broadcasts.len());
+                for (sender, message) in &server_msg.broadcasts {
+                    let message = public_bundles[sender]
+                        .verify_broadcast(message)
+                        .map_err(|error| error.blaming(*sender))?;
                     original_msgs.insert(*sender, message);
                 }
-                assert_eq!(data_dec.unicasts.len(), 0);
 
-                let bcast_sign_key =
-                    ecdsa::SigningKey::from_pkcs8_der(&private_bundle.broadcast_sign)?;
-                let signature: ecdsa::Signature = bcast_sign_key.sign(data);
-                let data = ClientMessage {
-                    unicasts: HashMap::new(),
-                    broadcast: Some(
-                        SignedMessage {
-                            message: data.to_vec(),
-                            signature: signature.to_vec(),
-                        }
-                        .encode_to_vec(),
+                let (state, echo) = match self.echo_mode {
+                    EchoMode::Full => (
+                        State::BroadcastCheck(original_msgs, our_original_msg.clone()),
+                        data.to_vec(),
                     ),
-                    protocol_type: self.protocol_type.into(),
-                }
-                .encode_to_vec();
+                    EchoMode::Digest => {
+                        let digest = broadcast_digest(
+                            &original_msgs,
+                            self.share_indices[share_idx],
+                            our_original_msg,
+                        );
+                        (
+                            State::BroadcastDigestCheck(
+                                original_msgs,
+                                our_original_msg.clone(),
+                                data.to_vec(),
+                            ),
+                            digest,
+                        )
+                    }
+                };
 
                 (
-                    State::BroadcastCheck(original_msgs, our_original_msg.clone()),
-                    data,
+                    state,
+                    echo_message(echo, private_bundle, self.protocol_type),
                     Recipient::Server,
                 )
             }
             State::BroadcastCheck(original_msgs, our_original_msg) => {
-                let data = ServerMessage::decode(data)?;
-                assert!(!data.broadcasts.contains_key(&self.share_indices[share_idx]));
-                assert_eq!(data.broadcasts.len(), self.participant_indices.len() - 1);
+                check_relayed_broadcasts(
+                    &server_msg,
+                    original_msgs,
+                    our_original_msg,
+                    self.share_indices[share_idx],
+                    &self.participant_indices,
+                    public_bundles,
+                )?;
+                transcript.absorb(&broadcast_digest(
+                    original_msgs,
+                    self.share_indices[share_idx],
+                    our_original_msg,
+                ));
 
-                let sign_pub_keys: HashMap<_, _> = public_bundles
-                    .iter()
-                    .map(|(party, bundle)| {
-                        let key = ecdsa::VerifyingKey::from_public_key_der(&bundle.broadcast_sign)?;
-                        Ok((party, key))
-                    })
-                    .collect::<Result<_>>()?;
-
-                for (relayer, relayed_msgs) in &data.broadcasts {
-                    let relayed_msgs = verify_message(relayed_msgs, &sign_pub_keys[relayer])?;
-                    let relayed_msgs = ServerMessage::decode(relayed_msgs.as_slice())?;
-                    assert_eq!(
-                        relayed_msgs.broadcasts.len(),
-                        self.participant_indices.len() - 1
-                    );
-
-                    for (sender, relayed_msg) in &relayed_msgs.broadcasts {
-                        let relayed_msg = verify_message(relayed_msg, &sign_pub_keys[sender])?;
-
-                        if *sender == self.share_indices[share_idx] {
-                            if our_original_msg != &relayed_msg {
-                                return Err("broadcast compromised".into());
-                            }
-                            continue;
-                        }
-                        if sender == relayer {
-                            continue;
-                        }
-                        if !original_msgs
-                            .get(sender)
-                            .is_some_and(|msg| msg == &relayed_msg)
-                        {
-                            return Err("broadcast compromised".into());
-                        }
-                    }
-                }
-                assert_eq!(data.unicasts.len(), 0);
+                let opened = open_broadcasts(
+                    original_msgs,
+                    self.share_indices[share_idx],
+                    private_bundle,
+                    self.blind_broadcasts,
+                )?;
+                let msg = protocol.advance(&original_round(&opened, self.protocol_type))?;
 
-                let data = ServerMessage {
-                    unicasts: HashMap::new(),
-                    broadcasts: original_msgs.clone(),
-                    protocol_type: self.protocol_type.into(),
+                finalize_round(
+                    msg,
+                    self.share_indices[share_idx],
+                    private_bundle,
+                    public_bundles,
+                    self.protocol_type,
+                    self.blind_broadcasts,
+                    transcript,
+                )?
+            }
+            State::BroadcastDigestCheck(original_msgs, our_original_msg, received) => {
+                let our_index = self.share_indices[share_idx];
+                let digest = broadcast_digest(original_msgs, our_index, our_original_msg);
+                let mismatching = check_relayed_digests(
+                    &server_msg,
+                    &digest,
+                    self.participant_indices.len(),
+                    public_bundles,
+                )?;
+
+                if mismatching.is_empty() {
+                    transcript.absorb(&digest);
+                    let opened = open_broadcasts(
+                        original_msgs,
+                        our_index,
+                        private_bundle,
+                        self.blind_broadcasts,
+                    )?;
+                    let msg = protocol.advance(&original_round(&opened, self.protocol_type))?;
+
+                    finalize_round(
+                        msg,
+                        our_index,
+                        private_bundle,
+                        public_bundles,
+                        self.protocol_type,
+                        self.blind_broadcasts,
+                        transcript,
+                    )?
+                } else {
+                    (
+                        State::BroadcastFetch(
+                            original_msgs.clone(),
+                            our_original_msg.clone(),
+                            mismatching,
+                        ),
+                        echo_message(received.clone(), private_bundle, self.protocol_type),
+                        Recipient::Server,
+                    )
                 }
-                .encode_to_vec();
+            }
+            State::BroadcastFetch(original_msgs, our_original_msg, mismatching) => {
+                // NOTE: a sender which equivocated is blamed here, as its signed broadcasts differ
+                check_relayed_broadcasts(
+                    &server_msg,
+                    original_msgs,
+                    our_original_msg,
+                    self.share_indices[share_idx],
+                    &self.participant_indices,
+                    public_bundles,
+                )?;
 
-                let msg = protocol.advance(&data)?;
+                // NOTE: all broadcasts match ours, so the digests of all parties had to match ours
+                return Err(match mismatching.first() {
+                    Some(&culprit) => Error::blame(culprit, "relayed an invalid broadcast digest"),
+                    None => Error::crypto("broadcast digests compromised"),
+                });
+            }
+            State::Aborted(..) => unreachable!(),
+            State::KeyConfirmation(group_key, final_msg, final_recipient) => {
+                let confirmation = key_confirmation(transcript, group_key);
+                if server_msg.broadcasts.len() + 1 != self.participant_indices.len() {
+                    return Err(Error::malformed("missing key confirmations"));
+                }
+                for (sender, confirmed) in &server_msg.broadcasts {
+                    let confirmed = public_bundles[sender]
+                        .verify_broadcast(confirmed)
+                        .map_err(|error| error.blaming(*sender))?;
+                    let confirmed = match self.blind_broadcasts {
+                        true => open_broadcast(
+                            &confirmed,
+                            self.share_indices[share_idx],
+                            private_bundle,
+                        )
+                        .map_err(|error| error.blaming(*sender))?,
+                        false => confirmed,
+                    };
+                    if confirmed != confirmation {
+                        return Err(Error::blame(*sender, "computed a different group key"));
+                    }
+                }
+                transcript.absorb(&confirmation);
 
-                finalize_round(msg, &private_bundle, &public_bundles, self.protocol_type)?
+                (State::KeyConfirmed, final_msg.clone(), *final_recipient)
             }
+            State::KeyConfirmed => return Err(Error::misuse("protocol already finished")),
             State::CardResponse => {
                 let msg = protocol.advance(&data)?;
 
-                finalize_round(msg, &private_bundle, &public_bundles, self.protocol_type)?
+                finalize_round(
+                    msg,
+                    self.share_indices[share_idx],
+                    private_bundle,
+                    public_bundles,
+                    self.protocol_type,
+                    self.blind_broadcasts,
+                    transcript,
+                )?
             }
             State::Running => {
-                let mut data = ServerMessage::decode(data)?;
+                let mut data = server_msg;
                 for (sender, unicast) in &mut data.unicasts {
-                    let verifying_key = &public_bundles[sender].unicast_sign;
-                    let verifying_key = ecdsa::VerifyingKey::from_public_key_der(verifying_key)?;
-                    *unicast = verify_message(unicast, &verifying_key)?;
+                    let blame = |error: Error| error.blaming(*sender);
+                    *unicast = verify_message(unicast, &public_bundles[sender].unicast_sign)
+                        .map_err(blame)?;
 
-                    *unicast = ecies::decrypt(&private_bundle.unicast_decrypt, unicast)
-                        .map_err(|_| "unicast compromised")?;
+                    *unicast = private_bundle.decrypt_unicast(unicast).map_err(blame)?;
                 }
                 for (sender, broadcast) in &mut data.broadcasts {
-                    let verifying_key = &public_bundles[sender].broadcast_sign;
-                    let verifying_key = ecdsa::VerifyingKey::from_public_key_der(verifying_key)?;
-                    *broadcast = verify_message(broadcast, &verifying_key)?;
+                    *broadcast = public_bundles[sender]
+                        .verify_broadcast(broadcast)
+                        .map_err(|error| error.blaming(*sender))?;
+                }
+                if let Some(our_broadcast) = transcript.pending_broadcast.take() {
+                    transcript.absorb(&broadcast_digest(
+                        &data.broadcasts,
+                        self.share_indices[share_idx],
+                        &our_broadcast,
+                    ));
                 }
-                let data = data.encode_to_vec();
+                data.broadcasts = open_broadcasts(
+                    &data.broadcasts,
+                    self.share_indices[share_idx],
+                    private_bundle,
+                    self.blind_broadcasts,
+                )?;
+                let encoded = Zeroizing::new(data.encode_to_vec());
+                data.unicasts.values_mut().for_each(Zeroize::zeroize);
 
-                let msg = protocol.advance(&data)?;
+                let msg = protocol.advance(&encoded)?;
 
-                finalize_round(msg, &private_bundle, &public_bundles, self.protocol_type)?
+                finalize_round(
+                    msg,
+                    self.share_indices[share_idx],
+                    private_bundle,
+                    public_bundles,
+                    self.protocol_type,
+                    self.blind_broadcasts,
+                    transcript,
+                )?
             }
         };
+
+        // NOTE: the final message of a keygen is held back until the group key is confirmed
+        if let Some(group_key) = protocol.group_key() {
+            if !matches!(state, State::KeyConfirmed) {
+                let confirmation = key_confirmation(transcript, &group_key);
+                let confirmation = match self.blind_broadcasts {
+                    true => blind_broadcast(
+                        &confirmation,
+                        self.share_indices[share_idx],
+                        public_bundles,
+                    )?,
+                    false => confirmation,
+                };
+                let confirmation = echo_message(confirmation, private_bundle, self.protocol_type);
+                *state = State::KeyConfirmation(group_key, msg, recipient);
+                self.transcripts[share_idx] = next_transcript;
+                return Ok((confirmation, Recipient::Server));
+            }
+        }
+        self.transcripts[share_idx] = next_transcript;
         Ok((msg, recipient))
     }
 
+    /// Serializes the state encrypted and authenticated with a 256-bit `key`
+    pub fn seal(&self, key: &[u8]) -> Result<Vec<u8>> {
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|_| Error::misuse("invalid state key length"))?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let state = Zeroizing::new(serde_json::to_vec(self)?);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &state,
+                    aad: SEALED_STATE_AAD,
+                },
+            )
+            .map_err(|_| Error::crypto("failed to seal state"))?;
+        Ok([nonce.as_slice(), ciphertext.as_slice()].concat())
+    }
+
+    /// Deserializes a state produced by `seal` with the same `key`
+    pub fn unseal(sealed: &[u8], key: &[u8]) -> Result<Self> {
+        let cipher = Aes256Gcm::new_from_slice(key)
+            .map_err(|_| Error::misuse("invalid state key length"))?;
+        if sealed.len() < SEALED_STATE_NONCE_LEN {
+            return Err(Error::malformed("sealed state too short"));
+        }
+        let (nonce, ciphertext) = sealed.split_at(SEALED_STATE_NONCE_LEN);
+        let state = Zeroizing::new(
+            cipher
+                .decrypt(
+                    Nonce::from_slice(nonce),
+                    Payload {
+                        msg: ciphertext,
+                        aad: SEALED_STATE_AAD,
+                    },
+                )
+                .map_err(|_| Error::crypto("sealed state compromised"))?,
+        );
+        Ok(serde_json::from_slice(&state)?)
+    }
+
     /// Finishes the computation of all shares
     pub fn finish_all(self) -> Result<Vec<Vec<u8>>> {
-        self.shares
+        Ok(self.finish_all_with_receipts()?.0)
+    }
+
+    /// Finishes the computation of all shares and returns them together with signed receipts
+    /// of their transcripts, which the parties can cross-check with `verify_receipt`
+    pub fn finish_all_with_receipts(mut self) -> Result<(Vec<Vec<u8>>, Vec<Vec<u8>>)> {
+        for (state, protocol) in &self.shares {
+            if let State::Aborted(party, reason) = state {
+                return Err(abort_error(*party, *reason));
+            }
+            if protocol.group_key().is_some() && !matches!(state, State::KeyConfirmed) {
+                return Err(Error::misuse("group key not confirmed"));
+            }
+        }
+        self.transcripts
+            .resize(self.shares.len(), Transcript::default());
+        let receipts = self
+            .transcripts
+            .iter()
+            .zip(&self.share_indices)
+            .map(|(transcript, &index)| {
+                transcript.receipt(index, &self.private_bundle, self.protocol_type)
+            })
+            .collect();
+        let results = self
+            .shares
             .into_iter()
             .map(|(_, share)| share.finish())
+            .collect::<Result<_>>()?;
+        Ok((results, receipts))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::auth::{cert_key_to_pkcs12, gen_key_with_csr_for_suite};
+    use const_oid::AssociatedOid;
+    use der::{Decode, Encode};
+    use p256::ecdsa::{self, DerSignature};
+    use prost::Message as _;
+    use rand::rngs::OsRng;
+    use std::time::Duration;
+    use x509_cert::builder::{Builder, CertificateBuilder, Profile};
+    use x509_cert::request::{CertReq, ExtensionReq};
+    use x509_cert::serial_number::SerialNumber;
+    use x509_cert::time::Validity;
+
+    #[cfg(feature = "elgamal")]
+    use crate::protocol::{elgamal, KeygenProtocol, ThresholdProtocol};
+
+    pub(crate) struct Identities {
+        pub certs: HashMap<u32, Vec<u8>>,
+        pub pkcs12s: HashMap<u32, Vec<u8>>,
+    }
+
+    impl Identities {
+        /// Issues a certificate with a `MeeSignPublicBundle` for each party
+        pub fn issue(indices: impl IntoIterator<Item = u32>) -> Self {
+            Self::issue_with(indices, |_| (CipherSuite::default(), false))
+        }
+
+        /// Issues certificates with the `CipherSuite` and hybrid mode chosen for each party
+        fn issue_with(
+            indices: impl IntoIterator<Item = u32>,
+            options: impl Fn(u32) -> (CipherSuite, bool),
+        ) -> Self {
+            let ca_key = ecdsa::SigningKey::random(&mut OsRng);
+            let mut certs = HashMap::new();
+            let mut pkcs12s = HashMap::new();
+            for index in indices {
+                let (suite, hybrid) = options(index);
+                let (keys, csr) =
+                    gen_key_with_csr_for_suite(&format!("party {index}"), suite, hybrid).unwrap();
+                let csr = CertReq::from_der(&csr).unwrap();
+                let extension_req = csr
+                    .info
+                    .attributes
+                    .iter()
+                    .find(|attr| attr.oid == ExtensionReq::OID)
+                    .and_then(|attr| attr.values.get(0))
+                    .unwrap();
+                let extension_req =
+                    ExtensionReq::from_der(&extension_req.to_der().unwrap()).unwrap();
+                let bundle = extension_req
+                    .0
+                    .into_iter()
+                    .find(|ext| ext.extn_id == MeeSignPublicBundle::OID)
+                    .unwrap();
+                let bundle = MeeSignPublicBundle::from_der(bundle.extn_value.as_bytes()).unwrap();
+
+                let mut builder = CertificateBuilder::new(
+                    Profile::Root,
+                    SerialNumber::from(index + 1),
+                    Validity::from_now(Duration::from_secs(3600)).unwrap(),
+                    csr.info.subject.clone(),
+                    csr.info.public_key.clone(),
+                    &ca_key,
+                )
+                .unwrap();
+                builder.add_extension(&bundle).unwrap();
+                let cert = builder.build::<DerSignature>().unwrap().to_der().unwrap();
+
+                pkcs12s.insert(index, cert_key_to_pkcs12(&keys, &cert).unwrap());
+                certs.insert(index, cert);
+            }
+            Self { certs, pkcs12s }
+        }
+
+        /// Encodes the certificates of `indices` as sent by the server
+        pub fn certs(&self, indices: &[u32]) -> Vec<u8> {
+            ServerMessage {
+                protocol_type: 0,
+                unicasts: HashMap::new(),
+                broadcasts: indices
+                    .iter()
+                    .map(|i| (*i, self.certs[i].clone()))
+                    .collect(),
+                aborts: HashMap::new(),
+            }
+            .encode_to_vec()
+        }
+
+        fn layer(
+            &self,
+            index: u32,
+            initial_state: State,
+            share: Box<dyn Protocol>,
+            indices: &[u32],
+            protocol_type: ProtocolType,
+        ) -> SecureLayer {
+            SecureLayer::new(
+                initial_state,
+                vec![share],
+                &self.certs(indices),
+                &self.pkcs12s[&index],
+                protocol_type,
+            )
+            .unwrap()
+        }
+    }
+
+    /// Simulates the server by relaying the messages of all parties
+    pub(crate) fn route(
+        protocol_type: ProtocolType,
+        outputs: &HashMap<u32, Vec<u8>>,
+    ) -> HashMap<u32, Vec<u8>> {
+        let outputs: HashMap<u32, ClientMessage> = outputs
+            .iter()
+            .map(|(&i, out)| (i, ClientMessage::decode(out.as_slice()).unwrap()))
+            .collect();
+
+        outputs
+            .keys()
+            .map(|&idx| {
+                let mut unicasts = HashMap::new();
+                let mut broadcasts = HashMap::new();
+                let mut aborts = HashMap::new();
+                for (&sender, msg) in &outputs {
+                    if sender == idx {
+                        continue;
+                    }
+                    if let Some(broadcast) = &msg.broadcast {
+                        broadcasts.insert(sender, broadcast.clone());
+                    }
+                    if let Some(abort) = &msg.abort {
+                        aborts.insert(sender, abort.clone());
+                    }
+                    if let Some(unicast) = msg.unicasts.get(&idx) {
+                        unicasts.insert(sender, unicast.clone());
+                    }
+                }
+                let msg = ServerMessage {
+                    protocol_type: protocol_type.into(),
+                    unicasts,
+                    broadcasts,
+                    aborts,
+                };
+                (idx, msg.encode_to_vec())
+            })
+            .collect()
+    }
+
+    /// Advances all parties, passing each state through serialization beforehand
+    /// and redelivering each input afterwards
+    fn advance_all(
+        layers: &mut HashMap<u32, SecureLayer>,
+        inputs: &HashMap<u32, Vec<u8>>,
+    ) -> HashMap<u32, Vec<u8>> {
+        layers
+            .iter_mut()
+            .map(|(idx, layer)| {
+                *layer = serde_json::from_slice(&serde_json::to_vec(&*layer).unwrap()).unwrap();
+                let (out, recipient) = layer.advance_share(0, &inputs[idx]).unwrap();
+
+                *layer = serde_json::from_slice(&serde_json::to_vec(&*layer).unwrap()).unwrap();
+                let (repeated, repeated_recipient) = layer.advance_share(0, &inputs[idx]).unwrap();
+                assert_eq!(repeated, out);
+                assert_eq!(repeated_recipient, recipient);
+                (*idx, out)
+            })
+            .collect()
+    }
+
+    /// The parties of a protocol, each computing one share, with the server simulated by `route`
+    struct Parties {
+        protocol_type: ProtocolType,
+        layers: HashMap<u32, SecureLayer>,
+        /// The messages sent to the server since the protocol started
+        sent: Vec<Vec<u8>>,
+    }
+
+    impl Parties {
+        /// Starts a keygen among `indices` and acknowledges the certificates
+        fn keygen(ids: &Identities, protocol_type: ProtocolType, indices: &[u32]) -> Self {
+            let mut layers = indices
+                .iter()
+                .map(|&i| {
+                    let certs = ids.certs(indices);
+                    let layer =
+                        SecureLayer::keygen(protocol_type, false, 1, &certs, &ids.pkcs12s[&i]);
+                    (i, layer.unwrap())
+                })
+                .collect();
+            let acks = indices.iter().map(|&i| (i, Vec::new())).collect();
+            advance_all(&mut layers, &acks);
+            Self {
+                protocol_type,
+                layers,
+                sent: Vec::new(),
+            }
+        }
+
+        /// Starts a threshold protocol among `indices` with their shares of `groups`
+        fn threshold(
+            ids: &Identities,
+            protocol_type: ProtocolType,
+            groups: &HashMap<u32, Vec<u8>>,
+            indices: &[u32],
+        ) -> Self {
+            let layers = indices
+                .iter()
+                .map(|&i| {
+                    let group = [groups[&i].clone()];
+                    let certs = ids.certs(indices);
+                    let layer =
+                        SecureLayer::init(Some(protocol_type), &group, &certs, &ids.pkcs12s[&i]);
+                    (i, layer.unwrap())
+                })
+                .collect();
+            Self {
+                protocol_type,
+                layers,
+                sent: Vec::new(),
+            }
+        }
+
+        /// Advances all parties with their `inputs`, see `advance_all`
+        fn advance(&mut self, inputs: &HashMap<u32, Vec<u8>>) -> HashMap<u32, Vec<u8>> {
+            let outputs = advance_all(&mut self.layers, inputs);
+            self.sent.extend(outputs.values().cloned());
+            outputs
+        }
+
+        /// Advances all parties with the `outputs` of the previous round relayed by the server
+        fn relay(&mut self, outputs: &HashMap<u32, Vec<u8>>) -> HashMap<u32, Vec<u8>> {
+            self.advance(&route(self.protocol_type, outputs))
+        }
+
+        /// Advances all parties from their `inits` through all rounds of the protocol
+        /// and returns the outputs of the last one
+        fn run(&mut self, inits: &HashMap<u32, Vec<u8>>) -> HashMap<u32, Vec<u8>> {
+            let rounds = self
+                .layers
+                .values()
+                .next()
+                .unwrap()
+                .status(0)
+                .unwrap()
+                .rounds_total;
+            let mut outputs = self.advance(inits);
+            for _ in 1..rounds {
+                outputs = self.relay(&outputs);
+            }
+            for layer in self.layers.values() {
+                let status = layer.status(0).unwrap();
+                assert_eq!(status.round, "Done");
+                assert_eq!(status.rounds_done, rounds);
+                assert_eq!(status.recipient, Some(Recipient::Server));
+                assert!(status.finished);
+            }
+            outputs
+        }
+
+        /// Finishes all parties, checking that their transcript receipts match
+        fn finish(self, ids: &Identities) -> HashMap<u32, Vec<u8>> {
+            let mut digests = Vec::new();
+            let results = self
+                .layers
+                .into_iter()
+                .map(|(i, layer)| {
+                    let (mut results, receipts) = layer.finish_all_with_receipts().unwrap();
+                    digests.push(verify_receipt(&receipts[0], &ids.certs[&i]).unwrap());
+                    let other = ids.certs.keys().find(|&&other| other != i).unwrap();
+                    assert!(verify_receipt(&receipts[0], &ids.certs[other]).is_err());
+                    (i, results.remove(0))
+                })
+                .collect();
+            assert!(digests.windows(2).all(|pair| pair[0] == pair[1]));
+            results
+        }
+    }
+
+    /// The `ProtocolGroupInit` of each of the `indices` starting a keygen
+    fn group_inits(
+        protocol_type: ProtocolType,
+        indices: &[u32],
+        threshold: u32,
+        echo_mode: proto::EchoMode,
+        blind_broadcasts: bool,
+    ) -> HashMap<u32, Vec<u8>> {
+        indices
+            .iter()
+            .map(|&index| {
+                let init = ProtocolGroupInit {
+                    protocol_type: protocol_type.into(),
+                    index,
+                    parties: indices.len() as u32,
+                    threshold,
+                    echo_mode: echo_mode.into(),
+                    blind_broadcasts,
+                };
+                (index, init.encode_to_vec())
+            })
+            .collect()
+    }
+
+    /// The `ProtocolInit` of each of the `indices` starting a threshold protocol on `data`
+    fn inits(
+        protocol_type: ProtocolType,
+        indices: &[u32],
+        data: &[u8],
+        echo_mode: proto::EchoMode,
+        blind_broadcasts: bool,
+    ) -> HashMap<u32, Vec<u8>> {
+        indices
+            .iter()
+            .map(|&index| {
+                let init = ProtocolInit {
+                    protocol_type: protocol_type.into(),
+                    indices: indices.to_vec(),
+                    index,
+                    data: data.to_vec(),
+                    echo_mode: echo_mode.into(),
+                    blind_broadcasts,
+                };
+                (index, init.encode_to_vec())
+            })
             .collect()
     }
+
+    /// Extracts the plain broadcasts from the outputs of the final round
+    fn final_broadcasts(outputs: &HashMap<u32, Vec<u8>>) -> HashMap<u32, Vec<u8>> {
+        outputs
+            .iter()
+            .map(|(&i, out)| {
+                let msg = ClientMessage::decode(out.as_slice()).unwrap();
+                let msg = SignedMessage::decode(msg.broadcast.unwrap().as_slice()).unwrap();
+                (i, msg.message)
+            })
+            .collect()
+    }
+
+    /// Runs a keygen of `protocol_type` among `indices`,
+    /// returning the group key and the group share of each party
+    fn run_keygen(
+        ids: &Identities,
+        protocol_type: ProtocolType,
+        indices: &[u32],
+        threshold: u32,
+        echo_mode: proto::EchoMode,
+        blind_broadcasts: bool,
+    ) -> (Vec<u8>, HashMap<u32, Vec<u8>>) {
+        let mut parties = Parties::keygen(ids, protocol_type, indices);
+        let inits = group_inits(
+            protocol_type,
+            indices,
+            threshold,
+            echo_mode,
+            blind_broadcasts,
+        );
+        let outputs = parties.run(&inits);
+        for layer in parties.layers.values() {
+            assert_eq!(layer.status(0).unwrap().state, StateKind::KeyConfirmed);
+        }
+
+        let pk = parties.layers[&indices[0]].shares[0].1.group_key().unwrap();
+        if blind_broadcasts {
+            assert_server_blind(&parties.sent, &pk);
+        } else {
+            let broadcasts = final_broadcasts(&outputs);
+            assert!(broadcasts
+                .values()
+                .all(|msg| *msg == broadcasts[&indices[0]]));
+            // NOTE: FROST and MuSig2 broadcast their group key serialized as JSON
+            if matches!(protocol_type, ProtocolType::Elgamal | ProtocolType::Gg18) {
+                assert_eq!(broadcasts[&indices[0]], pk);
+            }
+        }
+        (pk, parties.finish(ids))
+    }
+
+    /// Runs a threshold protocol of `protocol_type` on `data` among `indices`,
+    /// returning the result of each party
+    fn run_threshold(
+        ids: &Identities,
+        protocol_type: ProtocolType,
+        groups: &HashMap<u32, Vec<u8>>,
+        indices: &[u32],
+        data: &[u8],
+        echo_mode: proto::EchoMode,
+        blind_broadcasts: bool,
+    ) -> Vec<Vec<u8>> {
+        let mut parties = Parties::threshold(ids, protocol_type, groups, indices);
+        parties.run(&inits(
+            protocol_type,
+            indices,
+            data,
+            echo_mode,
+            blind_broadcasts,
+        ));
+        let sent = std::mem::take(&mut parties.sent);
+
+        let results: Vec<_> = parties.finish(ids).into_values().collect();
+        if blind_broadcasts {
+            assert_server_blind(&sent, &results[0]);
+        }
+        results
+    }
+
+    #[cfg(feature = "elgamal")]
+    fn elgamal_keygen(
+        ids: &Identities,
+        parties: u32,
+        threshold: u32,
+        echo_mode: proto::EchoMode,
+        blind_broadcasts: bool,
+    ) -> (Vec<u8>, HashMap<u32, Vec<u8>>) {
+        let indices: Vec<u32> = (0..parties).collect();
+        let protocol_type = ProtocolType::Elgamal;
+        run_keygen(
+            ids,
+            protocol_type,
+            &indices,
+            threshold,
+            echo_mode,
+            blind_broadcasts,
+        )
+    }
+
+    #[cfg(feature = "elgamal")]
+    fn elgamal_decrypt(
+        ids: &Identities,
+        groups: &HashMap<u32, Vec<u8>>,
+        indices: &[u32],
+        data: &[u8],
+        echo_mode: proto::EchoMode,
+        blind_broadcasts: bool,
+    ) -> Vec<Vec<u8>> {
+        let protocol_type = ProtocolType::Elgamal;
+        run_threshold(
+            ids,
+            protocol_type,
+            groups,
+            indices,
+            data,
+            echo_mode,
+            blind_broadcasts,
+        )
+    }
+
+    /// Runs a keygen and a signing of `protocol_type` in both echo modes,
+    /// checking the signature under the group key
+    fn signing_session(protocol_type: ProtocolType, indices: &[u32], threshold: u32) {
+        let message = b"hello";
+        // NOTE: GG18 parties sign the digest of the message
+        let data = match protocol_type {
+            ProtocolType::Gg18 => <Sha256 as sha2::Digest>::digest(message).to_vec(),
+            _ => message.to_vec(),
+        };
+        for echo_mode in [proto::EchoMode::Full, proto::EchoMode::Digest] {
+            let ids = Identities::issue(indices.iter().copied());
+            let (_, groups) = run_keygen(&ids, protocol_type, indices, threshold, echo_mode, false);
+            let shares: Vec<_> = groups.values().cloned().collect();
+            let group_key = protocol::inspect_group(&shares).unwrap().public_key;
+
+            let signers = &indices[..threshold as usize];
+            for signature in run_threshold(
+                &ids,
+                protocol_type,
+                &groups,
+                signers,
+                &data,
+                echo_mode,
+                false,
+            ) {
+                assert!(protocol::verify(protocol_type, &group_key, message, &signature).is_ok());
+            }
+        }
+    }
+
+    /// Checks that a keygen of `protocol_type` among `indices` finishes only after
+    /// every party confirmed the group key
+    fn requires_key_confirmation(protocol_type: ProtocolType, indices: &[u32], threshold: u32) {
+        let ids = Identities::issue(indices.iter().copied());
+        let mut parties = Parties::keygen(&ids, protocol_type, indices);
+        let inits = group_inits(
+            protocol_type,
+            indices,
+            threshold,
+            proto::EchoMode::Full,
+            false,
+        );
+        let rounds = parties.layers[&indices[0]].status(0).unwrap().rounds_total;
+        let mut outputs = parties.advance(&inits);
+        for _ in 2..rounds {
+            outputs = parties.relay(&outputs);
+        }
+
+        // the keygen is done, but the group key is not confirmed yet
+        let copy = |layer: &SecureLayer| -> SecureLayer {
+            serde_json::from_slice(&serde_json::to_vec(layer).unwrap()).unwrap()
+        };
+        let (first, second) = (indices[0], indices[1]);
+        assert!(copy(&parties.layers[&first]).finish_all().is_err());
+
+        // the second party withholds its confirmation from the first one
+        let inputs = route(protocol_type, &outputs);
+        let mut input = ServerMessage::decode(inputs[&first].as_slice()).unwrap();
+        input.broadcasts.remove(&second);
+        let mut layer = copy(&parties.layers[&first]);
+        assert!(layer.advance_share(0, &input.encode_to_vec()).is_err());
+
+        parties.advance(&inputs);
+        for (_, layer) in parties.layers {
+            assert!(layer.finish_all().is_ok());
+        }
+    }
+
+    /// Checks that none of the messages `sent` to the server reveals the `secret`
+    fn assert_server_blind(sent: &[Vec<u8>], secret: &[u8]) {
+        for msg in sent {
+            assert!(!msg.windows(secret.len()).any(|window| window == secret));
+        }
+    }
+
+    #[cfg(feature = "elgamal")]
+    #[test]
+    fn bundles_serialize_as_der() {
+        let ids = Identities::issue(0..2);
+        let share = Box::new(elgamal::KeygenContext::new());
+        let layer = ids.layer(0, State::CertSwap, share, &[0, 1], ProtocolType::Elgamal);
+
+        let ser = serde_json::to_value(&layer).unwrap();
+        for (i, cert) in &ids.certs {
+            let der = extract_public_bundle_der(cert).unwrap();
+            assert_eq!(
+                ser["public_bundles"][i.to_string()],
+                serde_json::to_value(&der).unwrap()
+            );
+        }
+        assert!(ser["private_bundle"].is_array());
+    }
+
+    #[cfg(feature = "elgamal")]
+    #[test]
+    fn sealed_state() {
+        let ids = Identities::issue(0..2);
+        let share = Box::new(elgamal::KeygenContext::new());
+        let layer = ids.layer(0, State::CertSwap, share, &[0, 1], ProtocolType::Elgamal);
+
+        let key = derive_state_key(&ids.pkcs12s[&0]).unwrap();
+        assert_eq!(key, derive_state_key(&ids.pkcs12s[&0]).unwrap());
+        assert_ne!(key, derive_state_key(&ids.pkcs12s[&1]).unwrap());
+
+        let mut sealed = layer.seal(&key).unwrap();
+        let unsealed = SecureLayer::unseal(&sealed, &key).unwrap();
+        assert_eq!(
+            serde_json::to_vec(&layer).unwrap(),
+            serde_json::to_vec(&unsealed).unwrap()
+        );
+
+        let other_key = derive_state_key(&ids.pkcs12s[&1]).unwrap();
+        assert!(SecureLayer::unseal(&sealed, &other_key).is_err());
+
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        assert!(SecureLayer::unseal(&sealed, &key).is_err());
+    }
+
+    #[cfg(feature = "elgamal")]
+    #[test]
+    fn elgamal_session() {
+        for echo_mode in [proto::EchoMode::Full, proto::EchoMode::Digest] {
+            let ids = Identities::issue(0..3);
+            let (pk, groups) = elgamal_keygen(&ids, 3, 2, echo_mode, false);
+
+            let msg = b"hello";
+            let ct = elgamal::encrypt(msg, &pk).unwrap();
+            for result in elgamal_decrypt(&ids, &groups, &[0, 2], &ct, echo_mode, false) {
+                assert_eq!(result, msg);
+            }
+        }
+    }
+
+    #[cfg(feature = "elgamal")]
+    #[test]
+    fn blind_session() {
+        for echo_mode in [proto::EchoMode::Full, proto::EchoMode::Digest] {
+            let ids = Identities::issue_with(0..3, |i| (CipherSuite::default(), i == 0));
+            let (pk, groups) = elgamal_keygen(&ids, 3, 2, echo_mode, true);
+
+            let msg = b"a message only the participants may read";
+            let ct = elgamal::encrypt(msg, &pk).unwrap();
+            for result in elgamal_decrypt(&ids, &groups, &[0, 2], &ct, echo_mode, true) {
+                assert_eq!(result, msg);
+            }
+        }
+    }
+
+    #[cfg(feature = "elgamal")]
+    #[test]
+    fn mixed_cipher_suites() {
+        let ids = Identities::issue_with(0..3, |i| match i % 2 {
+            0 => (CipherSuite::Ed25519X25519, false),
+            _ => (CipherSuite::P256Ecies, false),
+        });
+        let (pk, groups) = elgamal_keygen(&ids, 3, 2, proto::EchoMode::Full, false);
+
+        let msg = b"hello";
+        let ct = elgamal::encrypt(msg, &pk).unwrap();
+        for result in elgamal_decrypt(&ids, &groups, &[0, 1], &ct, proto::EchoMode::Full, false) {
+            assert_eq!(result, msg);
+        }
+    }
+
+    #[cfg(feature = "elgamal")]
+    #[test]
+    fn hybrid_identities() {
+        let ids = Identities::issue_with(0..3, |i| (CipherSuite::default(), i != 1));
+        let (pk, groups) = elgamal_keygen(&ids, 3, 2, proto::EchoMode::Digest, false);
+
+        let msg = b"hello";
+        let ct = elgamal::encrypt(msg, &pk).unwrap();
+        for result in elgamal_decrypt(&ids, &groups, &[0, 2], &ct, proto::EchoMode::Full, false) {
+            assert_eq!(result, msg);
+        }
+    }
+
+    #[test]
+    fn hybrid_broadcasts_require_co_signature() {
+        let ids = Identities::issue_with([0], |_| (CipherSuite::default(), true));
+        let private_bundle =
+            PrivateBundle::from_der(extract_private_bundle_der(&ids.pkcs12s[&0]).unwrap()).unwrap();
+        let public_bundle =
+            PublicBundle::try_from(extract_public_bundle_der(&ids.certs[&0]).unwrap()).unwrap();
+
+        let signed = private_bundle.sign_broadcast(b"hello".to_vec());
+        assert_eq!(public_bundle.verify_broadcast(&signed).unwrap(), b"hello");
+
+        let mut stripped = SignedMessage::decode(signed.as_slice()).unwrap();
+        stripped.pq_signature.clear();
+        assert!(public_bundle
+            .verify_broadcast(&stripped.encode_to_vec())
+            .is_err());
+    }
+
+    #[cfg(feature = "elgamal")]
+    #[test]
+    fn digest_echo_detects_equivocation() {
+        let indices = [0, 1, 2];
+        let ids = Identities::issue(indices);
+        let mut parties = Parties::keygen(&ids, ProtocolType::Elgamal, &indices);
+        let inits = group_inits(
+            ProtocolType::Elgamal,
+            &indices,
+            2,
+            proto::EchoMode::Digest,
+            false,
+        );
+
+        // commitments are reliably broadcast and echoed
+        let commitments = parties.advance(&inits);
+        let echoes = parties.relay(&commitments);
+        let polynomials = parties.relay(&echoes);
+
+        // party 1 equivocates by sending its old commitment to party 0
+        let mut inputs = route(ProtocolType::Elgamal, &polynomials);
+        let mut input = ServerMessage::decode(inputs[&0].as_slice()).unwrap();
+        let old_broadcast = ClientMessage::decode(commitments[&1].as_slice())
+            .unwrap()
+            .broadcast;
+        input.broadcasts.insert(1, old_broadcast.unwrap());
+        inputs.insert(0, input.encode_to_vec());
+
+        let digests = parties.advance(&inputs);
+        let fetched = parties.relay(&digests);
+        let inputs = route(ProtocolType::Elgamal, &fetched);
+        for (i, layer) in &mut parties.layers {
+            let error = layer.advance_share(0, &inputs[i]).unwrap_err();
+            if *i != 1 {
+                assert!(matches!(
+                    error,
+                    Error::Crypto {
+                        culprit: Some(1),
+                        ..
+                    }
+                ));
+            }
+        }
+    }
+
+    #[cfg(feature = "elgamal")]
+    #[test]
+    fn declined_session_aborts() {
+        let indices = [0, 1, 2];
+        let ids = Identities::issue(indices);
+        let (pk, groups) = elgamal_keygen(&ids, 3, 2, proto::EchoMode::Full, false);
+        let ct = elgamal::encrypt(b"hello", &pk).unwrap();
+
+        let mut parties = Parties::threshold(&ids, ProtocolType::Elgamal, &groups, &indices);
+        let mut outputs = parties.advance(&inits(
+            ProtocolType::Elgamal,
+            &indices,
+            &ct,
+            proto::EchoMode::Full,
+            false,
+        ));
+
+        // party 2 declines instead of sending its echo
+        let decliner = parties.layers.get_mut(&2).unwrap();
+        let abort = decliner.abort_share(0, AbortReason::Declined).unwrap();
+        assert!(decliner.advance_share(0, &[]).is_err());
+        outputs.insert(2, abort);
+
+        let inputs = route(ProtocolType::Elgamal, &outputs);
+        for i in [0, 1] {
+            let layer = parties.layers.get_mut(&i).unwrap();
+            assert!(layer.advance_share(0, &inputs[&i]).is_err());
+            assert!(matches!(
+                layer.shares[0].0,
+                State::Aborted(2, AbortReason::Declined)
+            ));
+            assert!(layer.abort_share(0, AbortReason::Error).is_err());
+        }
+        for (_, layer) in parties.layers {
+            assert!(layer.finish_all().is_err());
+        }
+    }
+
+    #[cfg(feature = "elgamal")]
+    #[test]
+    fn forged_abort_is_rejected() {
+        let ids = Identities::issue(0..2);
+        let (_, groups) = elgamal_keygen(&ids, 2, 2, proto::EchoMode::Full, false);
+        let share = Box::new(elgamal::DecryptContext::new(&groups[&0]).unwrap());
+        let mut layer = ids.layer(0, State::Running, share, &[0, 1], ProtocolType::Elgamal);
+        layer.participant_indices = vec![0, 1];
+        let forger = ids.layer(
+            1,
+            State::Running,
+            Box::new(elgamal::DecryptContext::new(&groups[&1]).unwrap()),
+            &[0, 1],
+            ProtocolType::Elgamal,
+        );
+
+        // party 1 cannot abort in the name of party 0
+        let abort = abort_message(
+            0,
+            AbortReason::Declined,
+            &forger.private_bundle,
+            ProtocolType::Elgamal,
+        );
+        let abort = ClientMessage::decode(abort.as_slice())
+            .unwrap()
+            .abort
+            .unwrap();
+        let input = ServerMessage {
+            protocol_type: ProtocolType::Elgamal.into(),
+            unicasts: HashMap::new(),
+            broadcasts: HashMap::new(),
+            aborts: HashMap::from([(1, abort)]),
+        };
+        assert!(layer.advance_share(0, &input.encode_to_vec()).is_err());
+        assert!(matches!(
+            layer.shares[0].0,
+            State::Aborted(0, AbortReason::Error)
+        ));
+    }
+
+    #[cfg(feature = "elgamal")]
+    #[test]
+    fn malformed_input_keeps_share() {
+        let ids = Identities::issue(0..2);
+        let (pk, groups) = elgamal_keygen(&ids, 2, 2, proto::EchoMode::Full, false);
+        let share = Box::new(elgamal::DecryptContext::new(&groups[&0]).unwrap());
+        let mut layer = ids.layer(0, State::Init, share, &[0, 1], ProtocolType::Elgamal);
+
+        let error = layer.advance_share(0, b"\xff").unwrap_err();
+        assert!(matches!(error, Error::MalformedInput(_)));
+        assert!(matches!(layer.shares[0].0, State::Init));
+
+        let init = |indices: Vec<u32>| ProtocolInit {
+            protocol_type: ProtocolType::Elgamal.into(),
+            indices,
+            index: 0,
+            data: elgamal::encrypt(b"hello", &pk).unwrap(),
+            echo_mode: proto::EchoMode::Full.into(),
+            blind_broadcasts: false,
+        };
+        let error = layer
+            .advance_share(0, &init(vec![0, 2]).encode_to_vec())
+            .unwrap_err();
+        assert!(matches!(error, Error::MalformedInput(_)));
+        assert!(matches!(layer.shares[0].0, State::Init));
+
+        assert!(layer
+            .advance_share(0, &init(vec![0, 1]).encode_to_vec())
+            .is_ok());
+        assert!(!matches!(
+            layer.shares[0].0,
+            State::Init | State::Aborted(..)
+        ));
+    }
+
+    #[test]
+    fn server_message_limits() {
+        let participants = [0, 1, 2];
+        let encode = |unicasts: HashMap<u32, Vec<u8>>, broadcasts: HashMap<u32, Vec<u8>>| {
+            ServerMessage {
+                protocol_type: ProtocolType::Elgamal.into(),
+                unicasts,
+                broadcasts,
+                aborts: HashMap::new(),
+            }
+            .encode_to_vec()
+        };
+        let bounds = MessageBounds::broadcasts(16);
+
+        let valid = encode(
+            HashMap::new(),
+            HashMap::from([(1, vec![0; 16]), (2, vec![0; 16])]),
+        );
+        assert!(decode_server_message(&valid, 0, &participants, bounds).is_ok());
+        let short = MessageBounds::broadcasts(15);
+        assert!(decode_server_message(&valid, 0, &participants, short).is_err());
+        assert!(decode_server_message(&valid, 1, &participants, bounds).is_err());
+        assert!(decode_server_message(&valid, 0, &[0, 1], bounds).is_err());
+
+        let unknown = encode(HashMap::new(), HashMap::from([(3, vec![0; 16])]));
+        assert!(decode_server_message(&unknown, 0, &participants, bounds).is_err());
+
+        let unicasts = encode(HashMap::from([(1, vec![0; 16])]), HashMap::new());
+        assert!(decode_server_message(&unicasts, 0, &participants, bounds).is_err());
+        let running = MessageBounds {
+            unicast_len: Some(16),
+            broadcast_len: 16,
+        };
+        assert!(decode_server_message(&unicasts, 0, &participants, running).is_ok());
+
+        let oversized = vec![0; bounds.max_len(participants.len()) + 1];
+        assert!(decode_server_message(&oversized, 0, &participants, bounds).is_err());
+    }
+
+    #[cfg(feature = "elgamal")]
+    #[test]
+    fn keygen_requires_key_confirmation() {
+        requires_key_confirmation(ProtocolType::Elgamal, &[0, 1, 2], 2);
+    }
+
+    /// Sends a card command numbered by the card responses received so far
+    #[derive(Deserialize, Serialize)]
+    struct CardRounds {
+        responses: u8,
+    }
+
+    #[typetag::serde(name = "test_card_rounds")]
+    impl Protocol for CardRounds {
+        fn advance(&mut self, _: &[u8]) -> Result<Message> {
+            self.responses += 1;
+            Ok(Message::new_card_command(vec![self.responses]))
+        }
+
+        fn finish(self: Box<Self>) -> Result<Vec<u8>> {
+            Err(Error::misuse("protocol not finished"))
+        }
+
+        fn round(&self) -> &'static str {
+            "Card"
+        }
+
+        fn rounds(&self) -> u16 {
+            1
+        }
+
+        fn is_finished(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn repeated_card_responses_advance() {
+        let ids = Identities::issue(0..2);
+        let share = Box::new(CardRounds { responses: 0 });
+        let mut layer = ids.layer(0, State::CardResponse, share, &[0, 1], ProtocolType::Frost);
+
+        for expected in 1..=3 {
+            let (command, recipient) = layer.advance_share(0, b"\x90\x00").unwrap();
+            assert_eq!(command, [expected]);
+            assert_eq!(recipient, Recipient::Card);
+        }
+    }
+
+    #[cfg(feature = "elgamal")]
+    #[test]
+    fn roster_mismatch_is_rejected() {
+        let ids = Identities::issue(0..4);
+        let (pk, groups) = elgamal_keygen(&ids, 3, 2, proto::EchoMode::Full, false);
+        let ct = elgamal::encrypt(b"hello", &pk).unwrap();
+
+        let init = |indices: &[u32], certified: &[u32]| {
+            let share = Box::new(elgamal::DecryptContext::new(&groups[&0]).unwrap());
+            let mut layer = ids.layer(0, State::Init, share, certified, ProtocolType::Elgamal);
+            let init = ProtocolInit {
+                protocol_type: ProtocolType::Elgamal.into(),
+                indices: indices.to_vec(),
+                index: 0,
+                data: ct.clone(),
+                echo_mode: proto::EchoMode::Full.into(),
+                blind_broadcasts: false,
+            };
+            layer.advance_share(0, &init.encode_to_vec())
+        };
+
+        assert!(init(&[0, 2], &[0, 2]).is_ok());
+        // certificates of non-participants
+        assert!(init(&[0, 2], &[0, 1, 2]).is_err());
+        // missing certificates
+        assert!(init(&[0, 1, 2], &[0, 2]).is_err());
+        // participants outside of the group
+        assert!(init(&[0, 3], &[0, 3]).is_err());
+        // duplicate participants
+        assert!(init(&[0, 2, 2], &[0, 2]).is_err());
+        // not enough participants
+        assert!(init(&[0], &[0]).is_err());
+    }
+
+    #[cfg(feature = "gg18")]
+    #[test]
+    fn gg18_session() {
+        signing_session(ProtocolType::Gg18, &[0, 1], 2);
+        requires_key_confirmation(ProtocolType::Gg18, &[0, 1], 2);
+    }
+
+    #[cfg(feature = "gg18")]
+    #[test]
+    fn gg18_refuses_groups_without_params() {
+        let indices = [0, 1];
+        let ids = Identities::issue(indices);
+        let (_, groups) = run_keygen(
+            &ids,
+            ProtocolType::Gg18,
+            &indices,
+            2,
+            proto::EchoMode::Full,
+            false,
+        );
+
+        // NOTE: groups created before tagging hold the bare share
+        let tagged: HashMap<String, Box<serde_json::value::RawValue>> =
+            serde_json::from_slice(&groups[&0]).unwrap();
+        let share = tagged["share"].get().as_bytes().to_vec();
+        let error = SecureLayer::init(
+            Some(ProtocolType::Gg18),
+            &[share],
+            &ids.certs(&indices),
+            &ids.pkcs12s[&0],
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(error, Error::Misuse(_)));
+    }
+
+    #[cfg(feature = "frost")]
+    #[test]
+    fn frost_session() {
+        // NOTE: FROST identifies the parties from 1
+        signing_session(ProtocolType::Frost, &[1, 2, 3], 2);
+        requires_key_confirmation(ProtocolType::Frost, &[1, 2, 3], 2);
+    }
+
+    #[cfg(feature = "musig2")]
+    #[test]
+    fn musig2_session() {
+        signing_session(ProtocolType::Musig2, &[0, 1, 2], 3);
+        requires_key_confirmation(ProtocolType::Musig2, &[0, 1, 2], 3);
+    }
 }
@@ -0,0 +1,441 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::protocol::{Recipient, Result};
+use crate::security::{AbortReason, ProtocolType, SecureLayer, ShareStatus};
+
+/// A message produced by a session, which has to be delivered to its `recipient`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response {
+    pub data: Vec<u8>,
+    pub recipient: Recipient,
+}
+
+/// The results of a finished session
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Output {
+    /// The result of each share, i.e. a group blob, a signature or a plaintext
+    pub results: Vec<Vec<u8>>,
+    /// The signed transcript receipt of each share, see `security::verify_receipt`
+    pub receipts: Vec<Vec<u8>>,
+}
+
+/// A protocol session computing one or more shares of a participant
+#[derive(Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Session {
+    inner: SecureLayer,
+}
+
+impl Session {
+    /// Configures a keygen of `protocol_type`
+    pub fn keygen(protocol_type: ProtocolType) -> KeygenBuilder {
+        KeygenBuilder {
+            protocol_type,
+            certs: Vec::new(),
+            identity: Vec::new(),
+            shares: 1,
+            with_card: false,
+        }
+    }
+
+    /// Configures a threshold protocol computing the `group` shares output by a keygen
+    pub fn threshold(group: Vec<Vec<u8>>) -> ThresholdBuilder {
+        ThresholdBuilder {
+            group,
+            certs: Vec::new(),
+            identity: Vec::new(),
+            protocol_type: None,
+            purpose: None,
+        }
+    }
+
+    /// Configures a signing session, refusing groups of encryption protocols
+    pub fn sign(group: Vec<Vec<u8>>) -> ThresholdBuilder {
+        ThresholdBuilder {
+            purpose: Some(Purpose::Sign),
+            ..Self::threshold(group)
+        }
+    }
+
+    /// Configures a decryption session, refusing groups of signing protocols
+    pub fn decrypt(group: Vec<Vec<u8>>) -> ThresholdBuilder {
+        ThresholdBuilder {
+            purpose: Some(Purpose::Decrypt),
+            ..Self::threshold(group)
+        }
+    }
+
+    /// Restores a session saved with `to_bytes`
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        Ok(serde_json::from_slice(data)?)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Restores a session saved with `seal`
+    pub fn unseal(sealed: &[u8], key: &[u8]) -> Result<Self> {
+        Ok(Self {
+            inner: SecureLayer::unseal(sealed, key)?,
+        })
+    }
+
+    /// Saves the session encrypted under `key`, see `security::derive_state_key`
+    pub fn seal(&self, key: &[u8]) -> Result<Vec<u8>> {
+        self.inner.seal(key)
+    }
+
+    pub fn protocol_type(&self) -> ProtocolType {
+        self.inner.protocol_type()
+    }
+
+    pub fn share_count(&self) -> usize {
+        self.inner.share_count()
+    }
+
+    pub fn status(&self, index: usize) -> Result<ShareStatus> {
+        self.inner.status(index)
+    }
+
+    /// Advances share `index` with a message from the server or a card.
+    /// A share which fails a cryptographic check is aborted,
+    /// its abort message can then be obtained from `abort`
+    pub fn advance(&mut self, index: usize, data: &[u8]) -> Result<Response> {
+        let (data, recipient) = self.inner.advance_share(index, data)?;
+        Ok(Response { data, recipient })
+    }
+
+    /// Aborts share `index` and returns the abort message for the server
+    pub fn abort(&mut self, index: usize, reason: AbortReason) -> Result<Vec<u8>> {
+        self.inner.abort_share(index, reason)
+    }
+
+    /// Returns the result of each share
+    pub fn finish(self) -> Result<Vec<Vec<u8>>> {
+        self.inner.finish_all()
+    }
+
+    /// Returns the result of each share together with the receipt of its transcript
+    pub fn finish_with_receipts(self) -> Result<Output> {
+        let (results, receipts) = self.inner.finish_all_with_receipts()?;
+        Ok(Output { results, receipts })
+    }
+}
+
+/// Configures a keygen, see `Session::keygen`
+#[derive(Clone)]
+pub struct KeygenBuilder {
+    protocol_type: ProtocolType,
+    certs: Vec<u8>,
+    identity: Vec<u8>,
+    shares: usize,
+    with_card: bool,
+}
+
+impl KeygenBuilder {
+    /// The `ServerMessage` broadcasting the certificate of each party
+    pub fn certs(mut self, certs: impl Into<Vec<u8>>) -> Self {
+        self.certs = certs.into();
+        self
+    }
+
+    /// The PKCS #12 bundle of the participant, see `auth::cert_key_to_pkcs12`
+    pub fn identity(mut self, pkcs12: impl Into<Vec<u8>>) -> Self {
+        self.identity = pkcs12.into();
+        self
+    }
+
+    /// The number of shares of the participant, one by default
+    pub fn shares(mut self, shares: usize) -> Self {
+        self.shares = shares;
+        self
+    }
+
+    /// Whether the shares are computed with a smart card
+    pub fn with_card(mut self, with_card: bool) -> Self {
+        self.with_card = with_card;
+        self
+    }
+
+    pub fn build(self) -> Result<Session> {
+        if self.shares == 0 {
+            return Err(Error::misuse("no shares requested"));
+        }
+        let inner = SecureLayer::keygen(
+            self.protocol_type,
+            self.with_card,
+            self.shares,
+            &self.certs,
+            &self.identity,
+        )?;
+        Ok(Session { inner })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Purpose {
+    Sign,
+    Decrypt,
+}
+
+/// Configures a threshold protocol, see `Session::threshold`
+#[derive(Clone)]
+pub struct ThresholdBuilder {
+    group: Vec<Vec<u8>>,
+    certs: Vec<u8>,
+    identity: Vec<u8>,
+    protocol_type: Option<ProtocolType>,
+    purpose: Option<Purpose>,
+}
+
+impl ThresholdBuilder {
+    /// The `ServerMessage` broadcasting the certificate of each party
+    pub fn certs(mut self, certs: impl Into<Vec<u8>>) -> Self {
+        self.certs = certs.into();
+        self
+    }
+
+    /// The PKCS #12 bundle of the participant, see `auth::cert_key_to_pkcs12`
+    pub fn identity(mut self, pkcs12: impl Into<Vec<u8>>) -> Self {
+        self.identity = pkcs12.into();
+        self
+    }
+
+    /// The protocol the group has to belong to. Required only for groups
+    /// created before they recorded their protocol
+    pub fn protocol(mut self, protocol_type: ProtocolType) -> Self {
+        self.protocol_type = Some(protocol_type);
+        self
+    }
+
+    pub fn build(self) -> Result<Session> {
+        let inner =
+            SecureLayer::init(self.protocol_type, &self.group, &self.certs, &self.identity)?;
+        let encrypts = inner.protocol_type() == ProtocolType::Elgamal;
+        match self.purpose {
+            Some(Purpose::Sign) if encrypts => {
+                return Err(Error::misuse("group cannot sign"));
+            }
+            Some(Purpose::Decrypt) if !encrypts => {
+                return Err(Error::misuse("group cannot decrypt"));
+            }
+            _ => {}
+        }
+        Ok(Session { inner })
+    }
+}
+
+#[cfg(all(test, feature = "elgamal"))]
+mod tests {
+    use super::*;
+    use crate::proto::{ProtocolGroupInit, ProtocolInit};
+    use crate::protocol::{elgamal, inspect_group};
+    use crate::security::derive_state_key;
+    use crate::security::tests::{route, Identities};
+    use prost::Message as _;
+    use std::collections::HashMap;
+
+    /// Advances all sessions until they finish, passing each one through persistence
+    fn run(
+        ids: &Identities,
+        protocol_type: ProtocolType,
+        mut sessions: HashMap<u32, Session>,
+        mut inputs: HashMap<u32, Vec<u8>>,
+    ) -> HashMap<u32, Vec<u8>> {
+        while !sessions.values().all(|s| s.status(0).unwrap().finished) {
+            let outputs = sessions
+                .iter_mut()
+                .map(|(i, session)| {
+                    let key = derive_state_key(&ids.pkcs12s[i]).unwrap();
+                    *session = Session::unseal(&session.seal(&key).unwrap(), &key).unwrap();
+                    *session = Session::from_bytes(&session.to_bytes().unwrap()).unwrap();
+
+                    let response = session.advance(0, &inputs[i]).unwrap();
+                    assert_eq!(response.recipient, Recipient::Server);
+                    (*i, response.data)
+                })
+                .collect();
+            inputs = route(protocol_type, &outputs);
+        }
+        sessions
+            .into_iter()
+            .map(|(i, session)| (i, session.finish().unwrap().remove(0)))
+            .collect()
+    }
+
+    #[test]
+    fn elgamal_session() {
+        let indices = [0, 1];
+        let ids = Identities::issue(indices);
+
+        let mut keygens: HashMap<u32, Session> = indices
+            .iter()
+            .map(|&i| {
+                let session = Session::keygen(ProtocolType::Elgamal)
+                    .certs(ids.certs(&indices))
+                    .identity(ids.pkcs12s[&i].clone())
+                    .build()
+                    .unwrap();
+                (i, session)
+            })
+            .collect();
+        for session in keygens.values_mut() {
+            session.advance(0, &[]).unwrap();
+        }
+        let inits = indices
+            .iter()
+            .map(|&index| {
+                let init = ProtocolGroupInit {
+                    protocol_type: ProtocolType::Elgamal.into(),
+                    index,
+                    parties: 2,
+                    threshold: 2,
+                    ..Default::default()
+                };
+                (index, init.encode_to_vec())
+            })
+            .collect();
+        let groups = run(&ids, ProtocolType::Elgamal, keygens, inits);
+
+        let group = vec![groups[&0].clone()];
+        let key = inspect_group(&group).unwrap().public_key;
+        let ciphertext = elgamal::encrypt(b"hello", &key).unwrap();
+        assert!(Session::sign(group)
+            .certs(ids.certs(&indices))
+            .identity(ids.pkcs12s[&0].clone())
+            .build()
+            .is_err());
+
+        let decrypts = indices
+            .iter()
+            .map(|&i| {
+                let session = Session::decrypt(vec![groups[&i].clone()])
+                    .certs(ids.certs(&indices))
+                    .identity(ids.pkcs12s[&i].clone())
+                    .protocol(ProtocolType::Elgamal)
+                    .build()
+                    .unwrap();
+                (i, session)
+            })
+            .collect();
+        let inits = indices
+            .iter()
+            .map(|&index| {
+                let init = ProtocolInit {
+                    protocol_type: ProtocolType::Elgamal.into(),
+                    indices: indices.to_vec(),
+                    index,
+                    data: ciphertext.clone(),
+                    ..Default::default()
+                };
+                (index, init.encode_to_vec())
+            })
+            .collect();
+        for plaintext in run(&ids, ProtocolType::Elgamal, decrypts, inits).values() {
+            assert_eq!(plaintext, b"hello");
+        }
+    }
+
+    #[cfg(feature = "musig2")]
+    #[test]
+    fn musig2_session() {
+        let indices = [0, 1];
+        let ids = Identities::issue(indices);
+
+        let mut keygens: HashMap<u32, Session> = indices
+            .iter()
+            .map(|&i| {
+                let session = Session::keygen(ProtocolType::Musig2)
+                    .certs(ids.certs(&indices))
+                    .identity(ids.pkcs12s[&i].clone())
+                    .build()
+                    .unwrap();
+                (i, session)
+            })
+            .collect();
+        for session in keygens.values_mut() {
+            session.advance(0, &[]).unwrap();
+        }
+        let inits = indices
+            .iter()
+            .map(|&index| {
+                let init = ProtocolGroupInit {
+                    protocol_type: ProtocolType::Musig2.into(),
+                    index,
+                    parties: 2,
+                    threshold: 2,
+                    ..Default::default()
+                };
+                (index, init.encode_to_vec())
+            })
+            .collect();
+        let groups = run(&ids, ProtocolType::Musig2, keygens, inits);
+
+        let group = vec![groups[&0].clone()];
+        let key = inspect_group(&group).unwrap().public_key;
+        assert!(Session::decrypt(group)
+            .certs(ids.certs(&indices))
+            .identity(ids.pkcs12s[&0].clone())
+            .build()
+            .is_err());
+
+        let mut signs: HashMap<u32, Session> = indices
+            .iter()
+            .map(|&i| {
+                let session = Session::sign(vec![groups[&i].clone()])
+                    .certs(ids.certs(&indices))
+                    .identity(ids.pkcs12s[&i].clone())
+                    .build()
+                    .unwrap();
+                (i, session)
+            })
+            .collect();
+        let error = signs.get_mut(&0).unwrap().advance(1, &[]).unwrap_err();
+        assert!(matches!(error, Error::Misuse(_)));
+        let error = signs.get_mut(&0).unwrap().abort(1, AbortReason::Error);
+        assert!(matches!(error, Err(Error::Misuse(_))));
+
+        let inits = indices
+            .iter()
+            .map(|&index| {
+                let init = ProtocolInit {
+                    protocol_type: ProtocolType::Musig2.into(),
+                    indices: indices.to_vec(),
+                    index,
+                    data: b"hello".to_vec(),
+                    ..Default::default()
+                };
+                (index, init.encode_to_vec())
+            })
+            .collect();
+        for signature in run(&ids, ProtocolType::Musig2, signs, inits).values() {
+            assert!(
+                crate::protocol::verify(ProtocolType::Musig2, &key, b"hello", signature).is_ok()
+            );
+        }
+    }
+
+    #[test]
+    fn builders_validate() {
+        let ids = Identities::issue([0]);
+        let error = Session::keygen(ProtocolType::Elgamal)
+            .certs(ids.certs(&[0]))
+            .identity(ids.pkcs12s[&0].clone())
+            .shares(0)
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(error, Error::Misuse(_)));
+
+        let error = Session::keygen(ProtocolType::Elgamal)
+            .certs(ids.certs(&[0]))
+            .build()
+            .err()
+            .unwrap();
+        assert!(!matches!(error, Error::Misuse(_)));
+
+        assert!(Session::decrypt(Vec::new()).build().is_err());
+    }
+}
@@ -0,0 +1,283 @@
+use crate::error::Error;
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, KeyInit, Nonce};
+use hkdf::Hkdf;
+use p256::ecdsa;
+use p256::ecdsa::signature::{Signer as _, Verifier as _};
+use p256::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey};
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::convert::TryFrom;
+use zeroize::Zeroizing;
+
+type Result<T> = std::result::Result<T, Error>;
+
+const X25519_KEY_LEN: usize = 32;
+const X25519_INFO: &[u8] = b"meesign x25519 chacha20poly1305";
+
+/// Identifies the algorithms behind the keys of a `MeeSignPublicBundle`.
+/// Each party signs with and receives unicasts encrypted under the suite of its own certificate,
+/// so parties with different suites can take part in one session
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CipherSuite {
+    /// ECDSA P-256 signatures and ECIES secp256k1 encryption
+    #[default]
+    P256Ecies,
+    /// Ed25519 signatures and X25519 with ChaCha20-Poly1305 encryption
+    Ed25519X25519,
+}
+
+impl TryFrom<u8> for CipherSuite {
+    type Error = Error;
+
+    fn try_from(id: u8) -> Result<Self> {
+        match id {
+            0 => Ok(CipherSuite::P256Ecies),
+            1 => Ok(CipherSuite::Ed25519X25519),
+            _ => Err(Error::malformed(format!("unsupported cipher suite {id}"))),
+        }
+    }
+}
+
+impl From<CipherSuite> for u8 {
+    fn from(suite: CipherSuite) -> u8 {
+        match suite {
+            CipherSuite::P256Ecies => 0,
+            CipherSuite::Ed25519X25519 => 1,
+        }
+    }
+}
+
+impl CipherSuite {
+    /// Generates a signing key, returning its PKCS#8 DER and the SPKI DER of its public key
+    pub(crate) fn generate_signing_key(self) -> Result<(Zeroizing<Vec<u8>>, Vec<u8>)> {
+        Ok(match self {
+            CipherSuite::P256Ecies => {
+                let key = ecdsa::SigningKey::random(&mut OsRng);
+                (
+                    Zeroizing::new(
+                        key.to_pkcs8_der()
+                            .map_err(|_| Error::serialization("failed to encode signing key"))?
+                            .as_bytes()
+                            .to_vec(),
+                    ),
+                    key.verifying_key()
+                        .to_public_key_der()
+                        .map_err(|_| Error::serialization("failed to encode verifying key"))?
+                        .as_bytes()
+                        .to_vec(),
+                )
+            }
+            CipherSuite::Ed25519X25519 => {
+                let key = ed25519_dalek::SigningKey::generate(&mut OsRng);
+                (
+                    Zeroizing::new(
+                        key.to_pkcs8_der()
+                            .map_err(|_| Error::serialization("failed to encode signing key"))?
+                            .as_bytes()
+                            .to_vec(),
+                    ),
+                    key.verifying_key()
+                        .to_public_key_der()
+                        .map_err(|_| Error::serialization("failed to encode verifying key"))?
+                        .as_bytes()
+                        .to_vec(),
+                )
+            }
+        })
+    }
+
+    /// Generates an encryption key pair, returning the raw private and public key
+    pub(crate) fn generate_encryption_key(self) -> (Zeroizing<Vec<u8>>, Vec<u8>) {
+        match self {
+            CipherSuite::P256Ecies => {
+                let (private_key, public_key) = ecies::utils::generate_keypair();
+                (
+                    Zeroizing::new(private_key.serialize().to_vec()),
+                    public_key.serialize().to_vec(),
+                )
+            }
+            CipherSuite::Ed25519X25519 => {
+                let private_key = x25519_dalek::StaticSecret::random_from_rng(OsRng);
+                let public_key = x25519_dalek::PublicKey::from(&private_key);
+                (
+                    Zeroizing::new(private_key.to_bytes().to_vec()),
+                    public_key.as_bytes().to_vec(),
+                )
+            }
+        }
+    }
+}
+
+/// A private signing key of either suite
+pub(crate) enum SigningKey {
+    P256(ecdsa::SigningKey),
+    Ed25519(ed25519_dalek::SigningKey),
+}
+
+impl SigningKey {
+    pub fn from_pkcs8_der(suite: CipherSuite, der: &[u8]) -> Result<Self> {
+        Ok(match suite {
+            CipherSuite::P256Ecies => SigningKey::P256(
+                ecdsa::SigningKey::from_pkcs8_der(der)
+                    .map_err(|_| Error::malformed("invalid signing key"))?,
+            ),
+            CipherSuite::Ed25519X25519 => SigningKey::Ed25519(
+                ed25519_dalek::SigningKey::from_pkcs8_der(der)
+                    .map_err(|_| Error::malformed("invalid signing key"))?,
+            ),
+        })
+    }
+
+    pub fn sign(&self, msg: &[u8]) -> Vec<u8> {
+        match self {
+            SigningKey::P256(key) => {
+                let signature: ecdsa::Signature = key.sign(msg);
+                signature.to_vec()
+            }
+            SigningKey::Ed25519(key) => key.sign(msg).to_vec(),
+        }
+    }
+}
+
+/// A public verifying key of either suite
+#[derive(Clone)]
+pub(crate) enum VerifyingKey {
+    P256(ecdsa::VerifyingKey),
+    Ed25519(ed25519_dalek::VerifyingKey),
+}
+
+impl VerifyingKey {
+    pub fn from_public_key_der(suite: CipherSuite, der: &[u8]) -> Result<Self> {
+        Ok(match suite {
+            CipherSuite::P256Ecies => VerifyingKey::P256(
+                ecdsa::VerifyingKey::from_public_key_der(der)
+                    .map_err(|_| Error::malformed("invalid verifying key"))?,
+            ),
+            CipherSuite::Ed25519X25519 => VerifyingKey::Ed25519(
+                ed25519_dalek::VerifyingKey::from_public_key_der(der)
+                    .map_err(|_| Error::malformed("invalid verifying key"))?,
+            ),
+        })
+    }
+
+    pub fn verify(&self, msg: &[u8], signature: &[u8]) -> Result<()> {
+        match self {
+            VerifyingKey::P256(key) => {
+                let signature = ecdsa::Signature::from_slice(signature)
+                    .map_err(|_| Error::crypto("invalid signature"))?;
+                key.verify(msg, &signature)
+                    .map_err(|_| Error::crypto("signature verification failed"))?;
+            }
+            VerifyingKey::Ed25519(key) => {
+                let signature = ed25519_dalek::Signature::from_slice(signature)
+                    .map_err(|_| Error::crypto("invalid signature"))?;
+                key.verify_strict(msg, &signature)
+                    .map_err(|_| Error::crypto("signature verification failed"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Encrypts `msg` for the holder of the raw private key to `public_key`
+pub(crate) fn encrypt(suite: CipherSuite, public_key: &[u8], msg: &[u8]) -> Result<Vec<u8>> {
+    match suite {
+        CipherSuite::P256Ecies => {
+            ecies::encrypt(public_key, msg).map_err(|_| Error::crypto("failed to encrypt unicast"))
+        }
+        CipherSuite::Ed25519X25519 => {
+            let public_key = <[u8; X25519_KEY_LEN]>::try_from(public_key)
+                .map_err(|_| Error::malformed("invalid encryption key"))?;
+            let public_key = x25519_dalek::PublicKey::from(public_key);
+            let ephemeral = x25519_dalek::EphemeralSecret::random_from_rng(OsRng);
+            let ephemeral_public = x25519_dalek::PublicKey::from(&ephemeral);
+            let shared = ephemeral.diffie_hellman(&public_key);
+            if !shared.was_contributory() {
+                return Err(Error::malformed("invalid encryption key"));
+            }
+
+            let cipher = x25519_cipher(shared.as_bytes(), &ephemeral_public, &public_key)?;
+            // NOTE: each message is encrypted under a fresh key, so a fixed nonce is sound
+            let ciphertext = cipher
+                .encrypt(&Nonce::default(), msg)
+                .map_err(|_| Error::crypto("failed to encrypt unicast"))?;
+            Ok([&ephemeral_public.as_bytes()[..], ciphertext.as_slice()].concat())
+        }
+    }
+}
+
+/// Decrypts a message produced by `encrypt` with the raw `private_key`
+pub(crate) fn decrypt(
+    suite: CipherSuite,
+    private_key: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>> {
+    match suite {
+        CipherSuite::P256Ecies => ecies::decrypt(private_key, ciphertext)
+            .map_err(|_| Error::crypto("unicast compromised")),
+        CipherSuite::Ed25519X25519 => {
+            if ciphertext.len() < X25519_KEY_LEN {
+                return Err(Error::crypto("unicast compromised"));
+            }
+            let (ephemeral_public, ciphertext) = ciphertext.split_at(X25519_KEY_LEN);
+            let ephemeral_public = <[u8; X25519_KEY_LEN]>::try_from(ephemeral_public)
+                .map_err(|_| Error::crypto("unicast compromised"))?;
+            let ephemeral_public = x25519_dalek::PublicKey::from(ephemeral_public);
+            let private_key = <[u8; X25519_KEY_LEN]>::try_from(private_key)
+                .map_err(|_| Error::malformed("invalid decryption key"))?;
+            let private_key = Zeroizing::new(private_key);
+            let private_key = x25519_dalek::StaticSecret::from(*private_key);
+            let shared = private_key.diffie_hellman(&ephemeral_public);
+            if !shared.was_contributory() {
+                return Err(Error::crypto("unicast compromised"));
+            }
+
+            let public_key = x25519_dalek::PublicKey::from(&private_key);
+            let cipher = x25519_cipher(shared.as_bytes(), &ephemeral_public, &public_key)?;
+            cipher
+                .decrypt(&Nonce::default(), ciphertext)
+                .map_err(|_| Error::crypto("unicast compromised"))
+        }
+    }
+}
+
+/// Derives the cipher of one message from an X25519 shared secret bound to both public keys
+fn x25519_cipher(
+    shared: &[u8],
+    ephemeral_public: &x25519_dalek::PublicKey,
+    public_key: &x25519_dalek::PublicKey,
+) -> Result<ChaCha20Poly1305> {
+    let salt = [ephemeral_public.as_bytes(), public_key.as_bytes()].concat();
+    let mut key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(X25519_INFO, &mut key[..])
+        .map_err(|_| Error::crypto("failed to derive unicast key"))?;
+    ChaCha20Poly1305::new_from_slice(&key[..]).map_err(|_| Error::crypto("invalid unicast key"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suites_roundtrip() {
+        for suite in [CipherSuite::P256Ecies, CipherSuite::Ed25519X25519] {
+            assert_eq!(CipherSuite::try_from(u8::from(suite)).unwrap(), suite);
+
+            let (private_key, public_key) = suite.generate_signing_key().unwrap();
+            let signing_key = SigningKey::from_pkcs8_der(suite, &private_key).unwrap();
+            let verifying_key = VerifyingKey::from_public_key_der(suite, &public_key).unwrap();
+            let signature = signing_key.sign(b"hello");
+            assert!(verifying_key.verify(b"hello", &signature).is_ok());
+            assert!(verifying_key.verify(b"world", &signature).is_err());
+
+            let (private_key, public_key) = suite.generate_encryption_key();
+            let ciphertext = encrypt(suite, &public_key, b"hello").unwrap();
+            assert_eq!(decrypt(suite, &private_key, &ciphertext).unwrap(), b"hello");
+            let (other_key, _) = suite.generate_encryption_key();
+            assert!(decrypt(suite, &other_key, &ciphertext).is_err());
+        }
+        assert!(CipherSuite::try_from(2).is_err());
+    }
+}
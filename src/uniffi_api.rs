@@ -0,0 +1,340 @@
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::auth;
+use crate::error::Error;
+#[cfg(feature = "elgamal")]
+use crate::protocol::elgamal;
+use crate::protocol::Recipient as ProtocolRecipient;
+use crate::security::{
+    derive_state_key, verify_receipt as verify_transcript_receipt,
+    AbortReason as SecureLayerAbortReason, ProtocolType,
+};
+use crate::session::Session;
+use crate::suite::CipherSuite as AuthCipherSuite;
+
+type FfiResult<T> = std::result::Result<T, MeeSignError>;
+
+/// The classified errors of this library, as raised in Kotlin and Swift
+#[derive(Debug)]
+pub enum MeeSignError {
+    Misuse {
+        message: String,
+    },
+    MalformedInput {
+        message: String,
+    },
+    Crypto {
+        message: String,
+        culprit: Option<u32>,
+    },
+    Card {
+        message: String,
+        status: u16,
+    },
+    Serialization {
+        message: String,
+    },
+    Aborted {
+        message: String,
+        party: u32,
+        declined: bool,
+    },
+    Internal {
+        message: String,
+    },
+}
+
+impl From<Error> for MeeSignError {
+    fn from(error: Error) -> Self {
+        let message = error.to_string();
+        match error {
+            Error::Misuse(_) => MeeSignError::Misuse { message },
+            Error::MalformedInput(_) => MeeSignError::MalformedInput { message },
+            Error::Crypto { culprit, .. } => MeeSignError::Crypto { message, culprit },
+            Error::Card { status } => MeeSignError::Card { message, status },
+            Error::Serialization(_) => MeeSignError::Serialization { message },
+            Error::Aborted { party, declined } => MeeSignError::Aborted {
+                message,
+                party,
+                declined,
+            },
+            Error::Panic(_) => MeeSignError::Internal { message },
+        }
+    }
+}
+
+impl fmt::Display for MeeSignError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeeSignError::Misuse { message }
+            | MeeSignError::MalformedInput { message }
+            | MeeSignError::Crypto { message, .. }
+            | MeeSignError::Card { message, .. }
+            | MeeSignError::Serialization { message }
+            | MeeSignError::Aborted { message, .. }
+            | MeeSignError::Internal { message } => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for MeeSignError {}
+
+#[derive(Clone, Copy)]
+pub enum ProtocolId {
+    Gg18,
+    Elgamal,
+    Frost,
+    Musig2,
+}
+
+impl From<ProtocolId> for ProtocolType {
+    fn from(pid: ProtocolId) -> Self {
+        match pid {
+            ProtocolId::Gg18 => ProtocolType::Gg18,
+            ProtocolId::Elgamal => ProtocolType::Elgamal,
+            ProtocolId::Frost => ProtocolType::Frost,
+            ProtocolId::Musig2 => ProtocolType::Musig2,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum AbortReason {
+    Error,
+    Declined,
+}
+
+impl From<AbortReason> for SecureLayerAbortReason {
+    fn from(reason: AbortReason) -> Self {
+        match reason {
+            AbortReason::Error => SecureLayerAbortReason::Error,
+            AbortReason::Declined => SecureLayerAbortReason::Declined,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum CipherSuite {
+    P256Ecies,
+    Ed25519X25519,
+}
+
+impl From<CipherSuite> for AuthCipherSuite {
+    fn from(suite: CipherSuite) -> Self {
+        match suite {
+            CipherSuite::P256Ecies => AuthCipherSuite::P256Ecies,
+            CipherSuite::Ed25519X25519 => AuthCipherSuite::Ed25519X25519,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Recipient {
+    Card,
+    Server,
+}
+
+impl From<ProtocolRecipient> for Recipient {
+    fn from(recipient: ProtocolRecipient) -> Self {
+        match recipient {
+            ProtocolRecipient::Card => Recipient::Card,
+            ProtocolRecipient::Server => Recipient::Server,
+        }
+    }
+}
+
+pub struct AuthKey {
+    pub key: Vec<u8>,
+    pub csr: Vec<u8>,
+}
+
+/// A message for the server or a card, depending on its `recipient`
+pub struct Response {
+    pub data: Vec<u8>,
+    pub recipient: Recipient,
+}
+
+/// Where the computation of one share stands
+pub struct ShareStatus {
+    pub state: String,
+    pub aborted_by: Option<u32>,
+    pub round: String,
+    pub rounds_done: u16,
+    pub rounds_total: u16,
+    pub recipient: Option<Recipient>,
+    pub finished: bool,
+}
+
+pub struct ProtocolResult {
+    pub results: Vec<Vec<u8>>,
+    pub receipts: Vec<Vec<u8>>,
+}
+
+/// A protocol session computing one or more shares of a participant
+pub struct Protocol {
+    /// Taken by `finish`
+    inner: Mutex<Option<Session>>,
+}
+
+impl Protocol {
+    fn wrap(inner: Session) -> Arc<Self> {
+        Arc::new(Protocol {
+            inner: Mutex::new(Some(inner)),
+        })
+    }
+
+    fn lock(&self) -> FfiResult<MutexGuard<'_, Option<Session>>> {
+        self.inner.lock().map_err(|_| MeeSignError::Internal {
+            message: "protocol state poisoned by a panic".into(),
+        })
+    }
+
+    /// Runs `f` on the secure layer, unless the protocol has finished
+    fn with<T>(&self, f: impl FnOnce(&mut Session) -> FfiResult<T>) -> FfiResult<T> {
+        match self.lock()?.as_mut() {
+            Some(inner) => f(inner),
+            None => Err(Error::misuse("protocol already finished").into()),
+        }
+    }
+
+    pub fn keygen(
+        protocol: ProtocolId,
+        certs: Vec<u8>,
+        pkcs12: Vec<u8>,
+        shares: u32,
+        with_card: bool,
+    ) -> FfiResult<Arc<Self>> {
+        let inner = Session::keygen(protocol.into())
+            .certs(certs)
+            .identity(pkcs12)
+            .shares(shares as usize)
+            .with_card(with_card)
+            .build()?;
+        Ok(Protocol::wrap(inner))
+    }
+
+    /// Starts a threshold protocol computing the `group` shares output by a keygen.
+    /// The protocol is detected from the group, and checked against `protocol` if given
+    pub fn init(
+        group: Vec<Vec<u8>>,
+        certs: Vec<u8>,
+        pkcs12: Vec<u8>,
+        protocol: Option<ProtocolId>,
+    ) -> FfiResult<Arc<Self>> {
+        let mut builder = Session::threshold(group).certs(certs).identity(pkcs12);
+        if let Some(protocol) = protocol {
+            builder = builder.protocol(protocol.into());
+        }
+        Ok(Protocol::wrap(builder.build()?))
+    }
+
+    pub fn deserialize(data: Vec<u8>) -> FfiResult<Arc<Self>> {
+        Ok(Protocol::wrap(Session::from_bytes(&data)?))
+    }
+
+    /// Restores a protocol sealed with `seal`
+    pub fn unseal(sealed: Vec<u8>, key: Vec<u8>) -> FfiResult<Arc<Self>> {
+        Ok(Protocol::wrap(Session::unseal(&sealed, &key)?))
+    }
+
+    pub fn serialize(&self) -> FfiResult<Vec<u8>> {
+        self.with(|inner| Ok(inner.to_bytes()?))
+    }
+
+    /// Serializes the protocol encrypted under `key`, see `state_key`
+    pub fn seal(&self, key: Vec<u8>) -> FfiResult<Vec<u8>> {
+        self.with(|inner| Ok(inner.seal(&key)?))
+    }
+
+    /// Advances share `index` with a message from the server or a card.
+    /// A share which fails a cryptographic check is aborted and its abort message
+    /// can then be obtained from `abort`
+    pub fn advance(&self, index: u32, data: Vec<u8>) -> FfiResult<Response> {
+        self.with(|inner| {
+            let index = share_index(inner, index)?;
+            let response = inner.advance(index, &data)?;
+            Ok(Response {
+                data: response.data,
+                recipient: response.recipient.into(),
+            })
+        })
+    }
+
+    /// Aborts share `index` and returns the abort message for the server
+    pub fn abort(&self, index: u32, reason: AbortReason) -> FfiResult<Vec<u8>> {
+        self.with(|inner| {
+            let index = share_index(inner, index)?;
+            Ok(inner.abort(index, reason.into())?)
+        })
+    }
+
+    pub fn share_count(&self) -> FfiResult<u32> {
+        self.with(|inner| Ok(inner.share_count() as u32))
+    }
+
+    pub fn status(&self, index: u32) -> FfiResult<ShareStatus> {
+        self.with(|inner| {
+            let status = inner.status(index as usize)?;
+            Ok(ShareStatus {
+                state: format!("{:?}", status.state),
+                aborted_by: status.aborted_by,
+                round: status.round.into(),
+                rounds_done: status.rounds_done,
+                rounds_total: status.rounds_total,
+                recipient: status.recipient.map(Into::into),
+                finished: status.finished,
+            })
+        })
+    }
+
+    /// Finishes the protocol and returns the results of all shares
+    /// together with the signed receipts of their transcripts
+    pub fn finish(&self) -> FfiResult<ProtocolResult> {
+        let inner = self
+            .lock()?
+            .take()
+            .ok_or_else(|| Error::misuse("protocol already finished"))?;
+        let output = inner.finish_with_receipts()?;
+        Ok(ProtocolResult {
+            results: output.results,
+            receipts: output.receipts,
+        })
+    }
+}
+
+fn share_index(inner: &Session, index: u32) -> FfiResult<usize> {
+    let index = index as usize;
+    if index >= inner.share_count() {
+        return Err(Error::misuse(format!("no share {index}")).into());
+    }
+    Ok(index)
+}
+
+pub fn auth_keygen(name: String, suite: CipherSuite, hybrid: bool) -> FfiResult<AuthKey> {
+    let (key, csr) = auth::gen_key_with_csr_for_suite(&name, suite.into(), hybrid)?;
+    Ok(AuthKey { key, csr })
+}
+
+pub fn auth_cert_key_to_pkcs12(key: Vec<u8>, cert: Vec<u8>) -> FfiResult<Vec<u8>> {
+    Ok(auth::cert_key_to_pkcs12(&key, &cert)?)
+}
+
+/// Derives a key for sealing protocol states from the private bundle in `pkcs12`.
+/// NOTE: the returned copy is not wiped by the bindings, the caller should overwrite it when done
+pub fn state_key(pkcs12: Vec<u8>) -> FfiResult<Vec<u8>> {
+    Ok(derive_state_key(&pkcs12)?.to_vec())
+}
+
+/// Verifies a transcript receipt against the certificate of its issuer
+/// and returns the transcript digest
+pub fn verify_receipt(receipt: Vec<u8>, cert: Vec<u8>) -> FfiResult<Vec<u8>> {
+    Ok(verify_transcript_receipt(&receipt, &cert)?)
+}
+
+pub fn encrypt(msg: Vec<u8>, key: Vec<u8>) -> FfiResult<Vec<u8>> {
+    #[cfg(feature = "elgamal")]
+    return Ok(elgamal::encrypt(&msg, &key)?);
+    #[cfg(not(feature = "elgamal"))]
+    return Err(Error::misuse("encryption not supported").into());
+}
@@ -1,3 +1,4 @@
+use crate::error::Error;
 use crate::proto::{ClientMessage, ProtocolType};
 use prost::Message as _;
 use serde::{Deserialize, Serialize};
@@ -47,16 +48,19 @@ impl Message {
                 protocol_type: protocol_type.into(),
                 unicasts: data,
                 broadcast: None,
+                abort: None,
             },
             Self::Broadcast(data) => ClientMessage {
                 protocol_type: protocol_type.into(),
                 unicasts: HashMap::new(),
                 broadcast: Some(data),
+                abort: None,
             },
             Self::ReliableBroadcast(data) => ClientMessage {
                 protocol_type: protocol_type.into(),
                 unicasts: HashMap::new(),
                 broadcast: Some(data),
+                abort: None,
             },
             Self::CardCommand(_) => unreachable!(),
         }
@@ -66,11 +70,38 @@ impl Message {
     }
 }
 
+/// Maximum length of a JSON value received from other parties
+pub const MAX_JSON_LEN: usize = 1 << 20;
+
+/// Deserializes a JSON value received from other parties
+pub fn deserialize<'de, T: Deserialize<'de>>(data: &'de [u8]) -> Result<T, Error> {
+    // NOTE: `serde_json` already limits the nesting of values, so only the length is checked
+    if data.len() > MAX_JSON_LEN {
+        return Err(Error::malformed("JSON value too long"));
+    }
+    serde_json::from_slice(data).map_err(|error| Error::malformed(error.to_string()))
+}
+
 /// Deserializes values in a `HashMap`
 pub fn deserialize_map<'de, T: Deserialize<'de>>(
     map: &'de HashMap<u32, Vec<u8>>,
-) -> serde_json::Result<HashMap<u32, T>> {
+) -> Result<HashMap<u32, T>, Error> {
     map.iter()
-        .map(|(k, v)| Ok((*k, serde_json::from_slice::<T>(v.as_slice())?)))
+        .map(|(k, v)| Ok((*k, deserialize::<T>(v.as_slice())?)))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_length_is_limited() {
+        let value = |len| format!("\"{}\"", "a".repeat(len - 2));
+        assert!(deserialize::<String>(value(MAX_JSON_LEN).as_bytes()).is_ok());
+        assert!(deserialize::<String>(value(MAX_JSON_LEN + 1).as_bytes()).is_err());
+
+        let nested = format!("{}{}", "[".repeat(1000), "]".repeat(1000));
+        assert!(deserialize::<serde_json::Value>(nested.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,374 @@
+use wasm_bindgen::prelude::*;
+
+use crate::auth;
+use crate::error::Error;
+#[cfg(feature = "elgamal")]
+use crate::protocol::elgamal;
+use crate::security::{
+    derive_state_key, verify_receipt as verify_transcript_receipt,
+    AbortReason as SecureLayerAbortReason, ProtocolType,
+};
+use crate::session::Session;
+use crate::suite::CipherSuite as AuthCipherSuite;
+
+type JsResult<T> = std::result::Result<T, JsValue>;
+
+/// Converts `error` into a JS `Error` named after its classification.
+/// The party which caused or aborted the protocol is attached as `party` if it is known
+fn js_error(error: Error) -> JsValue {
+    let (name, party) = match error {
+        Error::Misuse(_) => ("MisuseError", None),
+        Error::MalformedInput(_) => ("MalformedInputError", None),
+        Error::Crypto { culprit, .. } => ("CryptoError", culprit),
+        Error::Card { .. } => ("CardError", None),
+        Error::Serialization(_) => ("SerializationError", None),
+        Error::Aborted { party, .. } => ("AbortedError", Some(party)),
+        Error::Panic(_) => ("InternalError", None),
+    };
+    let js_error = js_sys::Error::new(&error.to_string());
+    js_error.set_name(name);
+    if let Some(party) = party {
+        // NOTE: defining a property of a fresh error cannot fail
+        let _ = js_sys::Reflect::set(&js_error, &"party".into(), &party.into());
+    }
+    js_error.into()
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum ProtocolId {
+    Gg18,
+    Elgamal,
+    Frost,
+    Musig2,
+}
+
+impl From<ProtocolId> for ProtocolType {
+    fn from(pid: ProtocolId) -> Self {
+        match pid {
+            ProtocolId::Gg18 => ProtocolType::Gg18,
+            ProtocolId::Elgamal => ProtocolType::Elgamal,
+            ProtocolId::Frost => ProtocolType::Frost,
+            ProtocolId::Musig2 => ProtocolType::Musig2,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum AbortReason {
+    Error,
+    Declined,
+}
+
+impl From<AbortReason> for SecureLayerAbortReason {
+    fn from(reason: AbortReason) -> Self {
+        match reason {
+            AbortReason::Error => SecureLayerAbortReason::Error,
+            AbortReason::Declined => SecureLayerAbortReason::Declined,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum CipherSuite {
+    P256Ecies,
+    Ed25519X25519,
+}
+
+impl From<CipherSuite> for AuthCipherSuite {
+    fn from(suite: CipherSuite) -> Self {
+        match suite {
+            CipherSuite::P256Ecies => AuthCipherSuite::P256Ecies,
+            CipherSuite::Ed25519X25519 => AuthCipherSuite::Ed25519X25519,
+        }
+    }
+}
+
+/// Where the computation of one share stands
+#[wasm_bindgen]
+pub struct ShareStatus {
+    state: String,
+    aborted_by: Option<u32>,
+    round: String,
+    rounds_done: u16,
+    rounds_total: u16,
+    finished: bool,
+}
+
+#[wasm_bindgen]
+impl ShareStatus {
+    /// The name of the state of the secure layer, e.g. `Running`
+    #[wasm_bindgen(getter)]
+    pub fn state(&self) -> String {
+        self.state.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = abortedBy)]
+    pub fn aborted_by(&self) -> Option<u32> {
+        self.aborted_by
+    }
+
+    /// The name of the protocol round, e.g. `R11AwaitCommitmentResp`
+    #[wasm_bindgen(getter)]
+    pub fn round(&self) -> String {
+        self.round.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = roundsDone)]
+    pub fn rounds_done(&self) -> u16 {
+        self.rounds_done
+    }
+
+    #[wasm_bindgen(getter, js_name = roundsTotal)]
+    pub fn rounds_total(&self) -> u16 {
+        self.rounds_total
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+}
+
+#[wasm_bindgen]
+pub struct ProtocolResult {
+    results: Vec<u8>,
+    receipts: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl ProtocolResult {
+    #[wasm_bindgen(getter)]
+    pub fn results(&self) -> Vec<u8> {
+        self.results.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn receipts(&self) -> Vec<u8> {
+        self.receipts.clone()
+    }
+}
+
+/// A protocol session computing one or more shares of a participant.
+/// Browsers cannot reach smart cards, so all responses go to the server
+#[wasm_bindgen]
+pub struct Protocol {
+    inner: Session,
+}
+
+#[wasm_bindgen]
+impl Protocol {
+    pub fn keygen(
+        protocol: ProtocolId,
+        certs: &[u8],
+        pkcs12: &[u8],
+        shares: usize,
+    ) -> JsResult<Protocol> {
+        let inner = Session::keygen(protocol.into())
+            .certs(certs)
+            .identity(pkcs12)
+            .shares(shares)
+            .build()
+            .map_err(js_error)?;
+        Ok(Protocol { inner })
+    }
+
+    /// Starts a threshold protocol with the first `shares` shares of `group`,
+    /// which has to belong to `protocol`
+    pub fn init(
+        protocol: ProtocolId,
+        group: &[u8],
+        certs: &[u8],
+        pkcs12: &[u8],
+        shares: usize,
+    ) -> JsResult<Protocol> {
+        let group: Vec<Vec<u8>> =
+            serde_json::from_slice(group).map_err(|error| js_error(error.into()))?;
+        let group = group
+            .get(..shares)
+            .ok_or_else(|| js_error(Error::malformed("group holds fewer shares than requested")))?;
+        let inner = Session::threshold(group.to_vec())
+            .certs(certs)
+            .identity(pkcs12)
+            .protocol(protocol.into())
+            .build()
+            .map_err(js_error)?;
+        Ok(Protocol { inner })
+    }
+
+    pub fn serialize(&self) -> JsResult<Vec<u8>> {
+        self.inner.to_bytes().map_err(js_error)
+    }
+
+    pub fn deserialize(data: &[u8]) -> JsResult<Protocol> {
+        let inner = Session::from_bytes(data).map_err(js_error)?;
+        Ok(Protocol { inner })
+    }
+
+    /// Serializes the protocol encrypted under `key`, see `stateKey`
+    #[wasm_bindgen(js_name = serializeSealed)]
+    pub fn serialize_sealed(&self, key: &[u8]) -> JsResult<Vec<u8>> {
+        self.inner.seal(key).map_err(js_error)
+    }
+
+    #[wasm_bindgen(js_name = deserializeSealed)]
+    pub fn deserialize_sealed(sealed: &[u8], key: &[u8]) -> JsResult<Protocol> {
+        let inner = Session::unseal(sealed, key).map_err(js_error)?;
+        Ok(Protocol { inner })
+    }
+
+    /// Advances share `index` with a message from the server and returns the response.
+    /// A share which fails a cryptographic check is aborted and its abort message
+    /// can then be obtained from `abort`
+    pub fn advance(&mut self, index: usize, data: &[u8]) -> JsResult<Vec<u8>> {
+        self.check_share(index)?;
+        let response = self.inner.advance(index, data).map_err(js_error)?;
+        Ok(response.data)
+    }
+
+    /// Aborts share `index` and returns the abort message for the server
+    pub fn abort(&mut self, index: usize, reason: AbortReason) -> JsResult<Vec<u8>> {
+        self.check_share(index)?;
+        self.inner.abort(index, reason.into()).map_err(js_error)
+    }
+
+    #[wasm_bindgen(js_name = shareCount)]
+    pub fn share_count(&self) -> usize {
+        self.inner.share_count()
+    }
+
+    pub fn status(&self, index: usize) -> JsResult<ShareStatus> {
+        let status = self.inner.status(index).map_err(js_error)?;
+        Ok(ShareStatus {
+            state: format!("{:?}", status.state),
+            aborted_by: status.aborted_by,
+            round: status.round.into(),
+            rounds_done: status.rounds_done,
+            rounds_total: status.rounds_total,
+            finished: status.finished,
+        })
+    }
+
+    /// Finishes the protocol and returns the JSON list of the results of all shares
+    pub fn finish(self) -> JsResult<Vec<u8>> {
+        let results = self.inner.finish().map_err(js_error)?;
+        serde_json::to_vec(&results).map_err(|error| js_error(error.into()))
+    }
+
+    /// Finishes the protocol like `finish`, additionally returning
+    /// the signed transcript receipts of all shares
+    #[wasm_bindgen(js_name = finishWithReceipts)]
+    pub fn finish_with_receipts(self) -> JsResult<ProtocolResult> {
+        let output = self.inner.finish_with_receipts().map_err(js_error)?;
+        Ok(ProtocolResult {
+            results: serde_json::to_vec(&output.results).map_err(|error| js_error(error.into()))?,
+            receipts: serde_json::to_vec(&output.receipts)
+                .map_err(|error| js_error(error.into()))?,
+        })
+    }
+}
+
+impl Protocol {
+    fn check_share(&self, index: usize) -> JsResult<()> {
+        if index >= self.inner.share_count() {
+            return Err(js_error(Error::misuse(format!("no share {index}"))));
+        }
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+pub struct AuthKey {
+    key: Vec<u8>,
+    csr: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl AuthKey {
+    #[wasm_bindgen(getter)]
+    pub fn key(&self) -> Vec<u8> {
+        self.key.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn csr(&self) -> Vec<u8> {
+        self.csr.clone()
+    }
+}
+
+#[wasm_bindgen(js_name = authKeygen)]
+pub fn auth_keygen(name: &str) -> JsResult<AuthKey> {
+    let (key, csr) = auth::gen_key_with_csr(name).map_err(js_error)?;
+    Ok(AuthKey { key, csr })
+}
+
+#[wasm_bindgen(js_name = authKeygenWithSuite)]
+pub fn auth_keygen_with_suite(name: &str, suite: CipherSuite, hybrid: bool) -> JsResult<AuthKey> {
+    let (key, csr) =
+        auth::gen_key_with_csr_for_suite(name, suite.into(), hybrid).map_err(js_error)?;
+    Ok(AuthKey { key, csr })
+}
+
+#[wasm_bindgen(js_name = authCertKeyToPkcs12)]
+pub fn auth_cert_key_to_pkcs12(key: &[u8], cert: &[u8]) -> JsResult<Vec<u8>> {
+    auth::cert_key_to_pkcs12(key, cert).map_err(js_error)
+}
+
+/// Derives a key for sealing protocol states from the private bundle in `pkcs12`.
+/// NOTE: the returned copy is not wiped by the bindings, the caller should overwrite it when done
+#[wasm_bindgen(js_name = stateKey)]
+pub fn state_key(pkcs12: &[u8]) -> JsResult<Vec<u8>> {
+    Ok(derive_state_key(pkcs12).map_err(js_error)?.to_vec())
+}
+
+/// Verifies a transcript receipt against the certificate of its issuer
+/// and returns the transcript digest
+#[wasm_bindgen(js_name = verifyReceipt)]
+pub fn verify_receipt(receipt: &[u8], cert: &[u8]) -> JsResult<Vec<u8>> {
+    verify_transcript_receipt(receipt, cert).map_err(js_error)
+}
+
+#[cfg(feature = "elgamal")]
+#[wasm_bindgen]
+pub fn encrypt(msg: &[u8], key: &[u8]) -> JsResult<Vec<u8>> {
+    elgamal::encrypt(msg, key).map_err(js_error)
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn error_name(error: JsValue) -> String {
+        js_sys::Error::from(error).name().into()
+    }
+
+    #[wasm_bindgen_test]
+    fn auth_keygen_uses_browser_randomness() {
+        let first = auth_keygen("alice").unwrap();
+        let second = auth_keygen("alice").unwrap();
+        assert!(!first.csr().is_empty());
+        assert_ne!(first.key(), second.key());
+    }
+
+    #[wasm_bindgen_test]
+    fn errors_are_classified() {
+        let key = auth_keygen("alice").unwrap().key();
+        let error = Protocol::keygen(ProtocolId::Frost, b"\xff", &key, 1)
+            .err()
+            .unwrap();
+        assert_eq!(error_name(error), "MalformedInputError");
+
+        let error = Protocol::deserialize(b"{").err().unwrap();
+        assert_eq!(error_name(error), "SerializationError");
+    }
+
+    #[cfg(feature = "elgamal")]
+    #[wasm_bindgen_test]
+    fn encrypt_rejects_invalid_keys() {
+        let error = encrypt(b"hello", &[0xff; 32]).err().unwrap();
+        assert_eq!(error_name(error), "MalformedInputError");
+    }
+}